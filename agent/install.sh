@@ -149,8 +149,8 @@ heartbeat_interval_seconds = 30
 [features]
 # Include top process information
 collect_processes = true
-# Enable HTTP endpoint health checks
-http_health_checks = false
+# Enable agent-side HTTP, TCP, DNS and ping health checks
+health_checks = false
 EOF
         success "Example config created at ${CONFIG_DIR}/agent.toml"
     else
@@ -181,6 +181,8 @@ ProtectSystem=strict
 ProtectHome=true
 ReadOnlyPaths=/
 ReadWritePaths=${CONFIG_DIR}
+# Spool for metrics that couldn't be delivered (/var/lib/nubilus)
+StateDirectory=nubilus
 
 [Install]
 WantedBy=multi-user.target
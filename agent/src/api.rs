@@ -6,9 +6,10 @@ use std::time::Duration;
 use tracing::{debug, error, info, warn};
 
 use crate::config::Config;
-use crate::models::{MetricsPayload, RegisterRequest, RegisterResponse};
+use crate::models::{HealthCheckPayload, MetricsPayload, RegisterRequest, RegisterResponse, ServiceInventory};
 
 /// API client for Nubilus backend communication
+#[derive(Clone)]
 pub struct ApiClient {
     client: Client,
     base_url: String,
@@ -37,6 +38,19 @@ pub enum ApiError {
     Other(String),
 }
 
+impl ApiError {
+    /// Whether the request may succeed if retried later with the same payload
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            ApiError::NotRegistered
+                | ApiError::RateLimited
+                | ApiError::ServerError(_)
+                | ApiError::NetworkError(_)
+        )
+    }
+}
+
 impl ApiClient {
     /// Create a new API client from configuration
     pub fn new(config: &Config) -> Result<Self> {
@@ -126,6 +140,41 @@ impl ApiClient {
         self.handle_response(response, "submit metrics").await
     }
 
+    /// Submit the result of an agent-side health check
+    pub async fn submit_health_check(&self, result: &HealthCheckPayload) -> Result<(), ApiError> {
+        let url = format!("{}/ingest/health", self.base_url);
+
+        debug!("Submitting health check for endpoint {}: up={}",
+               result.endpoint_id, result.is_up);
+
+        let response = self
+            .client
+            .post(&url)
+            .header("X-API-Key", &self.api_key)
+            .json(result)
+            .send()
+            .await?;
+
+        self.handle_response(response, "submit health check").await
+    }
+
+    /// Replace the server's service inventory for the listed service types
+    pub async fn submit_services(&self, inventory: &ServiceInventory) -> Result<(), ApiError> {
+        let url = format!("{}/ingest/services", self.base_url);
+
+        debug!("Submitting {} discovered service(s)", inventory.services.len());
+
+        let response = self
+            .client
+            .post(&url)
+            .header("X-API-Key", &self.api_key)
+            .json(inventory)
+            .send()
+            .await?;
+
+        self.handle_response(response, "submit services").await
+    }
+
     /// Send a heartbeat to keep the server status active
     pub async fn heartbeat(&self) -> Result<(), ApiError> {
         let url = format!("{}/ingest/heartbeat", self.base_url);
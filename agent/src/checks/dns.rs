@@ -0,0 +1,621 @@
+//! DNS resolution health check
+//!
+//! Sends one recursive query straight to the resolver over UDP, sending it
+//! again once if no answer arrives within half the timeout, and retrying over
+//! TCP when the answer doesn't fit in a datagram. Going around the system's
+//! stub resolver means the check measures the configured resolver itself,
+//! without caching or fallback to other nameservers.
+
+use anyhow::{Context, Result};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream, UdpSocket};
+
+use crate::config::{DnsCheckConfig, DnsRecordType, HealthCheckConfig};
+use crate::models::HealthCheckPayload;
+
+/// Nameservers used when a check has no resolver configured
+const RESOLV_CONF: &str = "/etc/resolv.conf";
+
+/// Largest UDP response accepted
+const MAX_UDP_RESPONSE: usize = 4096;
+
+/// Run a single DNS check and build the payload to report
+///
+/// The check is down when the lookup fails, returns no records of the
+/// requested type, or returns answers other than the expected set.
+pub async fn run(check: &HealthCheckConfig, dns: &DnsCheckConfig, checked_from: &str) -> HealthCheckPayload {
+    let timeout = Duration::from_secs(check.timeout_seconds);
+
+    let start = Instant::now();
+    let result = tokio::time::timeout(timeout, lookup(dns, timeout / 2)).await;
+    let response_time = start.elapsed().as_secs_f64() * 1000.0;
+
+    let (answers, error_message) = match result {
+        Ok(Ok(answers)) => {
+            let message = compare_answers(dns, &answers);
+            (answers, message)
+        }
+        Ok(Err(e)) => (Vec::new(), Some(format!("{:#}", e))),
+        Err(_) => (Vec::new(), Some(format!("Timeout after {}s", check.timeout_seconds))),
+    };
+
+    HealthCheckPayload {
+        endpoint_id: check.endpoint_id.clone(),
+        status_code: None,
+        response_time,
+        is_up: error_message.is_none(),
+        error_message,
+        checked_from: checked_from.to_string(),
+        answers,
+        ping: None,
+    }
+}
+
+/// Query the resolver and return the answers of the requested type, sorted
+///
+/// The UDP query is sent again if there's no answer after `retry_after`.
+async fn lookup(check: &DnsCheckConfig, retry_after: Duration) -> Result<Vec<String>> {
+    let resolver = match check.resolver_addr() {
+        Some(resolver) => resolver?,
+        None => system_resolver()?,
+    };
+    // Random, so answers meant for someone else aren't taken for ours
+    let random = uuid::Uuid::new_v4();
+    let id = u16::from_be_bytes([random.as_bytes()[0], random.as_bytes()[1]]);
+    let query = build_query(id, &check.record, check.record_type)?;
+
+    let mut response = query_udp(resolver, id, &query, retry_after)
+        .await
+        .with_context(|| format!("Failed to query {}", resolver))?;
+    if is_truncated(&response) {
+        response = query_tcp(resolver, &query)
+            .await
+            .with_context(|| format!("Failed to query {} over TCP", resolver))?;
+    }
+
+    let mut answers = parse_response(&response, id, check.record_type)?;
+    answers.sort();
+    answers.dedup();
+    Ok(answers)
+}
+
+/// First usable nameserver from /etc/resolv.conf
+fn system_resolver() -> Result<SocketAddr> {
+    let content =
+        std::fs::read_to_string(RESOLV_CONF).with_context(|| format!("Failed to read {}", RESOLV_CONF))?;
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            (fields.next() == Some("nameserver")).then(|| fields.next()).flatten()
+        })
+        // Skips scoped IPv6 addresses ("fe80::1%eth0"), which need an interface
+        .find_map(|address| address.parse::<IpAddr>().ok())
+        .map(|ip| SocketAddr::new(ip, 53))
+        .with_context(|| format!("No nameserver in {}", RESOLV_CONF))
+}
+
+async fn query_udp(resolver: SocketAddr, id: u16, query: &[u8], retry_after: Duration) -> Result<Vec<u8>> {
+    let local: SocketAddr = if resolver.is_ipv4() {
+        (Ipv4Addr::UNSPECIFIED, 0).into()
+    } else {
+        (Ipv6Addr::UNSPECIFIED, 0).into()
+    };
+    let socket = UdpSocket::bind(local).await?;
+    socket.connect(resolver).await?;
+    socket.send(query).await?;
+
+    match tokio::time::timeout(retry_after, receive(&socket, id)).await {
+        Ok(response) => response,
+        // The query or its answer was lost on the way
+        Err(_) => {
+            socket.send(query).await?;
+            receive(&socket, id).await
+        }
+    }
+}
+
+/// Wait for the answer to the query with the given ID
+async fn receive(socket: &UdpSocket, id: u16) -> Result<Vec<u8>> {
+    let mut buf = vec![0u8; MAX_UDP_RESPONSE];
+    loop {
+        let len = socket.recv(&mut buf).await?;
+        // Late answers to an earlier query are ignored
+        if len >= 2 && buf[..2] == id.to_be_bytes() {
+            buf.truncate(len);
+            return Ok(buf);
+        }
+    }
+}
+
+/// Send the query over TCP, where messages are prefixed with their length
+async fn query_tcp(resolver: SocketAddr, query: &[u8]) -> Result<Vec<u8>> {
+    let mut stream = TcpStream::connect(resolver).await?;
+    let mut message = (query.len() as u16).to_be_bytes().to_vec();
+    message.extend_from_slice(query);
+    stream.write_all(&message).await?;
+
+    let len = stream.read_u16().await? as usize;
+    let mut response = vec![0u8; len];
+    stream.read_exact(&mut response).await?;
+    Ok(response)
+}
+
+/// Type code of a record type on the wire
+fn type_code(record_type: DnsRecordType) -> u16 {
+    match record_type {
+        DnsRecordType::A => 1,
+        DnsRecordType::Cname => 5,
+        DnsRecordType::Mx => 15,
+        DnsRecordType::Txt => 16,
+        DnsRecordType::Aaaa => 28,
+    }
+}
+
+/// Build a recursive query for one record
+fn build_query(id: u16, name: &str, record_type: DnsRecordType) -> Result<Vec<u8>> {
+    let mut query = Vec::with_capacity(32 + name.len());
+    query.extend_from_slice(&id.to_be_bytes());
+    // Recursion desired
+    query.extend_from_slice(&0x0100u16.to_be_bytes());
+    // One question, no answer, authority or additional records
+    query.extend_from_slice(&[0, 1, 0, 0, 0, 0, 0, 0]);
+
+    let name = name.trim_end_matches('.');
+    if name.len() > 253 {
+        anyhow::bail!("Name is too long: {}", name);
+    }
+    for label in name.split('.') {
+        if label.is_empty() || label.len() > 63 {
+            anyhow::bail!("Invalid name: {}", name);
+        }
+        query.push(label.len() as u8);
+        query.extend_from_slice(label.as_bytes());
+    }
+    query.push(0);
+
+    query.extend_from_slice(&type_code(record_type).to_be_bytes());
+    // Class IN
+    query.extend_from_slice(&1u16.to_be_bytes());
+    Ok(query)
+}
+
+/// Whether the resolver cut the answer short to fit it in a datagram
+fn is_truncated(response: &[u8]) -> bool {
+    response.len() >= 4 && response[2] & 0x02 != 0
+}
+
+/// Cursor over a DNS message
+struct Reader<'a> {
+    message: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        let bytes = self
+            .message
+            .get(self.pos..self.pos + len)
+            .context("Truncated DNS response")?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    /// Read a possibly compressed name, without the trailing dot
+    fn name(&mut self) -> Result<String> {
+        let mut labels = Vec::new();
+        let mut pos = self.pos;
+        // Where reading continues once the name is done, set at the first pointer
+        let mut resume = None;
+
+        for _ in 0..128 {
+            let len = *self.message.get(pos).context("Truncated DNS response")? as usize;
+            match len {
+                0 => {
+                    self.pos = resume.unwrap_or(pos + 1);
+                    return Ok(labels.join("."));
+                }
+                len if len & 0xC0 == 0xC0 => {
+                    let low = *self.message.get(pos + 1).context("Truncated DNS response")? as usize;
+                    resume.get_or_insert(pos + 2);
+                    pos = (len & 0x3F) << 8 | low;
+                }
+                len => {
+                    let label = self
+                        .message
+                        .get(pos + 1..pos + 1 + len)
+                        .context("Truncated DNS response")?;
+                    labels.push(String::from_utf8_lossy(label).into_owned());
+                    pos += 1 + len;
+                }
+            }
+        }
+        anyhow::bail!("Invalid name compression in DNS response")
+    }
+}
+
+/// Extract the answers of the requested type from a response
+///
+/// Other records, like the CNAMEs followed on the way to an address, are
+/// skipped.
+fn parse_response(response: &[u8], id: u16, record_type: DnsRecordType) -> Result<Vec<String>> {
+    let mut reader = Reader {
+        message: response,
+        pos: 0,
+    };
+    if reader.u16()? != id {
+        anyhow::bail!("DNS response has the wrong ID");
+    }
+    let flags = reader.u16()?;
+    if flags & 0x8000 == 0 {
+        anyhow::bail!("Resolver sent a query instead of a response");
+    }
+    match flags & 0x000F {
+        0 => {}
+        2 => anyhow::bail!("Resolver failed (SERVFAIL)"),
+        3 => anyhow::bail!("Name does not exist (NXDOMAIN)"),
+        5 => anyhow::bail!("Resolver refused the query (REFUSED)"),
+        code => anyhow::bail!("Resolver returned error code {}", code),
+    }
+    let questions = reader.u16()?;
+    let answers = reader.u16()?;
+    // Authority and additional records aren't needed
+    reader.bytes(4)?;
+
+    for _ in 0..questions {
+        reader.name()?;
+        // Type and class
+        reader.bytes(4)?;
+    }
+
+    let wanted = type_code(record_type);
+    let mut records = Vec::new();
+    for _ in 0..answers {
+        reader.name()?;
+        let rtype = reader.u16()?;
+        // Class and TTL
+        reader.bytes(6)?;
+        let len = reader.u16()? as usize;
+        let end = reader.pos + len;
+        if rtype == wanted {
+            records.push(parse_record(&mut reader, record_type, len)?);
+        }
+        reader.pos = end;
+    }
+    Ok(records)
+}
+
+/// Format the data of a record the way it's written in zone files
+fn parse_record(reader: &mut Reader, record_type: DnsRecordType, len: usize) -> Result<String> {
+    Ok(match record_type {
+        DnsRecordType::A => {
+            let octets: [u8; 4] = reader.bytes(len)?.try_into().context("Invalid A record")?;
+            Ipv4Addr::from(octets).to_string()
+        }
+        DnsRecordType::Aaaa => {
+            let octets: [u8; 16] = reader.bytes(len)?.try_into().context("Invalid AAAA record")?;
+            Ipv6Addr::from(octets).to_string()
+        }
+        DnsRecordType::Cname => reader.name()?,
+        DnsRecordType::Mx => {
+            let preference = reader.u16()?;
+            format!("{} {}", preference, reader.name()?)
+        }
+        DnsRecordType::Txt => {
+            // One or more character strings, joined like most tools do
+            let end = reader.pos + len;
+            let mut text = String::new();
+            while reader.pos < end {
+                let len = reader.u8()? as usize;
+                text.push_str(&String::from_utf8_lossy(reader.bytes(len)?));
+            }
+            text
+        }
+    })
+}
+
+/// Check the answers against the expected set, returning why they don't match
+fn compare_answers(check: &DnsCheckConfig, answers: &[String]) -> Option<String> {
+    if answers.is_empty() {
+        return Some(format!("No {} records for {}", check.record_type, check.record));
+    }
+    if check.expected.is_empty() {
+        return None;
+    }
+
+    let normalized = |values: &[String]| {
+        let mut values: Vec<String> = values
+            .iter()
+            .map(|value| normalize(check.record_type, value))
+            .collect();
+        values.sort();
+        values.dedup();
+        values
+    };
+    if normalized(answers) == normalized(&check.expected) {
+        None
+    } else {
+        Some(format!(
+            "Expected {}, got {}",
+            check.expected.join(", "),
+            answers.join(", ")
+        ))
+    }
+}
+
+/// Bring an answer into a form that compares equal however it's written
+fn normalize(record_type: DnsRecordType, value: &str) -> String {
+    match record_type {
+        DnsRecordType::A | DnsRecordType::Aaaa => value
+            .parse::<IpAddr>()
+            .map(|ip| ip.to_string())
+            .unwrap_or_else(|_| value.to_string()),
+        // Names are case-insensitive and may be written fully qualified
+        DnsRecordType::Cname | DnsRecordType::Mx => value.trim_end_matches('.').to_lowercase(),
+        DnsRecordType::Txt => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CheckKind;
+    use tokio::net::TcpListener;
+
+    /// Pointer to the name in the question of a response
+    const QUESTION: &[u8] = &[0xC0, 12];
+
+    /// Fake resolver on a random port, sending back what `answer` returns for each query
+    async fn serve<F>(mut answer: F) -> SocketAddr
+    where
+        F: FnMut(&[u8]) -> Vec<Vec<u8>> + Send + 'static,
+    {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let address = socket.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buf = [0u8; 512];
+            while let Ok((len, peer)) = socket.recv_from(&mut buf).await {
+                for response in answer(&buf[..len]) {
+                    socket.send_to(&response, peer).await.unwrap();
+                }
+            }
+        });
+        address
+    }
+
+    /// Answer queries over TCP on the same port as the fake resolver
+    async fn serve_tcp(address: SocketAddr, answer: fn(&[u8]) -> Vec<u8>) {
+        let listener = TcpListener::bind(address).await.unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let len = stream.read_u16().await.unwrap() as usize;
+            let mut query = vec![0u8; len];
+            stream.read_exact(&mut query).await.unwrap();
+            let response = answer(&query);
+            stream.write_all(&(response.len() as u16).to_be_bytes()).await.unwrap();
+            stream.write_all(&response).await.unwrap();
+        });
+    }
+
+    /// Response to `query` with the given flags besides QR, RD and RA
+    fn response(query: &[u8], flags: u16, answers: &[Vec<u8>]) -> Vec<u8> {
+        let mut message = query[..2].to_vec();
+        message.extend_from_slice(&(0x8180 | flags).to_be_bytes());
+        message.extend_from_slice(&[0, 1]);
+        message.extend_from_slice(&(answers.len() as u16).to_be_bytes());
+        message.extend_from_slice(&[0, 0, 0, 0]);
+        // The question, as it was asked
+        message.extend_from_slice(&query[12..]);
+        for answer in answers {
+            message.extend_from_slice(answer);
+        }
+        message
+    }
+
+    /// Resource record with a name in wire format
+    fn record(name: &[u8], rtype: u16, data: &[u8]) -> Vec<u8> {
+        let mut record = name.to_vec();
+        record.extend_from_slice(&rtype.to_be_bytes());
+        // Class IN, TTL 300
+        record.extend_from_slice(&[0, 1, 0, 0, 1, 0x2C]);
+        record.extend_from_slice(&(data.len() as u16).to_be_bytes());
+        record.extend_from_slice(data);
+        record
+    }
+
+    /// Uncompressed name in wire format
+    fn name(name: &str) -> Vec<u8> {
+        let mut wire = Vec::new();
+        for label in name.split('.') {
+            wire.push(label.len() as u8);
+            wire.extend_from_slice(label.as_bytes());
+        }
+        wire.push(0);
+        wire
+    }
+
+    /// Type code the query asks for
+    fn query_type(query: &[u8]) -> u16 {
+        u16::from_be_bytes([query[query.len() - 4], query[query.len() - 3]])
+    }
+
+    /// Answers for example.com and www.example.com, a CNAME of web.example.com
+    fn example_zone(query: &[u8]) -> Vec<Vec<u8>> {
+        let www = query[13..16] == *b"www";
+        // Where "example.com" starts in the question
+        let domain = if www { 16 } else { 12 };
+        let web = [&[3][..], b"web", &[0xC0, domain]].concat();
+        let answers = match query_type(query) {
+            1 if www => {
+                // The CNAME's data starts after its name, type, class, TTL and length
+                let cname_target = (query.len() + 12) as u8;
+                vec![record(QUESTION, 5, &web), record(&[0xC0, cname_target], 1, &[10, 0, 0, 10])]
+            }
+            1 => vec![record(QUESTION, 1, &[10, 0, 0, 11]), record(QUESTION, 1, &[10, 0, 0, 10])],
+            5 => vec![record(QUESTION, 5, &web)],
+            15 => vec![
+                record(QUESTION, 15, &[&[0, 20][..], &name("mx2.example.com")].concat()),
+                record(QUESTION, 15, &[&[0, 10][..], &[2], b"mx", &[0xC0, domain]].concat()),
+            ],
+            16 => vec![record(QUESTION, 16, &[&[5][..], b"v=spf", &[8], b"1 -all x"].concat())],
+            28 => vec![record(QUESTION, 28, &"2001:db8::1".parse::<Ipv6Addr>().unwrap().octets())],
+            _ => Vec::new(),
+        };
+        vec![response(query, 0, &answers)]
+    }
+
+    async fn resolve(
+        resolver: SocketAddr,
+        record: &str,
+        record_type: DnsRecordType,
+        expected: &[&str],
+    ) -> HealthCheckPayload {
+        let dns = DnsCheckConfig {
+            record: record.to_string(),
+            record_type,
+            resolver: Some(resolver.to_string()),
+            expected: expected.iter().map(|answer| answer.to_string()).collect(),
+        };
+        let check = HealthCheckConfig {
+            endpoint_id: "dns".to_string(),
+            kind: CheckKind::Dns(dns.clone()),
+            timeout_seconds: 2,
+            interval_seconds: 60,
+        };
+        run(&check, &dns, "test").await
+    }
+
+    #[tokio::test]
+    async fn reads_each_record_type() {
+        let resolver = serve(example_zone).await;
+
+        let cases = [
+            (DnsRecordType::A, vec!["10.0.0.10", "10.0.0.11"]),
+            (DnsRecordType::Aaaa, vec!["2001:db8::1"]),
+            (DnsRecordType::Cname, vec!["web.example.com"]),
+            (DnsRecordType::Mx, vec!["10 mx.example.com", "20 mx2.example.com"]),
+            (DnsRecordType::Txt, vec!["v=spf1 -all x"]),
+        ];
+        for (record_type, answers) in cases {
+            let result = resolve(resolver, "example.com", record_type, &[]).await;
+            assert_eq!(result.error_message, None, "{}", record_type);
+            assert!(result.is_up);
+            assert_eq!(result.answers, answers, "{}", record_type);
+        }
+    }
+
+    #[tokio::test]
+    async fn follows_cnames_to_the_address() {
+        let resolver = serve(example_zone).await;
+
+        let result = resolve(resolver, "www.example.com", DnsRecordType::A, &[]).await;
+        assert_eq!(result.error_message, None);
+        assert_eq!(result.answers, ["10.0.0.10"]);
+
+        let result = resolve(resolver, "www.example.com", DnsRecordType::Cname, &[]).await;
+        assert_eq!(result.answers, ["web.example.com"]);
+    }
+
+    #[tokio::test]
+    async fn reports_missing_names() {
+        let resolver = serve(|query| vec![response(query, 3, &[])]).await;
+
+        let result = resolve(resolver, "missing.example.com", DnsRecordType::A, &[]).await;
+        assert!(!result.is_up);
+        assert_eq!(result.error_message.as_deref(), Some("Name does not exist (NXDOMAIN)"));
+
+        let resolver = serve(|query| vec![response(query, 0, &[])]).await;
+        let result = resolve(resolver, "example.com", DnsRecordType::Aaaa, &[]).await;
+        assert_eq!(result.error_message.as_deref(), Some("No AAAA records for example.com"));
+    }
+
+    #[tokio::test]
+    async fn ignores_answers_to_other_queries() {
+        let resolver = serve(|query| {
+            let mut stale = response(query, 0, &[record(QUESTION, 1, &[10, 0, 0, 99])]);
+            stale[1] ^= 0xFF;
+            vec![stale, response(query, 0, &[record(QUESTION, 1, &[10, 0, 0, 10])])]
+        })
+        .await;
+
+        let result = resolve(resolver, "example.com", DnsRecordType::A, &[]).await;
+        assert_eq!(result.error_message, None);
+        assert_eq!(result.answers, ["10.0.0.10"]);
+
+        let query = build_query(0x1234, "example.com", DnsRecordType::A).unwrap();
+        let error = parse_response(&response(&query, 0, &[]), 0x4321, DnsRecordType::A).unwrap_err();
+        assert_eq!(error.to_string(), "DNS response has the wrong ID");
+    }
+
+    #[tokio::test]
+    async fn retries_truncated_answers_over_tcp() {
+        let resolver = serve(|query| vec![response(query, 0x0200, &[])]).await;
+        serve_tcp(resolver, |query| {
+            let answers: Vec<Vec<u8>> = (1..=40).map(|host| record(QUESTION, 1, &[10, 0, 1, host])).collect();
+            response(query, 0, &answers)
+        })
+        .await;
+
+        let result = resolve(resolver, "example.com", DnsRecordType::A, &[]).await;
+        assert_eq!(result.error_message, None);
+        assert_eq!(result.answers.len(), 40);
+        assert!(result.answers.contains(&"10.0.1.40".to_string()));
+    }
+
+    #[tokio::test]
+    async fn sends_the_query_again_when_unanswered() {
+        let mut queries = 0;
+        let resolver = serve(move |query| {
+            queries += 1;
+            // The first query is lost
+            if queries == 1 {
+                Vec::new()
+            } else {
+                vec![response(query, 0, &[record(QUESTION, 1, &[10, 0, 0, 10])])]
+            }
+        })
+        .await;
+
+        let result = resolve(resolver, "example.com", DnsRecordType::A, &[]).await;
+        assert_eq!(result.error_message, None);
+        assert_eq!(result.answers, ["10.0.0.10"]);
+        // The query is sent again after half the timeout
+        assert!(result.response_time >= 1000.0, "{}", result.response_time);
+    }
+
+    #[tokio::test]
+    async fn times_out_without_answers() {
+        let resolver = serve(|_| Vec::new()).await;
+
+        let result = resolve(resolver, "example.com", DnsRecordType::A, &[]).await;
+        assert!(!result.is_up);
+        assert_eq!(result.error_message.as_deref(), Some("Timeout after 2s"));
+    }
+
+    #[tokio::test]
+    async fn compares_answers_with_the_expected_set() {
+        let resolver = serve(example_zone).await;
+
+        let result = resolve(resolver, "example.com", DnsRecordType::A, &["10.0.0.11", "10.0.0.10"]).await;
+        assert!(result.is_up);
+        let expected = ["20 mx2.example.com", "10 MX.example.com."];
+        let result = resolve(resolver, "example.com", DnsRecordType::Mx, &expected).await;
+        assert!(result.is_up);
+
+        let result = resolve(resolver, "example.com", DnsRecordType::A, &["10.0.0.10"]).await;
+        assert!(!result.is_up);
+        assert_eq!(result.error_message.as_deref(), Some("Expected 10.0.0.10, got 10.0.0.10, 10.0.0.11"));
+        assert_eq!(result.answers, ["10.0.0.10", "10.0.0.11"]);
+    }
+}
@@ -0,0 +1,70 @@
+//! HTTP endpoint health check
+
+use anyhow::{Context, Result};
+use reqwest::{Client, Method};
+use std::time::{Duration, Instant};
+
+use crate::config::{HealthCheckConfig, HttpCheckConfig};
+use crate::models::HealthCheckPayload;
+
+/// Build the HTTP client shared by all HTTP checks
+///
+/// Timeouts are applied per request, since every check has its own.
+pub fn build_client() -> Result<Client> {
+    Client::builder()
+        .user_agent(format!("nubilus-agent/{}", env!("CARGO_PKG_VERSION")))
+        .build()
+        .context("Failed to create health check HTTP client")
+}
+
+/// Run a single HTTP check and build the payload to report
+pub async fn run(
+    client: &Client,
+    check: &HealthCheckConfig,
+    http: &HttpCheckConfig,
+    checked_from: &str,
+) -> HealthCheckPayload {
+    // Method was validated when the config was loaded
+    let method = Method::from_bytes(http.method.to_uppercase().as_bytes()).unwrap_or(Method::GET);
+    let timeout = Duration::from_secs(check.timeout_seconds);
+
+    let start = Instant::now();
+    let result = client
+        .request(method, &http.url)
+        .timeout(timeout)
+        .send()
+        .await;
+    let response_time = start.elapsed().as_secs_f64() * 1000.0;
+
+    let (status_code, is_up, error_message) = match result {
+        Ok(response) => {
+            let status = response.status().as_u16();
+            if status == http.expected_status {
+                (Some(status), true, None)
+            } else {
+                (
+                    Some(status),
+                    false,
+                    Some(format!("Expected status {}, got {}", http.expected_status, status)),
+                )
+            }
+        }
+        Err(e) if e.is_timeout() => (
+            None,
+            false,
+            Some(format!("Timeout after {}s", check.timeout_seconds)),
+        ),
+        Err(e) => (None, false, Some(e.to_string())),
+    };
+
+    HealthCheckPayload {
+        endpoint_id: check.endpoint_id.clone(),
+        status_code,
+        response_time,
+        is_up,
+        error_message,
+        checked_from: checked_from.to_string(),
+        answers: Vec::new(),
+        ping: None,
+    }
+}
@@ -0,0 +1,26 @@
+//! Agent-side health checks
+//!
+//! These checks run on the monitored server itself, so they can reach
+//! internal services that the Nubilus backend cannot. Results are reported
+//! through the same `/ingest/health` route the backend scheduler uses.
+//! Process checks are part of the metrics payload and only go through that
+//! route when they are tied to an endpoint.
+
+pub mod dns;
+pub mod http;
+pub mod ping;
+pub mod process;
+pub mod tcp;
+
+use crate::config::{CheckKind, HealthCheckConfig};
+use crate::models::HealthCheckPayload;
+
+/// Run a single `[[health_checks]]` entry with the checker for its kind
+pub async fn run(http_client: &reqwest::Client, check: &HealthCheckConfig, checked_from: &str) -> HealthCheckPayload {
+    match &check.kind {
+        CheckKind::Http(settings) => http::run(http_client, check, settings, checked_from).await,
+        CheckKind::Tcp(settings) => tcp::run(check, settings, checked_from).await,
+        CheckKind::Dns(settings) => dns::run(check, settings, checked_from).await,
+        CheckKind::Ping(settings) => ping::run(check, settings, checked_from).await,
+    }
+}
@@ -0,0 +1,313 @@
+//! ICMP ping health check
+//!
+//! Uses Linux ICMP datagram sockets ("ping sockets"), so the agent doesn't
+//! need CAP_NET_RAW. The kernel fills in the echo identifier and checksum
+//! and only hands us the replies to our own requests. Ping sockets are
+//! allowed for the groups in the `net.ipv4.ping_group_range` sysctl (for
+//! IPv6 too), which most distributions set to cover every group.
+
+use anyhow::{Context, Result};
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+use tokio::net::UdpSocket;
+use tokio::time::{sleep_until, timeout_at};
+
+use crate::config::{HealthCheckConfig, PingCheckConfig};
+use crate::models::{HealthCheckPayload, PingStatistics};
+
+/// Time between echo requests, the same as `ping`
+const PACKET_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Bytes of data after the ICMP header, the same as `ping`
+const PAYLOAD_SIZE: usize = 56;
+
+/// Run a single ping check and build the payload to report
+///
+/// Echo requests are sent once per second, and replies are waited for
+/// until the check times out. The reported response time is the average
+/// round trip time.
+pub async fn run(check: &HealthCheckConfig, settings: &PingCheckConfig, checked_from: &str) -> HealthCheckPayload {
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(check.timeout_seconds);
+
+    let start = Instant::now();
+    let result = ping(settings, deadline).await;
+    let elapsed = start.elapsed().as_secs_f64() * 1000.0;
+
+    let (response_time, error_message, ping) = match result {
+        Ok((stats, last_error)) => {
+            let error_message = if stats.packets_received == 0 {
+                let mut message = format!("100% packet loss ({} packets sent)", stats.packets_sent);
+                if let Some(e) = last_error {
+                    message.push_str(&format!(": {}", e));
+                }
+                Some(message)
+            } else {
+                settings
+                    .max_packet_loss
+                    .filter(|&max| stats.packet_loss > max)
+                    .map(|max| format!("{:.0}% packet loss, at most {}% allowed", stats.packet_loss, max))
+            };
+            (stats.rtt_avg_ms.unwrap_or(elapsed), error_message, Some(stats))
+        }
+        Err(e) => (elapsed, Some(format!("{:#}", e)), None),
+    };
+
+    HealthCheckPayload {
+        endpoint_id: check.endpoint_id.clone(),
+        status_code: None,
+        response_time,
+        is_up: error_message.is_none(),
+        error_message,
+        checked_from: checked_from.to_string(),
+        answers: Vec::new(),
+        ping,
+    }
+}
+
+/// Send the echo requests and collect the round trip times
+///
+/// Also returns the last error a request failed with, if any.
+async fn ping(
+    check: &PingCheckConfig,
+    deadline: tokio::time::Instant,
+) -> Result<(PingStatistics, Option<std::io::Error>)> {
+    let address = timeout_at(deadline, tokio::net::lookup_host((check.host.as_str(), 0)))
+        .await
+        .with_context(|| format!("Timeout resolving {}", check.host))?
+        .with_context(|| format!("Failed to resolve {}", check.host))?
+        .next()
+        .with_context(|| format!("No address for {}", check.host))?;
+    let ipv6 = address.is_ipv6();
+
+    let socket = icmp_socket(address.ip())?;
+    socket
+        .connect(address)
+        .await
+        .with_context(|| format!("Failed to ping {}", address.ip()))?;
+    // The kernel uses the socket's port as the echo identifier
+    let id = socket.local_addr()?.port();
+
+    let count = check.count as usize;
+    let mut sent_at: Vec<Option<Instant>> = vec![None; count];
+    let mut rtts: Vec<Option<f64>> = vec![None; count];
+    let mut next_seq = 0;
+    let mut next_send = tokio::time::Instant::now();
+    // Why packets couldn't be sent or were answered with an ICMP error
+    let mut last_error = None;
+    let mut buf = [0u8; 1024];
+
+    loop {
+        tokio::select! {
+            _ = sleep_until(next_send), if next_seq < count => {
+                match socket.send(&echo_request(ipv6, next_seq as u16)).await {
+                    Ok(_) => sent_at[next_seq] = Some(Instant::now()),
+                    Err(e) => last_error = Some(e),
+                }
+                next_seq += 1;
+                next_send += PACKET_INTERVAL;
+            }
+            received = socket.recv(&mut buf) => match received {
+                Ok(len) => {
+                    if let Some(seq) = echo_reply_seq(ipv6, id, &buf[..len]) {
+                        // Duplicates and replies to unknown requests are ignored
+                        if let (Some(Some(sent)), Some(rtt @ None)) = (sent_at.get(seq), rtts.get_mut(seq)) {
+                            *rtt = Some(sent.elapsed().as_secs_f64() * 1000.0);
+                        }
+                    }
+                }
+                // E.g. host unreachable, reported for one of the requests
+                Err(e) => last_error = Some(e),
+            },
+            _ = sleep_until(deadline) => break,
+        }
+
+        let all_answered = sent_at.iter().zip(&rtts).all(|(sent, rtt)| sent.is_none() || rtt.is_some());
+        if next_seq == count && all_answered {
+            break;
+        }
+    }
+
+    let packets_sent = sent_at.iter().flatten().count() as u32;
+    let rtts: Vec<f64> = rtts.into_iter().flatten().collect();
+    Ok((statistics(packets_sent, &rtts), last_error))
+}
+
+/// Summarize the round trip times the way `ping` does
+fn statistics(packets_sent: u32, rtts: &[f64]) -> PingStatistics {
+    let packets_received = rtts.len() as u32;
+    let packet_loss = if packets_sent > 0 {
+        f64::from(packets_sent - packets_received) / f64::from(packets_sent) * 100.0
+    } else {
+        100.0
+    };
+
+    let (min, avg, max, mdev) = if rtts.is_empty() {
+        (None, None, None, None)
+    } else {
+        let count = rtts.len() as f64;
+        let avg = rtts.iter().sum::<f64>() / count;
+        let mean_square = rtts.iter().map(|rtt| rtt * rtt).sum::<f64>() / count;
+        (
+            rtts.iter().copied().reduce(f64::min),
+            Some(avg),
+            rtts.iter().copied().reduce(f64::max),
+            Some((mean_square - avg * avg).max(0.0).sqrt()),
+        )
+    };
+
+    PingStatistics {
+        packets_sent,
+        packets_received,
+        packet_loss,
+        rtt_min_ms: min,
+        rtt_avg_ms: avg,
+        rtt_max_ms: max,
+        rtt_mdev_ms: mdev,
+    }
+}
+
+/// Build an echo request; the kernel sets the identifier and checksum
+fn echo_request(ipv6: bool, seq: u16) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(8 + PAYLOAD_SIZE);
+    // Type, code and checksum
+    packet.extend_from_slice(&[if ipv6 { 128 } else { 8 }, 0, 0, 0]);
+    // Identifier and sequence number
+    packet.extend_from_slice(&[0, 0]);
+    packet.extend_from_slice(&seq.to_be_bytes());
+    packet.extend((0..PAYLOAD_SIZE).map(|i| i as u8));
+    packet
+}
+
+/// Sequence number of an echo reply to our requests, `None` for other ICMP messages
+fn echo_reply_seq(ipv6: bool, id: u16, packet: &[u8]) -> Option<usize> {
+    let reply_type = if ipv6 { 129 } else { 0 };
+    if packet.len() < 8 || packet[0] != reply_type || packet[4..6] != id.to_be_bytes() {
+        return None;
+    }
+    Some(u16::from_be_bytes([packet[6], packet[7]]) as usize)
+}
+
+/// Open an ICMP datagram socket for the address family of `ip`
+#[cfg(target_os = "linux")]
+fn icmp_socket(ip: IpAddr) -> Result<UdpSocket> {
+    use std::os::fd::FromRawFd;
+
+    let (domain, protocol) = match ip {
+        IpAddr::V4(_) => (libc::AF_INET, libc::IPPROTO_ICMP),
+        IpAddr::V6(_) => (libc::AF_INET6, libc::IPPROTO_ICMPV6),
+    };
+    let fd = unsafe { libc::socket(domain, libc::SOCK_DGRAM | libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC, protocol) };
+    if fd < 0 {
+        let error = std::io::Error::last_os_error();
+        if error.kind() == std::io::ErrorKind::PermissionDenied {
+            anyhow::bail!("ICMP sockets aren't allowed for the agent's group (see the net.ipv4.ping_group_range sysctl)");
+        }
+        return Err(error).context("Failed to create ICMP socket");
+    }
+
+    // The descriptor was just created and isn't owned by anything else
+    let socket = unsafe { std::net::UdpSocket::from_raw_fd(fd) };
+    Ok(UdpSocket::from_std(socket)?)
+}
+
+/// Fallback for non-Linux systems
+#[cfg(not(target_os = "linux"))]
+fn icmp_socket(_ip: IpAddr) -> Result<UdpSocket> {
+    anyhow::bail!("Ping checks are only supported on Linux")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The reply a host sends to `request`, as the kernel hands it to a socket with identifier `id`
+    fn echo_reply(ipv6: bool, id: u16, request: &[u8]) -> Vec<u8> {
+        let mut reply = request.to_vec();
+        reply[0] = if ipv6 { 129 } else { 0 };
+        reply[4..6].copy_from_slice(&id.to_be_bytes());
+        reply
+    }
+
+    fn assert_close(actual: Option<f64>, expected: f64) {
+        let actual = actual.unwrap();
+        assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn summarizes_round_trip_times() {
+        let stats = statistics(4, &[10.0, 30.0, 20.0]);
+        assert_eq!(stats.packets_sent, 4);
+        assert_eq!(stats.packets_received, 3);
+        assert_close(Some(stats.packet_loss), 25.0);
+        assert_close(stats.rtt_min_ms, 10.0);
+        assert_close(stats.rtt_avg_ms, 20.0);
+        assert_close(stats.rtt_max_ms, 30.0);
+        // Population standard deviation, as ping reports it
+        assert_close(stats.rtt_mdev_ms, (200.0f64 / 3.0).sqrt());
+    }
+
+    #[test]
+    fn summarizes_a_single_reply() {
+        let stats = statistics(1, &[12.5]);
+        assert_close(Some(stats.packet_loss), 0.0);
+        assert_close(stats.rtt_min_ms, 12.5);
+        assert_close(stats.rtt_avg_ms, 12.5);
+        assert_close(stats.rtt_max_ms, 12.5);
+        assert_close(stats.rtt_mdev_ms, 0.0);
+    }
+
+    #[test]
+    fn summarizes_lost_packets() {
+        let stats = statistics(3, &[]);
+        assert_eq!(stats.packets_received, 0);
+        assert_close(Some(stats.packet_loss), 100.0);
+        assert_eq!(stats.rtt_min_ms, None);
+        assert_eq!(stats.rtt_avg_ms, None);
+        assert_eq!(stats.rtt_max_ms, None);
+        assert_eq!(stats.rtt_mdev_ms, None);
+
+        // Nothing could be sent at all
+        assert_close(Some(statistics(0, &[]).packet_loss), 100.0);
+    }
+
+    #[test]
+    fn builds_echo_requests() {
+        let request = echo_request(false, 0x0102);
+        assert_eq!(request.len(), 8 + PAYLOAD_SIZE);
+        // Echo request, code 0
+        assert_eq!(request[..2], [8, 0]);
+        // Checksum and identifier are left for the kernel to fill in
+        assert_eq!(request[2..6], [0, 0, 0, 0]);
+        assert_eq!(request[6..8], [0x01, 0x02]);
+        assert!(request[8..].iter().enumerate().all(|(i, &byte)| byte == i as u8));
+
+        assert_eq!(echo_request(true, 7)[..2], [128, 0]);
+    }
+
+    #[test]
+    fn matches_replies_to_requests() {
+        for ipv6 in [false, true] {
+            for seq in [0, 1, 300] {
+                let reply = echo_reply(ipv6, 0x4242, &echo_request(ipv6, seq));
+                assert_eq!(echo_reply_seq(ipv6, 0x4242, &reply), Some(seq as usize));
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_other_packets() {
+        let request = echo_request(false, 3);
+
+        // A reply to another socket's requests
+        assert_eq!(echo_reply_seq(false, 0x4242, &echo_reply(false, 0x4343, &request)), None);
+        // Our own request, and an IPv6 reply read as IPv4
+        assert_eq!(echo_reply_seq(false, 0, &request), None);
+        assert_eq!(echo_reply_seq(false, 0x4242, &echo_reply(true, 0x4242, &request)), None);
+        // Destination unreachable
+        let mut unreachable = echo_reply(false, 0x4242, &request);
+        unreachable[0] = 3;
+        assert_eq!(echo_reply_seq(false, 0x4242, &unreachable), None);
+        // Too short to hold a sequence number
+        assert_eq!(echo_reply_seq(false, 0x4242, &echo_reply(false, 0x4242, &request)[..7]), None);
+    }
+}
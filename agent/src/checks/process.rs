@@ -0,0 +1,231 @@
+//! Process presence and count checks
+//!
+//! Each check counts the running processes that match all of its matchers
+//! and passes when the count is within the configured bounds. Checks run
+//! with every metrics collection against the process list that was just
+//! refreshed, and their results are part of the metrics payload.
+
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use anyhow::{Context, Result};
+use glob::Pattern;
+use regex::Regex;
+use sysinfo::{Process, ProcessStatus, System, Users};
+
+use crate::config::ProcessCheckConfig;
+use crate::models::{HealthCheckPayload, ProcessCheckResult};
+
+/// User a check matches on
+#[derive(Debug, Clone)]
+enum UserMatcher {
+    Name(String),
+    Uid(u32),
+}
+
+/// A process check with its patterns compiled
+#[derive(Debug, Clone)]
+struct ProcessCheck {
+    name: String,
+    process_name: Option<Pattern>,
+    cmdline: Option<Regex>,
+    user: Option<UserMatcher>,
+    pidfile: Option<PathBuf>,
+    min_count: usize,
+    max_count: Option<usize>,
+    endpoint_id: Option<String>,
+}
+
+/// The configured process checks
+#[derive(Debug, Clone)]
+pub struct ProcessChecks {
+    checks: Vec<ProcessCheck>,
+}
+
+impl ProcessChecks {
+    /// Compile and validate the `[[process_checks]]` config entries
+    pub fn from_config(configs: &[ProcessCheckConfig]) -> Result<Self> {
+        let checks = configs
+            .iter()
+            .map(|config| {
+                ProcessCheck::from_config(config).with_context(|| format!("Invalid process check '{}'", config.name))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { checks })
+    }
+
+    /// Run every check against the current process list
+    pub fn run(&self, system: &System) -> Vec<ProcessCheckResult> {
+        if self.checks.is_empty() {
+            return Vec::new();
+        }
+
+        // Threads and zombies aren't running processes
+        let processes: Vec<&Process> = system
+            .processes()
+            .values()
+            .filter(|process| process.thread_kind().is_none())
+            .filter(|process| !matches!(process.status(), ProcessStatus::Zombie | ProcessStatus::Dead))
+            .collect();
+        let users = if self.checks.iter().any(|check| matches!(check.user, Some(UserMatcher::Name(_)))) {
+            Users::new_with_refreshed_list()
+        } else {
+            Users::new()
+        };
+
+        self.checks
+            .iter()
+            .map(|check| check.run(&processes, &users))
+            .collect()
+    }
+}
+
+impl ProcessCheck {
+    fn from_config(config: &ProcessCheckConfig) -> Result<Self> {
+        if config.name.is_empty() {
+            anyhow::bail!("process_checks.name cannot be empty");
+        }
+        if config.process_name.is_none() && config.cmdline.is_none() && config.user.is_none() && config.pidfile.is_none()
+        {
+            anyhow::bail!("at least one of process_name, cmdline, user or pidfile is required");
+        }
+        if config.max_count.is_some_and(|max| max < config.min_count) {
+            anyhow::bail!("process_checks.max_count cannot be less than min_count");
+        }
+        if let Some(endpoint_id) = &config.endpoint_id {
+            // The backend rejects results for IDs that aren't UUIDs
+            if uuid::Uuid::parse_str(endpoint_id).is_err() {
+                anyhow::bail!("process_checks.endpoint_id must be the endpoint's UUID ({})", endpoint_id);
+            }
+        }
+
+        let process_name = config
+            .process_name
+            .as_deref()
+            .map(|pattern| Pattern::new(pattern).with_context(|| format!("Invalid pattern: {}", pattern)))
+            .transpose()?;
+        let cmdline = config
+            .cmdline
+            .as_deref()
+            .map(|regex| Regex::new(regex).with_context(|| format!("Invalid regular expression: {}", regex)))
+            .transpose()?;
+        let user = config.user.as_deref().map(|user| match user.parse() {
+            Ok(uid) => UserMatcher::Uid(uid),
+            Err(_) => UserMatcher::Name(user.to_string()),
+        });
+
+        Ok(Self {
+            name: config.name.clone(),
+            process_name,
+            cmdline,
+            user,
+            pidfile: config.pidfile.clone(),
+            min_count: config.min_count,
+            max_count: config.max_count,
+            endpoint_id: config.endpoint_id.clone(),
+        })
+    }
+
+    fn run(&self, processes: &[&Process], users: &Users) -> ProcessCheckResult {
+        let start = Instant::now();
+
+        let pidfile_pid = match self.pidfile.as_deref().map(read_pidfile).transpose() {
+            Ok(pid) => pid,
+            Err(e) => return self.result(Vec::new(), Some(format!("{:#}", e)), start),
+        };
+
+        let mut matched_pids: Vec<u32> = processes
+            .iter()
+            .filter(|process| pidfile_pid.is_none_or(|pid| process.pid().as_u32() == pid))
+            .filter(|process| self.matches(process, users))
+            .map(|process| process.pid().as_u32())
+            .collect();
+        matched_pids.sort_unstable();
+
+        let count = matched_pids.len();
+        let message = if count < self.min_count {
+            Some(format!("Expected at least {} matching process(es), found {}", self.min_count, count))
+        } else {
+            self.max_count
+                .filter(|&max| count > max)
+                .map(|max| format!("Expected at most {} matching process(es), found {}", max, count))
+        };
+        self.result(matched_pids, message, start)
+    }
+
+    /// Whether a process matches every matcher of the check (the pidfile is checked separately)
+    fn matches(&self, process: &Process, users: &Users) -> bool {
+        if let Some(pattern) = &self.process_name {
+            // The kernel truncates process names to 15 characters; the executable name isn't
+            let exe_name = process
+                .exe()
+                .and_then(Path::file_name)
+                .map(|name| name.to_string_lossy());
+            let name_matches =
+                pattern.matches(process.name()) || exe_name.is_some_and(|name| pattern.matches(&name));
+            if !name_matches {
+                return false;
+            }
+        }
+
+        if let Some(regex) = &self.cmdline {
+            if !regex.is_match(&process.cmd().join(" ")) {
+                return false;
+            }
+        }
+
+        if let Some(user) = &self.user {
+            let Some(uid) = process.user_id() else {
+                return false;
+            };
+            let user_matches = match user {
+                UserMatcher::Uid(expected) => **uid == *expected,
+                UserMatcher::Name(name) => users.get_user_by_id(uid).is_some_and(|u| u.name() == name),
+            };
+            if !user_matches {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    fn result(&self, matched_pids: Vec<u32>, message: Option<String>, start: Instant) -> ProcessCheckResult {
+        ProcessCheckResult {
+            name: self.name.clone(),
+            passed: message.is_none(),
+            count: matched_pids.len(),
+            matched_pids,
+            min_count: self.min_count,
+            max_count: self.max_count,
+            message,
+            endpoint_id: self.endpoint_id.clone(),
+            duration_ms: start.elapsed().as_secs_f64() * 1000.0,
+        }
+    }
+}
+
+/// Read the PID from a pidfile
+fn read_pidfile(path: &Path) -> Result<u32> {
+    let content =
+        std::fs::read_to_string(path).with_context(|| format!("Failed to read pidfile {}", path.display()))?;
+    content
+        .trim()
+        .parse()
+        .with_context(|| format!("Invalid PID in {}", path.display()))
+}
+
+/// Build the health check payload for a check tied to a dashboard endpoint
+pub fn health_payload(result: &ProcessCheckResult, checked_from: &str) -> Option<HealthCheckPayload> {
+    let endpoint_id = result.endpoint_id.clone()?;
+    Some(HealthCheckPayload {
+        endpoint_id,
+        status_code: None,
+        response_time: result.duration_ms,
+        is_up: result.passed,
+        error_message: result.message.clone(),
+        checked_from: checked_from.to_string(),
+        answers: Vec::new(),
+        ping: None,
+    })
+}
@@ -0,0 +1,214 @@
+//! TCP port health check
+
+use anyhow::Result;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::timeout_at;
+
+use crate::config::{HealthCheckConfig, TcpCheckConfig};
+use crate::models::HealthCheckPayload;
+
+/// Most data read while waiting for the expected reply
+const MAX_REPLY_BYTES: usize = 64 * 1024;
+
+/// Run a single TCP check and build the payload to report
+///
+/// The reported response time is the time it took to connect, including
+/// name resolution. The timeout covers the whole check.
+pub async fn run(check: &HealthCheckConfig, tcp: &TcpCheckConfig, checked_from: &str) -> HealthCheckPayload {
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(check.timeout_seconds);
+
+    let start = Instant::now();
+    let connected = timeout_at(deadline, TcpStream::connect((tcp.host.as_str(), tcp.port))).await;
+    let response_time = start.elapsed().as_secs_f64() * 1000.0;
+
+    let error_message = match connected {
+        Ok(Ok(mut stream)) => match timeout_at(deadline, exchange(&mut stream, tcp)).await {
+            Ok(Ok(())) => None,
+            Ok(Err(e)) => Some(e.to_string()),
+            Err(_) => Some(format!(
+                "Timeout after {}s waiting for {:?}",
+                check.timeout_seconds,
+                tcp.expect.as_deref().unwrap_or_default()
+            )),
+        },
+        Ok(Err(e)) => Some(format!("Connection failed: {}", e)),
+        Err(_) => Some(format!("Timeout after {}s", check.timeout_seconds)),
+    };
+
+    HealthCheckPayload {
+        endpoint_id: check.endpoint_id.clone(),
+        status_code: None,
+        response_time,
+        is_up: error_message.is_none(),
+        error_message,
+        checked_from: checked_from.to_string(),
+        answers: Vec::new(),
+        ping: None,
+    }
+}
+
+/// Send the configured payload and wait for the expected reply
+async fn exchange(stream: &mut TcpStream, tcp: &TcpCheckConfig) -> Result<()> {
+    if let Some(send) = &tcp.send {
+        stream.write_all(send.as_bytes()).await?;
+        stream.flush().await?;
+    }
+
+    let Some(expect) = &tcp.expect else {
+        return Ok(());
+    };
+
+    let mut reply = Vec::new();
+    let mut buf = [0u8; 4096];
+    loop {
+        let len = stream.read(&mut buf).await?;
+        if len == 0 {
+            anyhow::bail!(
+                "Connection closed before {:?} was received (got {:?})",
+                expect,
+                truncate(&String::from_utf8_lossy(&reply), 100)
+            );
+        }
+        reply.extend_from_slice(&buf[..len]);
+        if String::from_utf8_lossy(&reply).contains(expect.as_str()) {
+            return Ok(());
+        }
+        if reply.len() >= MAX_REPLY_BYTES {
+            anyhow::bail!("{:?} not found in the first {} bytes of the reply", expect, reply.len());
+        }
+    }
+}
+
+/// The first `max` characters of `text`
+fn truncate(text: &str, max: usize) -> String {
+    text.chars().take(max).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CheckKind;
+    use std::future::Future;
+    use tokio::net::TcpListener;
+
+    /// Accept one connection on a random port and hand it to `handler`
+    async fn serve<F, Fut>(handler: F) -> u16
+    where
+        F: FnOnce(TcpStream) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            handler(stream).await;
+        });
+        port
+    }
+
+    async fn connect(port: u16, send: Option<&str>, expect: Option<&str>) -> HealthCheckPayload {
+        let tcp = TcpCheckConfig {
+            host: "127.0.0.1".to_string(),
+            port,
+            send: send.map(str::to_string),
+            expect: expect.map(str::to_string),
+        };
+        let check = HealthCheckConfig {
+            endpoint_id: "tcp".to_string(),
+            kind: CheckKind::Tcp(tcp.clone()),
+            timeout_seconds: 1,
+            interval_seconds: 60,
+        };
+        run(&check, &tcp, "test").await
+    }
+
+    #[tokio::test]
+    async fn connects() {
+        let port = serve(|_stream| async {}).await;
+
+        let result = connect(port, None, None).await;
+        assert_eq!(result.error_message, None);
+        assert!(result.is_up);
+        assert_eq!(result.status_code, None);
+    }
+
+    #[tokio::test]
+    async fn reports_refused_connections() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        drop(listener);
+
+        let result = connect(port, None, None).await;
+        assert!(!result.is_up);
+        assert!(result.error_message.unwrap().starts_with("Connection failed: "));
+    }
+
+    #[tokio::test]
+    async fn finds_the_expected_reply() {
+        let port = serve(|mut stream| async move {
+            stream.write_all(b"220 mail.example.com ESMTP\r\n").await.unwrap();
+            let mut buf = [0u8; 64];
+            let len = stream.read(&mut buf).await.unwrap();
+            assert_eq!(&buf[..len], b"EHLO nubilus\r\n");
+            // The reply arrives in pieces
+            stream.write_all(b"25").await.unwrap();
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            stream.write_all(b"0-mail.example.com\r\n").await.unwrap();
+        })
+        .await;
+
+        let result = connect(port, Some("EHLO nubilus\r\n"), Some("250-mail")).await;
+        assert_eq!(result.error_message, None);
+        assert!(result.is_up);
+    }
+
+    #[tokio::test]
+    async fn fails_when_the_peer_closes_first() {
+        let port = serve(|mut stream| async move {
+            stream.write_all(b"554 go away\r\n").await.unwrap();
+        })
+        .await;
+
+        let result = connect(port, None, Some("220")).await;
+        assert!(!result.is_up);
+        assert_eq!(
+            result.error_message.as_deref(),
+            Some(r#"Connection closed before "220" was received (got "554 go away\r\n")"#)
+        );
+    }
+
+    #[tokio::test]
+    async fn stops_reading_after_the_reply_limit() {
+        let port = serve(|mut stream| async move {
+            let chunk = vec![b'x'; 16 * 1024];
+            // Stops once the check hangs up
+            while stream.write_all(&chunk).await.is_ok() {}
+        })
+        .await;
+
+        let result = connect(port, None, Some("220")).await;
+        assert!(!result.is_up);
+        let message = result.error_message.unwrap();
+        assert!(message.starts_with(r#""220" not found in the first "#), "{}", message);
+        let read: usize = message.split_whitespace().nth(6).unwrap().parse().unwrap();
+        assert!((MAX_REPLY_BYTES..MAX_REPLY_BYTES + 4096).contains(&read), "{}", message);
+    }
+
+    #[tokio::test]
+    async fn times_out_waiting_for_the_reply() {
+        let port = serve(|stream| async move {
+            // Keeps the connection open without answering
+            let _stream = stream;
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        })
+        .await;
+
+        let result = connect(port, None, Some("220")).await;
+        assert!(!result.is_up);
+        assert_eq!(result.error_message.as_deref(), Some(r#"Timeout after 1s waiting for "220""#));
+        // Connecting to localhost is quick, the wait for the reply isn't counted
+        assert!(result.response_time < 500.0, "{}", result.response_time);
+    }
+}
@@ -0,0 +1,383 @@
+//! cgroup v2 resource metrics collector
+//!
+//! Walks the unified hierarchy under /sys/fs/cgroup and reports CPU,
+//! throttling, memory, I/O and task counts for the cgroups selected in the
+//! `[cgroups]` config section, e.g. systemd services or Docker containers.
+//! Hosts still on the legacy (v1 or hybrid) hierarchy report nothing.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::Result;
+use tracing::debug;
+
+use super::filter::NameFilter;
+use super::rate::{Counter, CounterRates, CounterWidth};
+use crate::config::CgroupConfig;
+use crate::models::CgroupMetrics;
+
+/// Where the cgroup v2 hierarchy is mounted
+pub const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
+/// Selects which cgroups are reported
+#[derive(Debug, Clone)]
+pub struct CgroupFilter {
+    paths: NameFilter,
+    max_cgroups: usize,
+}
+
+impl CgroupFilter {
+    /// Build the filter from the `[cgroups]` config section
+    pub fn from_config(config: &CgroupConfig) -> Result<Self> {
+        Ok(Self {
+            paths: NameFilter::for_paths(&config.include, &config.exclude)?,
+            max_cgroups: config.max_cgroups,
+        })
+    }
+}
+
+impl Default for CgroupFilter {
+    fn default() -> Self {
+        Self::from_config(&CgroupConfig::default()).expect("default cgroup patterns are valid")
+    }
+}
+
+/// Cumulative per-cgroup counters that are turned into rates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CgroupCounter {
+    UsageUsec,
+    UserUsec,
+    SystemUsec,
+    Periods,
+    Throttled,
+    ThrottledUsec,
+    ReadBytes,
+    WriteBytes,
+    ReadIos,
+    WriteIos,
+}
+
+impl Counter for CgroupCounter {
+    fn width(&self) -> CounterWidth {
+        match self {
+            // Added up across devices
+            CgroupCounter::ReadBytes
+            | CgroupCounter::WriteBytes
+            | CgroupCounter::ReadIos
+            | CgroupCounter::WriteIos => CounterWidth::Unbounded,
+            _ => CounterWidth::U64,
+        }
+    }
+}
+
+/// Previous counters of each cgroup, keyed by path and inode (which changes
+/// when a cgroup is removed and created again under the same path)
+pub type CgroupRates = CounterRates<(String, u64, CgroupCounter)>;
+
+/// Collect metrics of the selected cgroups
+pub fn collect(filter: &CgroupFilter, rates: &mut CgroupRates) -> Vec<CgroupMetrics> {
+    collect_from(Path::new(CGROUP_ROOT), filter, rates)
+}
+
+/// Collect metrics of the selected cgroups under a cgroup v2 mount at `root`
+pub fn collect_from(root: &Path, filter: &CgroupFilter, rates: &mut CgroupRates) -> Vec<CgroupMetrics> {
+    // Only the unified hierarchy has cgroup.controllers at its root
+    if !root.join("cgroup.controllers").exists() {
+        return Vec::new();
+    }
+
+    let mut paths = Vec::new();
+    find_cgroups(root, root, filter, &mut paths);
+    if paths.len() > filter.max_cgroups {
+        debug!(
+            "{} cgroups match, reporting the {} that used the most CPU time",
+            paths.len(),
+            filter.max_cgroups
+        );
+        let usage = |path: &String| {
+            read_flat_keyed(&root.join(path.trim_start_matches('/')).join("cpu.stat"))
+                .get("usage_usec")
+                .copied()
+                .unwrap_or(0)
+        };
+        paths.sort_by_cached_key(|path| std::cmp::Reverse(usage(path)));
+        paths.truncate(filter.max_cgroups);
+    }
+    paths.sort();
+
+    let mut counters = Vec::new();
+    let mut metrics = Vec::new();
+    let mut inodes = Vec::new();
+    for path in paths {
+        let dir = root.join(path.trim_start_matches('/'));
+        let inode = inode(&dir);
+        let cpu = read_flat_keyed(&dir.join("cpu.stat"));
+        let io = std::fs::read_to_string(dir.join("io.stat")).ok().map(|content| parse_io_stat(&content));
+
+        for (key, counter) in [
+            ("usage_usec", CgroupCounter::UsageUsec),
+            ("user_usec", CgroupCounter::UserUsec),
+            ("system_usec", CgroupCounter::SystemUsec),
+            ("nr_periods", CgroupCounter::Periods),
+            ("nr_throttled", CgroupCounter::Throttled),
+            ("throttled_usec", CgroupCounter::ThrottledUsec),
+        ] {
+            if let Some(&value) = cpu.get(key) {
+                counters.push(((path.clone(), inode, counter), value));
+            }
+        }
+        if let Some(io) = io {
+            counters.extend([
+                ((path.clone(), inode, CgroupCounter::ReadBytes), io.read_bytes),
+                ((path.clone(), inode, CgroupCounter::WriteBytes), io.write_bytes),
+                ((path.clone(), inode, CgroupCounter::ReadIos), io.read_ios),
+                ((path.clone(), inode, CgroupCounter::WriteIos), io.write_ios),
+            ]);
+        }
+
+        let memory_current = read_value(&dir.join("memory.current"));
+        let memory_max = read_value(&dir.join("memory.max"));
+
+        inodes.push(inode);
+        metrics.push(CgroupMetrics {
+            path,
+            cpu_usage_percent: None,
+            cpu_user_percent: None,
+            cpu_system_percent: None,
+            cpu_throttled_periods_percent: None,
+            cpu_throttled_percent: None,
+            nr_throttled: cpu.get("nr_throttled").copied(),
+            memory_current,
+            memory_max,
+            memory_usage: match (memory_current, memory_max) {
+                (Some(current), Some(max)) if max > 0 => Some((current as f64 / max as f64) * 100.0),
+                _ => None,
+            },
+            io_read_bytes_per_sec: None,
+            io_write_bytes_per_sec: None,
+            io_read_iops: None,
+            io_write_iops: None,
+            pids_current: read_value(&dir.join("pids.current")),
+            pids_max: read_value(&dir.join("pids.max")),
+        });
+    }
+
+    let rates = rates.update(counters);
+    for (cgroup, inode) in metrics.iter_mut().zip(inodes) {
+        let rate = |counter| rates.get(&(cgroup.path.clone(), inode, counter)).copied();
+        // usec per second, as a percentage of one CPU
+        let percent = |counter| rate(counter).map(|usec_per_sec| usec_per_sec / 10_000.0);
+
+        cgroup.cpu_usage_percent = percent(CgroupCounter::UsageUsec);
+        cgroup.cpu_user_percent = percent(CgroupCounter::UserUsec);
+        cgroup.cpu_system_percent = percent(CgroupCounter::SystemUsec);
+        cgroup.cpu_throttled_percent = percent(CgroupCounter::ThrottledUsec);
+        cgroup.cpu_throttled_periods_percent =
+            match (rate(CgroupCounter::Throttled), rate(CgroupCounter::Periods)) {
+                (Some(throttled), Some(periods)) if periods > 0.0 => Some((throttled / periods) * 100.0),
+                (Some(_), Some(_)) => Some(0.0),
+                _ => None,
+            };
+        cgroup.io_read_bytes_per_sec = rate(CgroupCounter::ReadBytes);
+        cgroup.io_write_bytes_per_sec = rate(CgroupCounter::WriteBytes);
+        cgroup.io_read_iops = rate(CgroupCounter::ReadIos);
+        cgroup.io_write_iops = rate(CgroupCounter::WriteIos);
+    }
+
+    metrics
+}
+
+/// Collect the paths (relative to the root, starting with "/") of matching cgroups
+fn find_cgroups(root: &Path, dir: &Path, filter: &CgroupFilter, paths: &mut Vec<String>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        if !entry.file_type().is_ok_and(|t| t.is_dir()) {
+            continue;
+        }
+        let child = entry.path();
+        let Ok(relative) = child.strip_prefix(root) else {
+            continue;
+        };
+        let path = format!("/{}", relative.to_string_lossy());
+        if filter.paths.matches(&path) {
+            paths.push(path);
+        }
+        find_cgroups(root, &child, filter, paths);
+    }
+}
+
+/// Inode number of a cgroup directory, 0 if it can't be read
+#[cfg(unix)]
+fn inode(dir: &Path) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+
+    std::fs::metadata(dir).map(|m| m.ino()).unwrap_or(0)
+}
+
+/// Fallback for non-Unix systems, which have no cgroups anyway
+#[cfg(not(unix))]
+fn inode(_dir: &Path) -> u64 {
+    0
+}
+
+/// Read a single-value file such as memory.current; "max" means no limit
+fn read_value(path: &Path) -> Option<u64> {
+    std::fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Read a flat keyed file such as cpu.stat (`key value` per line)
+fn read_flat_keyed(path: &Path) -> HashMap<String, u64> {
+    let Ok(content) = std::fs::read_to_string(path) else {
+        return HashMap::new();
+    };
+    content
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.split_once(' ')?;
+            Some((key.to_string(), value.trim().parse().ok()?))
+        })
+        .collect()
+}
+
+/// I/O totals of a cgroup across all devices
+struct IoTotals {
+    read_bytes: u64,
+    write_bytes: u64,
+    read_ios: u64,
+    write_ios: u64,
+}
+
+/// Parse io.stat and add up every device
+///
+/// Format: `8:0 rbytes=1459200 wbytes=314773504 rios=192 wios=353 dbytes=0 dios=0`
+fn parse_io_stat(content: &str) -> IoTotals {
+    let mut totals = IoTotals {
+        read_bytes: 0,
+        write_bytes: 0,
+        read_ios: 0,
+        write_ios: 0,
+    };
+    for line in content.lines() {
+        for field in line.split_whitespace().skip(1) {
+            let Some((key, value)) = field.split_once('=') else {
+                continue;
+            };
+            let value: u64 = value.parse().unwrap_or(0);
+            match key {
+                "rbytes" => totals.read_bytes += value,
+                "wbytes" => totals.write_bytes += value,
+                "rios" => totals.read_ios += value,
+                "wios" => totals.write_ios += value,
+                _ => {}
+            }
+        }
+    }
+    totals
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// Create a cgroup directory with the given CPU time
+    fn add_cgroup(root: &Path, path: &str, usage_usec: u64) {
+        let dir = root.join(path.trim_start_matches('/'));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("cpu.stat"),
+            format!("usage_usec {}\nuser_usec 0\nsystem_usec 0\n", usage_usec),
+        )
+        .unwrap();
+    }
+
+    fn fake_hierarchy() -> tempfile::TempDir {
+        let root = tempfile::tempdir().unwrap();
+        fs::write(
+            root.path().join("cgroup.controllers"),
+            "cpu io memory pids\n",
+        )
+        .unwrap();
+        add_cgroup(root.path(), "/system.slice/nginx.service", 5_000);
+        add_cgroup(root.path(), "/system.slice/cron.service", 100);
+        add_cgroup(root.path(), "/system.slice/postgresql.service", 90_000);
+        add_cgroup(root.path(), "/docker/0123abcd", 20_000);
+        // Created by systemd running inside the container
+        add_cgroup(root.path(), "/docker/0123abcd/init.scope", 10);
+        root
+    }
+
+    fn reported_paths(root: &Path, filter: &CgroupFilter) -> Vec<String> {
+        collect_from(root, filter, &mut CgroupRates::new())
+            .into_iter()
+            .map(|cgroup| cgroup.path)
+            .collect()
+    }
+
+    #[test]
+    fn wildcards_stay_within_one_level() {
+        let root = fake_hierarchy();
+        assert_eq!(
+            reported_paths(root.path(), &CgroupFilter::default()),
+            [
+                "/docker/0123abcd",
+                "/system.slice/cron.service",
+                "/system.slice/nginx.service",
+                "/system.slice/postgresql.service",
+            ]
+        );
+
+        let nested = CgroupFilter::from_config(&CgroupConfig {
+            include: vec!["/docker/**".to_string()],
+            ..CgroupConfig::default()
+        })
+        .unwrap();
+        assert_eq!(
+            reported_paths(root.path(), &nested),
+            ["/docker/0123abcd", "/docker/0123abcd/init.scope"]
+        );
+    }
+
+    #[test]
+    fn keeps_the_busiest_cgroups() {
+        let root = fake_hierarchy();
+        let filter = CgroupFilter::from_config(&CgroupConfig {
+            max_cgroups: 2,
+            ..CgroupConfig::default()
+        })
+        .unwrap();
+        assert_eq!(
+            reported_paths(root.path(), &filter),
+            ["/docker/0123abcd", "/system.slice/postgresql.service"]
+        );
+    }
+
+    #[test]
+    fn ignores_legacy_hierarchies() {
+        let root = fake_hierarchy();
+        fs::remove_file(root.path().join("cgroup.controllers")).unwrap();
+        assert!(reported_paths(root.path(), &CgroupFilter::default()).is_empty());
+    }
+
+    #[test]
+    fn adds_up_io_stat_devices() {
+        let content = "\
+8:0 rbytes=1459200 wbytes=314773504 rios=192 wios=353 dbytes=0 dios=0
+259:0 rbytes=4096 wbytes=0 rios=1 wios=0 dbytes=512 dios=1
+253:1 rbytes=garbage wbytes=8192 rios=2 wios=2
+";
+        let totals = parse_io_stat(content);
+        assert_eq!(totals.read_bytes, 1_463_296);
+        assert_eq!(totals.write_bytes, 314_781_696);
+        assert_eq!(totals.read_ios, 195);
+        assert_eq!(totals.write_ios, 355);
+
+        let empty = parse_io_stat("");
+        assert_eq!(
+            empty.read_bytes + empty.write_bytes + empty.read_ios + empty.write_ios,
+            0
+        );
+    }
+}
@@ -1,7 +1,12 @@
 //! CPU metrics collector
 
+use std::collections::HashMap;
+
 use sysinfo::System;
 
+use super::rate::{Counter, CounterRates, CounterWidth};
+use crate::models::CpuCoreMetrics;
+
 /// CPU metrics data
 pub struct CpuMetrics {
     /// Overall CPU usage percentage (0-100)
@@ -14,10 +19,106 @@ pub struct CpuMetrics {
     pub load_average_5m: Option<f64>,
     /// 15-minute load average (Unix only)
     pub load_average_15m: Option<f64>,
+    /// Usage of the busiest core (0-100)
+    pub max_core_usage: Option<f64>,
+    /// Per-core usage and frequency, empty unless requested
+    pub cores: Vec<CpuCoreMetrics>,
+    /// Time spent in each CPU state since the previous sample (Linux only)
+    pub times: Option<CpuTimes>,
+    /// Context switches per second (Linux only)
+    pub context_switches_per_sec: Option<f64>,
+    /// Interrupts serviced per second (Linux only)
+    pub interrupts_per_sec: Option<f64>,
+    /// Processes and threads created per second (Linux only)
+    pub forks_per_sec: Option<f64>,
+    /// Tasks currently runnable (Linux only)
+    pub procs_running: Option<u64>,
+    /// Tasks currently blocked on I/O (Linux only)
+    pub procs_blocked: Option<u64>,
+}
+
+/// Share of CPU time spent in each state, as percentages (0-100) that add up to 100
+#[derive(Debug, Clone, Copy)]
+pub struct CpuTimes {
+    pub user: f64,
+    pub nice: f64,
+    pub system: f64,
+    pub idle: f64,
+    pub iowait: f64,
+    pub irq: f64,
+    pub softirq: f64,
+    /// Time stolen by the hypervisor for other guests
+    pub steal: f64,
+    /// Time spent running guest VMs (nice and non-nice)
+    pub guest: f64,
+}
+
+/// Cumulative counters from /proc/stat that are turned into rates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StatCounter {
+    User,
+    Nice,
+    System,
+    Idle,
+    Iowait,
+    Irq,
+    Softirq,
+    Steal,
+    Guest,
+    GuestNice,
+    ContextSwitches,
+    Interrupts,
+    Forks,
+}
+
+impl Counter for StatCounter {
+    fn width(&self) -> CounterWidth {
+        match self {
+            // Summed into an unsigned long by the kernel
+            StatCounter::Forks => CounterWidth::LONG,
+            _ => CounterWidth::U64,
+        }
+    }
+}
+
+/// Previous /proc/stat counters
+#[derive(Default)]
+pub struct StatRates {
+    /// Scheduler counters (interrupts, context switches and forks)
+    counters: CounterRates<StatCounter>,
+    /// Time counters of the aggregate cpu line, in jiffies
+    jiffies: HashMap<StatCounter, u64>,
+}
+
+impl StatRates {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the cpu line and return the time breakdown since the previous sample
+    ///
+    /// iowait may occasionally go backwards on tickless kernels; any state
+    /// whose counter decreased is treated as zero time rather than dropping
+    /// the whole breakdown.
+    fn cpu_times(&mut self, jiffies: &[(StatCounter, u64)]) -> Option<CpuTimes> {
+        let previous = std::mem::replace(&mut self.jiffies, jiffies.iter().copied().collect());
+        if previous.is_empty() || self.jiffies.is_empty() {
+            return None;
+        }
+
+        let delta = |counter| {
+            let current = self.jiffies.get(&counter).copied().unwrap_or(0);
+            current.saturating_sub(previous.get(&counter).copied().unwrap_or(0)) as f64
+        };
+        cpu_times(delta)
+    }
 }
 
 /// Collect CPU metrics from the system
-pub fn collect(system: &System) -> CpuMetrics {
+///
+/// Per-core figures are only included when `per_core` is set, but the
+/// busiest core is always reported.
+pub fn collect(system: &System, per_core: bool, stat_rates: &mut StatRates) -> CpuMetrics {
     let cpus = system.cpus();
     
     // Calculate average CPU usage across all cores
@@ -30,6 +131,24 @@ pub fn collect(system: &System) -> CpuMetrics {
 
     let count = cpus.len() as i32;
 
+    let max_core_usage = cpus
+        .iter()
+        .map(|cpu| cpu.cpu_usage() as f64)
+        .reduce(f64::max);
+
+    let cores = if per_core {
+        cpus.iter()
+            .enumerate()
+            .map(|(index, cpu)| CpuCoreMetrics {
+                core: index as u32,
+                usage: cpu.cpu_usage() as f64,
+                frequency_mhz: Some(cpu.frequency()).filter(|&mhz| mhz > 0),
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+
     // Get load averages (Unix only)
     let load_avg = System::load_average();
     let (load_average_1m, load_average_5m, load_average_15m) = if cfg!(unix) {
@@ -42,11 +161,222 @@ pub fn collect(system: &System) -> CpuMetrics {
         (None, None, None)
     };
 
+    let stat = read_proc_stat();
+    let times = stat_rates.cpu_times(stat.as_ref().map(|s| s.jiffies.as_slice()).unwrap_or_default());
+    let rates = stat_rates
+        .counters
+        .update(stat.as_ref().map(|s| s.counters.clone()).unwrap_or_default());
+    let rate = |counter| rates.get(&counter).copied();
+
     CpuMetrics {
         usage,
         count,
         load_average_1m,
         load_average_5m,
         load_average_15m,
+        max_core_usage,
+        cores,
+        times,
+        context_switches_per_sec: rate(StatCounter::ContextSwitches),
+        interrupts_per_sec: rate(StatCounter::Interrupts),
+        forks_per_sec: rate(StatCounter::Forks),
+        procs_running: stat.as_ref().and_then(|s| s.procs_running),
+        procs_blocked: stat.as_ref().and_then(|s| s.procs_blocked),
+    }
+}
+
+/// Turn the jiffies spent in each state into percentages of the elapsed CPU time
+///
+/// The kernel already counts guest time as user time (and guest_nice as
+/// nice), so it is subtracted there to avoid counting it twice.
+fn cpu_times(delta: impl Fn(StatCounter) -> f64) -> Option<CpuTimes> {
+    let user = delta(StatCounter::User);
+    let nice = delta(StatCounter::Nice);
+    let system = delta(StatCounter::System);
+    let idle = delta(StatCounter::Idle);
+    let iowait = delta(StatCounter::Iowait);
+    let irq = delta(StatCounter::Irq);
+    let softirq = delta(StatCounter::Softirq);
+    let steal = delta(StatCounter::Steal);
+    let guest = delta(StatCounter::Guest);
+    let guest_nice = delta(StatCounter::GuestNice);
+
+    let total = user + nice + system + idle + iowait + irq + softirq + steal;
+    if total <= 0.0 {
+        return None;
+    }
+    let percent = |value: f64| (value.max(0.0) / total) * 100.0;
+
+    Some(CpuTimes {
+        user: percent(user - guest),
+        nice: percent(nice - guest_nice),
+        system: percent(system),
+        idle: percent(idle),
+        iowait: percent(iowait),
+        irq: percent(irq),
+        softirq: percent(softirq),
+        steal: percent(steal),
+        guest: percent(guest + guest_nice),
+    })
+}
+
+/// Parsed contents of /proc/stat
+struct ProcStat {
+    /// Time counters of the aggregate cpu line
+    jiffies: Vec<(StatCounter, u64)>,
+    /// Scheduler counters
+    counters: Vec<(StatCounter, u64)>,
+    procs_running: Option<u64>,
+    procs_blocked: Option<u64>,
+}
+
+/// Read the aggregate CPU line and scheduler counters from /proc/stat (Linux only)
+///
+/// Relevant lines:
+/// - `cpu  user nice system idle iowait irq softirq steal guest guest_nice` (in jiffies)
+/// - `intr <total> ...` interrupts serviced since boot, then per-IRQ counts
+/// - `ctxt <n>` context switches since boot
+/// - `processes <n>` forks since boot
+/// - `procs_running <n>` / `procs_blocked <n>` current task counts
+///
+/// Older kernels have fewer fields on the cpu line; missing ones are zero.
+#[cfg(target_os = "linux")]
+fn read_proc_stat() -> Option<ProcStat> {
+    let content = std::fs::read_to_string("/proc/stat").ok()?;
+    Some(parse_proc_stat(&content))
+}
+
+/// Parse the contents of /proc/stat
+#[cfg(target_os = "linux")]
+fn parse_proc_stat(content: &str) -> ProcStat {
+    const CPU_FIELDS: [StatCounter; 10] = [
+        StatCounter::User,
+        StatCounter::Nice,
+        StatCounter::System,
+        StatCounter::Idle,
+        StatCounter::Iowait,
+        StatCounter::Irq,
+        StatCounter::Softirq,
+        StatCounter::Steal,
+        StatCounter::Guest,
+        StatCounter::GuestNice,
+    ];
+
+    let mut stat = ProcStat {
+        jiffies: Vec::new(),
+        counters: Vec::new(),
+        procs_running: None,
+        procs_blocked: None,
+    };
+
+    for line in content.lines() {
+        let mut fields = line.split_whitespace();
+        let Some(key) = fields.next() else {
+            continue;
+        };
+        let first = fields.clone().next().and_then(|v| v.parse::<u64>().ok());
+
+        match key {
+            "cpu" => {
+                for (counter, value) in CPU_FIELDS.iter().zip(fields) {
+                    stat.jiffies.push((*counter, value.parse().unwrap_or(0)));
+                }
+            }
+            "intr" => stat.counters.extend(first.map(|v| (StatCounter::Interrupts, v))),
+            "ctxt" => stat.counters.extend(first.map(|v| (StatCounter::ContextSwitches, v))),
+            "processes" => stat.counters.extend(first.map(|v| (StatCounter::Forks, v))),
+            "procs_running" => stat.procs_running = first,
+            "procs_blocked" => stat.procs_blocked = first,
+            _ => {}
+        }
+    }
+
+    stat
+}
+
+/// Fallback for non-Linux systems
+#[cfg(not(target_os = "linux"))]
+fn read_proc_stat() -> Option<ProcStat> {
+    None
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    const PROC_STAT: &str = "\
+cpu  1000 100 500 8000 200 10 40 0 150 50
+cpu0 500 50 250 4000 100 5 20 0 75 25
+intr 123456 0 9 0 0
+ctxt 987654
+btime 1700000000
+processes 4321
+procs_running 3
+procs_blocked 1
+softirq 5555 0 1 2
+";
+
+    #[test]
+    fn parses_proc_stat() {
+        let stat = parse_proc_stat(PROC_STAT);
+        assert_eq!(stat.jiffies.len(), 10);
+        assert_eq!(stat.jiffies[0], (StatCounter::User, 1000));
+        assert_eq!(stat.jiffies[4], (StatCounter::Iowait, 200));
+        assert_eq!(stat.jiffies[9], (StatCounter::GuestNice, 50));
+        assert_eq!(
+            stat.counters,
+            [
+                (StatCounter::Interrupts, 123456),
+                (StatCounter::ContextSwitches, 987654),
+                (StatCounter::Forks, 4321),
+            ]
+        );
+        assert_eq!(stat.procs_running, Some(3));
+        assert_eq!(stat.procs_blocked, Some(1));
+    }
+
+    #[test]
+    fn older_kernels_have_fewer_cpu_fields() {
+        let stat = parse_proc_stat("cpu  1000 100 500 8000\n");
+        assert_eq!(stat.jiffies.len(), 4);
+        assert!(stat.counters.is_empty());
+    }
+
+    #[test]
+    fn time_breakdown_needs_two_samples() {
+        let mut rates = StatRates::new();
+        let jiffies = parse_proc_stat(PROC_STAT).jiffies;
+        assert!(rates.cpu_times(&jiffies).is_none());
+    }
+
+    #[test]
+    fn time_breakdown_since_previous_sample() {
+        let mut rates = StatRates::new();
+        rates.cpu_times(&parse_proc_stat("cpu  0 0 0 0 0 0 0 0 0 0\n").jiffies);
+
+        // 100 jiffies in total, 20 of them user time of which 10 ran a guest
+        let times = rates
+            .cpu_times(&parse_proc_stat("cpu  20 0 10 60 10 0 0 0 10 0\n").jiffies)
+            .unwrap();
+        assert_eq!(times.user, 10.0);
+        assert_eq!(times.guest, 10.0);
+        assert_eq!(times.system, 10.0);
+        assert_eq!(times.idle, 60.0);
+        assert_eq!(times.iowait, 10.0);
+    }
+
+    #[test]
+    fn decreasing_counter_counts_as_no_time() {
+        let mut rates = StatRates::new();
+        rates.cpu_times(&parse_proc_stat("cpu  100 0 100 700 100 0 0 0 0 0\n").jiffies);
+
+        // iowait went backwards by 50 jiffies
+        let times = rates
+            .cpu_times(&parse_proc_stat("cpu  150 0 150 800 50 0 0 0 0 0\n").jiffies)
+            .unwrap();
+        assert_eq!(times.iowait, 0.0);
+        assert_eq!(times.user, 25.0);
+        assert_eq!(times.system, 25.0);
+        assert_eq!(times.idle, 50.0);
     }
 }
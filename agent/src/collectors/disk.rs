@@ -1,6 +1,14 @@
 //! Disk metrics collector
 
-use sysinfo::{Disks, System};
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use sysinfo::System;
+
+use super::filter::NameFilter;
+use super::rate::{sum_rates, Counter, CounterRates, CounterWidth};
+use crate::config::DiskConfig;
+use crate::models::{DiskIoMetrics, FilesystemHealth, FilesystemUsage};
 
 /// Disk metrics data
 #[derive(Debug)]
@@ -15,120 +23,687 @@ pub struct DiskMetrics {
     pub read_bytes: i64,
     /// Cumulative bytes written
     pub write_bytes: i64,
+    /// Read throughput across all devices (`None` until a previous sample exists)
+    pub read_bytes_per_sec: Option<f64>,
+    /// Write throughput across all devices
+    pub write_bytes_per_sec: Option<f64>,
+    /// I/O statistics of every reported block device
+    pub devices: Vec<DiskIoMetrics>,
+    /// Usage of every reported filesystem
+    pub filesystems: Vec<FilesystemUsage>,
 }
 
-/// Collect disk metrics from the system
-pub fn collect(_system: &System) -> DiskMetrics {
-    let disks = Disks::new_with_refreshed_list();
-    
-    let mut total: u64 = 0;
-    let mut available: u64 = 0;
+/// Selects which filesystems and block devices are reported
+#[derive(Debug, Clone)]
+pub struct DiskFilter {
+    mountpoints: NameFilter,
+    fs_types: NameFilter,
+    devices: NameFilter,
+}
 
-    // Only report the root (/) partition - this gives the most accurate
-    // representation of available disk space for most use cases
-    for disk in disks.list() {
-        if disk.mount_point() == std::path::Path::new("/") {
-            total = disk.total_space();
-            available = disk.available_space();
-            break;
+impl DiskFilter {
+    /// Build the filter from the `[disk]` config section
+    pub fn from_config(config: &DiskConfig) -> Result<Self> {
+        Ok(Self {
+            mountpoints: NameFilter::new(&config.include_mountpoints, &config.exclude_mountpoints)?,
+            fs_types: NameFilter::new(&config.include_fs_types, &config.exclude_fs_types)?,
+            devices: NameFilter::new(&config.include_devices, &config.exclude_devices)?,
+        })
+    }
+
+    fn matches(&self, mount_point: &str, fs_type: &str) -> bool {
+        self.mountpoints.matches(mount_point) && self.fs_types.matches(fs_type)
+    }
+}
+
+impl Default for DiskFilter {
+    fn default() -> Self {
+        Self::from_config(&DiskConfig::default()).expect("default disk patterns are valid")
+    }
+}
+
+/// Filesystem state carried between collections
+#[derive(Default)]
+pub struct FilesystemState {
+    /// Whether each mountpoint was mounted read-only at the previous collection
+    read_only: HashMap<String, bool>,
+    /// Mountpoints that went from read-write to read-only and haven't recovered
+    remounted_ro: HashSet<String>,
+    /// Last successful usage of each mountpoint, reported again while it is stale
+    last_usage: HashMap<String, FilesystemUsage>,
+    /// Network filesystem probes that timed out and are still blocked
+    #[cfg(target_os = "linux")]
+    hung_probes: HashMap<String, ProbeReceiver>,
+}
+
+impl FilesystemState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the mount's current mode and report whether it flipped to read-only
+    fn remounted_read_only(&mut self, mount_point: &str, read_only: bool) -> bool {
+        let was_read_only = self.read_only.insert(mount_point.to_string(), read_only);
+        if !read_only {
+            self.remounted_ro.remove(mount_point);
+        } else if was_read_only == Some(false) {
+            self.remounted_ro.insert(mount_point.to_string());
         }
+        self.remounted_ro.contains(mount_point)
     }
+}
+
+/// Collect disk metrics from the system
+pub fn collect(
+    _system: &System,
+    filter: &DiskFilter,
+    io_rates: &mut DeviceIoRates,
+    fs_state: &mut FilesystemState,
+) -> DiskMetrics {
+    let filesystems = collect_filesystems(filter, fs_state);
 
+    // Only report the root (/) partition - this gives the most accurate
+    // representation of available disk space for most use cases
+    let (total, available) = root_space();
     let used = total.saturating_sub(available);
-    
     let usage = if total > 0 {
         (used as f64 / total as f64) * 100.0
     } else {
         0.0
     };
 
-    // Get disk I/O stats from /proc/diskstats on Linux
-    let (read_bytes, write_bytes) = read_disk_io_stats();
-    
+    // Get per-device I/O stats from /sys/block on Linux
+    let devices = device_io_metrics(read_device_io_counters(filter), io_rates);
+
+    // Totals only count leaf devices, so I/O through md/dm isn't counted twice
+    let leaves = || devices.iter().filter(|d| d.is_leaf);
+    let read_bytes = leaves().map(|d| d.read_bytes).sum::<u64>();
+    let write_bytes = leaves().map(|d| d.write_bytes).sum::<u64>();
+    let read_bytes_per_sec = sum_rates(leaves().map(|d| d.read_bytes_per_sec));
+    let write_bytes_per_sec = sum_rates(leaves().map(|d| d.write_bytes_per_sec));
+
     DiskMetrics {
         usage,
         total: total as i64,
         used: used as i64,
-        read_bytes,
-        write_bytes,
-    }
-}
-
-/// Read disk I/O statistics from /proc/diskstats (Linux only)
-/// 
-/// /proc/diskstats format (fields are space-separated):
-/// 1. major number
-/// 2. minor number
-/// 3. device name
-/// 4. reads completed successfully
-/// 5. reads merged
-/// 6. sectors read           
-/// 7. time spent reading (ms)
-/// 8. writes completed
-/// 9. writes merged
-/// 10. sectors written       
-/// 11. time spent writing (ms)
-/// ... (more fields in newer kernels)
+        read_bytes: read_bytes as i64,
+        write_bytes: write_bytes as i64,
+        read_bytes_per_sec,
+        write_bytes_per_sec,
+        filesystems,
+        devices,
+    }
+}
+
+/// Size and available space of the root filesystem in bytes
 ///
-/// Each sector is 512 bytes.
+/// Read directly rather than from the filesystem list, so the `[disk]`
+/// filters (which exclude overlay roots by default) don't affect it.
 #[cfg(target_os = "linux")]
-fn read_disk_io_stats() -> (i64, i64) {
-    use std::fs;
-    
-    const SECTOR_SIZE: u64 = 512;
-    
-    let content = match fs::read_to_string("/proc/diskstats") {
+fn root_space() -> (u64, u64) {
+    statvfs("/").map_or((0, 0), |stats| (stats.f_blocks * stats.f_frsize, stats.f_bavail * stats.f_frsize))
+}
+
+/// Fallback for non-Linux systems, using sysinfo's disk list
+#[cfg(not(target_os = "linux"))]
+fn root_space() -> (u64, u64) {
+    let disks = sysinfo::Disks::new_with_refreshed_list();
+    disks
+        .list()
+        .iter()
+        .find(|disk| disk.mount_point() == std::path::Path::new("/"))
+        .map_or((0, 0), |disk| (disk.total_space(), disk.available_space()))
+}
+
+/// Pseudo filesystems that never hold user data
+#[cfg(target_os = "linux")]
+const PSEUDO_FS_TYPES: &[&str] = &[
+    "autofs", "binfmt_misc", "bpf", "cgroup", "cgroup2", "configfs", "debugfs", "devpts",
+    "efivarfs", "fusectl", "hugetlbfs", "mqueue", "nsfs", "proc", "pstore", "ramfs",
+    "rootfs", "rpc_pipefs", "securityfs", "selinuxfs", "sysfs", "tracefs",
+];
+
+/// Network filesystems, whose statvfs can block indefinitely on a hard mount
+#[cfg(target_os = "linux")]
+const NETWORK_FS_TYPES: &[&str] = &[
+    "nfs", "nfs4", "cifs", "smb3", "smbfs", "ceph", "glusterfs", "fuse.glusterfs",
+    "fuse.sshfs", "9p", "afs", "lustre",
+];
+
+/// How long network filesystems may take to answer statvfs before they are
+/// reported as stale; all network mounts are probed at once within this time
+#[cfg(target_os = "linux")]
+const NETWORK_FS_PROBE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// A mount from /proc/self/mounts selected for reporting
+#[cfg(target_os = "linux")]
+struct Mount {
+    device: String,
+    mount_point: String,
+    fs_type: String,
+    read_only: bool,
+}
+
+/// Collect usage of every real mounted filesystem (Linux only)
+///
+/// Reads `/proc/self/mounts` directly rather than going through sysinfo,
+/// which skips network filesystems and hides mount details.
+#[cfg(target_os = "linux")]
+fn collect_filesystems(filter: &DiskFilter, state: &mut FilesystemState) -> Vec<FilesystemUsage> {
+    let content = match std::fs::read_to_string("/proc/self/mounts") {
         Ok(c) => c,
-        Err(_) => return (0, 0),
+        Err(_) => return Vec::new(),
     };
-    
-    let mut total_read_sectors: u64 = 0;
-    let mut total_write_sectors: u64 = 0;
-    
+
+    let mut seen_mount_points = HashSet::new();
+    let mut seen_devices = HashSet::new();
+    let mut mounts = Vec::new();
+
     for line in content.lines() {
+        // Format: device mount_point fs_type options dump pass
         let fields: Vec<&str> = line.split_whitespace().collect();
-        
-        // Need at least 10 fields (up to sectors written)
-        if fields.len() < 10 {
+        if fields.len() < 3 {
             continue;
         }
-        
-        let device_name = fields[2];
-        
-        // Only count main disk devices, not partitions
-        // - nvme0n1 (not nvme0n1p1, nvme0n1p2)
-        // - sda (not sda1, sda2)
-        // - vda (not vda1, vda2)
-        // Skip loop, ram, dm- devices
-        let is_main_disk = (device_name.starts_with("nvme") && !device_name.contains('p'))
-            || (device_name.starts_with("sd") && device_name.len() == 3)
-            || (device_name.starts_with("vd") && device_name.len() == 3)
-            || (device_name.starts_with("xvd") && device_name.len() == 4)
-            || (device_name.starts_with("hd") && device_name.len() == 3);
-        
-        if !is_main_disk {
+
+        let device = unescape_mount_field(fields[0]);
+        let mount_point = unescape_mount_field(fields[1]);
+        let fs_type = fields[2];
+        let read_only = fields
+            .get(3)
+            .is_some_and(|options| options.split(',').any(|option| option == "ro"));
+
+        if PSEUDO_FS_TYPES.contains(&fs_type) || !filter.matches(&mount_point, fs_type) {
             continue;
         }
-        
-        // Field 6 (index 5): sectors read
-        // Field 10 (index 9): sectors written
-        if let (Ok(sectors_read), Ok(sectors_written)) = 
-            (fields[5].parse::<u64>(), fields[9].parse::<u64>()) 
+
+        // A block device mounted more than once (bind mounts) reports the same usage
+        if !seen_mount_points.insert(mount_point.clone())
+            || (device.starts_with('/') && !seen_devices.insert(device.clone()))
         {
-            total_read_sectors += sectors_read;
-            total_write_sectors += sectors_written;
+            continue;
+        }
+
+        mounts.push(Mount {
+            device,
+            mount_point,
+            fs_type: fs_type.to_string(),
+            read_only,
+        });
+    }
+
+    // Start every network probe before waiting on any, so hung mounts share one timeout
+    let deadline = std::time::Instant::now() + NETWORK_FS_PROBE_TIMEOUT;
+    let mut probes: HashMap<String, _> = mounts
+        .iter()
+        .filter(|mount| NETWORK_FS_TYPES.contains(&mount.fs_type.as_str()))
+        .map(|mount| (mount.mount_point.clone(), start_probe(state, &mount.mount_point)))
+        .collect();
+
+    let mut filesystems = Vec::new();
+    for mount in mounts {
+        let remounted_ro = state.remounted_read_only(&mount.mount_point, mount.read_only);
+
+        let stats = match probes.remove(&mount.mount_point) {
+            Some(probe) => probe.and_then(|receiver| wait_for_probe(state, &mount.mount_point, receiver, deadline)),
+            None => statvfs(&mount.mount_point).ok_or(FilesystemHealth::Error),
+        };
+
+        let stats = match stats {
+            Ok(stats) => stats,
+            Err(health) => {
+                // Keep reporting the mount so the failure shows up instead of a
+                // gap, with its last known usage if it ever answered
+                let last = state.last_usage.get(&mount.mount_point).cloned().unwrap_or_else(|| {
+                    filesystem_usage(mount.mount_point, mount.device, mount.fs_type, 0, 0, 0)
+                });
+                filesystems.push(FilesystemUsage {
+                    read_only: mount.read_only,
+                    health,
+                    ..last
+                });
+                continue;
+            }
+        };
+
+        let block_size = stats.f_frsize;
+        let total = stats.f_blocks * block_size;
+        if total == 0 {
+            continue;
+        }
+        let free = stats.f_bfree * block_size;
+        let available = stats.f_bavail * block_size;
+
+        let mut usage = filesystem_usage(
+            mount.mount_point,
+            mount.device,
+            mount.fs_type,
+            total,
+            total.saturating_sub(free),
+            available,
+        );
+        set_inode_usage(&mut usage, stats.f_files, stats.f_ffree);
+        usage.read_only = mount.read_only;
+        if remounted_ro {
+            usage.health = FilesystemHealth::RemountedReadOnly;
+        }
+        state.last_usage.insert(usage.mount_point.clone(), usage.clone());
+        filesystems.push(usage);
+    }
+
+    // Forget mounts that are gone so a later mount at the same path starts fresh
+    state.read_only.retain(|mount_point, _| seen_mount_points.contains(mount_point));
+    state.remounted_ro.retain(|mount_point| seen_mount_points.contains(mount_point));
+    state.last_usage.retain(|mount_point, _| seen_mount_points.contains(mount_point));
+    state.hung_probes.retain(|mount_point, _| seen_mount_points.contains(mount_point));
+
+    filesystems
+}
+
+/// Result of a statvfs probe running on its own thread
+#[cfg(target_os = "linux")]
+type ProbeReceiver = std::sync::mpsc::Receiver<Option<libc::statvfs>>;
+
+/// Start statvfs on a separate thread
+///
+/// A hung hard NFS mount blocks statvfs in uninterruptible sleep, so the
+/// probe thread may never return. While it is stuck no new probe is started
+/// for that mount, which keeps at most one blocked thread per mount.
+#[cfg(target_os = "linux")]
+fn start_probe(state: &mut FilesystemState, mount_point: &str) -> Result<ProbeReceiver, FilesystemHealth> {
+    use std::sync::mpsc::{self, TryRecvError};
+
+    if let Some(pending) = state.hung_probes.get(mount_point) {
+        match pending.try_recv() {
+            Err(TryRecvError::Empty) => return Err(FilesystemHealth::Stale),
+            // The stuck probe finished; probe again for fresh numbers
+            Ok(_) | Err(TryRecvError::Disconnected) => {
+                state.hung_probes.remove(mount_point);
+            }
+        }
+    }
+
+    let (sender, receiver) = mpsc::channel();
+    let path = mount_point.to_string();
+    std::thread::Builder::new()
+        .name("statvfs-probe".to_string())
+        .spawn(move || {
+            let _ = sender.send(statvfs(&path));
+        })
+        .map_err(|_| FilesystemHealth::Error)?;
+    Ok(receiver)
+}
+
+/// Wait until the deadline for a probe to answer
+///
+/// A probe that doesn't answer in time is kept, so its mount is reported as
+/// stale without a new probe until it finally returns.
+#[cfg(target_os = "linux")]
+fn wait_for_probe(
+    state: &mut FilesystemState,
+    mount_point: &str,
+    receiver: ProbeReceiver,
+    deadline: std::time::Instant,
+) -> Result<libc::statvfs, FilesystemHealth> {
+    use std::sync::mpsc::RecvTimeoutError;
+
+    match receiver.recv_timeout(deadline.saturating_duration_since(std::time::Instant::now())) {
+        Ok(stats) => stats.ok_or(FilesystemHealth::Error),
+        Err(RecvTimeoutError::Timeout) => {
+            state.hung_probes.insert(mount_point.to_string(), receiver);
+            Err(FilesystemHealth::Stale)
         }
+        Err(RecvTimeoutError::Disconnected) => Err(FilesystemHealth::Error),
     }
-    
-    (
-        (total_read_sectors * SECTOR_SIZE) as i64,
-        (total_write_sectors * SECTOR_SIZE) as i64,
-    )
+}
+
+/// Fallback for non-Linux systems, using sysinfo's disk list
+#[cfg(not(target_os = "linux"))]
+fn collect_filesystems(filter: &DiskFilter, _state: &mut FilesystemState) -> Vec<FilesystemUsage> {
+    let disks = sysinfo::Disks::new_with_refreshed_list();
+
+    disks
+        .list()
+        .iter()
+        .filter_map(|disk| {
+            let mount_point = disk.mount_point().to_string_lossy().to_string();
+            let fs_type = disk.file_system().to_string_lossy().to_string();
+            if !filter.matches(&mount_point, &fs_type) || disk.total_space() == 0 {
+                return None;
+            }
+
+            let total = disk.total_space();
+            let available = disk.available_space();
+            Some(filesystem_usage(
+                mount_point,
+                disk.name().to_string_lossy().to_string(),
+                fs_type,
+                total,
+                total.saturating_sub(available),
+                available,
+            ))
+        })
+        .collect()
+}
+
+/// Build a usage entry; usage % is relative to the space usable by non-root
+/// users (like `df`), so it reaches 100% when `available` hits zero
+fn filesystem_usage(
+    mount_point: String,
+    device: String,
+    fs_type: String,
+    total: u64,
+    used: u64,
+    available: u64,
+) -> FilesystemUsage {
+    let usable = used + available;
+    let usage = if usable > 0 {
+        (used as f64 / usable as f64) * 100.0
+    } else {
+        0.0
+    };
+
+    FilesystemUsage {
+        mount_point,
+        device,
+        fs_type,
+        total: total as i64,
+        used: used as i64,
+        available: available as i64,
+        usage,
+        inodes_total: None,
+        inodes_used: None,
+        inodes_free: None,
+        inode_usage: None,
+        read_only: false,
+        health: FilesystemHealth::Ok,
+    }
+}
+
+/// Fill in inode counts from statvfs
+///
+/// Filesystems that allocate inodes dynamically (btrfs, vfat, ...) report a
+/// total of zero; their inode fields stay empty.
+#[cfg(target_os = "linux")]
+fn set_inode_usage(usage: &mut FilesystemUsage, total: u64, free: u64) {
+    if total == 0 {
+        return;
+    }
+    let used = total.saturating_sub(free);
+    usage.inodes_total = Some(total);
+    usage.inodes_used = Some(used);
+    usage.inodes_free = Some(free);
+    usage.inode_usage = Some((used as f64 / total as f64) * 100.0);
+}
+
+/// Decode the octal escapes (e.g. `\040` for space) used in /proc/self/mounts
+#[cfg(target_os = "linux")]
+fn unescape_mount_field(field: &str) -> String {
+    field
+        .replace("\\040", " ")
+        .replace("\\011", "\t")
+        .replace("\\012", "\n")
+        .replace("\\134", "\\")
+}
+
+/// Query filesystem statistics for a path
+#[cfg(target_os = "linux")]
+fn statvfs(path: &str) -> Option<libc::statvfs> {
+    let c_path = std::ffi::CString::new(path).ok()?;
+    // SAFETY: statvfs only writes into the zeroed struct we pass it
+    let mut stats: libc::statvfs = unsafe { std::mem::zeroed() };
+    let ret = unsafe { libc::statvfs(c_path.as_ptr(), &mut stats) };
+    (ret == 0).then_some(stats)
+}
+
+/// Cumulative I/O counters of a block device
+#[derive(Debug, Clone, Default)]
+pub struct DeviceIoCounters {
+    pub name: String,
+    /// Device number ("major:minor"), which changes when a device is recreated
+    pub dev: String,
+    /// Whether the device sits directly on hardware (no slaves), rather than
+    /// being a stacked md/dm device whose I/O is already counted below it
+    pub is_leaf: bool,
+    pub reads_completed: u64,
+    pub reads_merged: u64,
+    pub read_bytes: u64,
+    pub read_time_ms: u64,
+    pub writes_completed: u64,
+    pub writes_merged: u64,
+    pub write_bytes: u64,
+    pub write_time_ms: u64,
+    pub in_flight: u64,
+    pub io_time_ms: u64,
+}
+
+/// Counters of a device that are turned into rates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IoCounter {
+    ReadsCompleted,
+    ReadBytes,
+    ReadTimeMs,
+    WritesCompleted,
+    WriteBytes,
+    WriteTimeMs,
+    IoTimeMs,
+}
+
+impl Counter for IoCounter {
+    fn width(&self) -> CounterWidth {
+        match self {
+            IoCounter::ReadsCompleted | IoCounter::WritesCompleted => CounterWidth::LONG,
+            // Sector counts scaled to bytes
+            IoCounter::ReadBytes | IoCounter::WriteBytes => CounterWidth::Unbounded,
+            // Printed as unsigned int milliseconds
+            IoCounter::ReadTimeMs | IoCounter::WriteTimeMs | IoCounter::IoTimeMs => CounterWidth::U32,
+        }
+    }
+}
+
+/// Previous device counters, keyed by device name and number
+pub type DeviceIoRates = CounterRates<(String, String, IoCounter)>;
+
+/// Build per-device I/O metrics, deriving IOPS, await and utilization from
+/// the previous sample
+pub fn device_io_metrics(devices: Vec<DeviceIoCounters>, rates: &mut DeviceIoRates) -> Vec<DiskIoMetrics> {
+    let current = devices.iter().flat_map(|d| {
+        [
+            (IoCounter::ReadsCompleted, d.reads_completed),
+            (IoCounter::ReadBytes, d.read_bytes),
+            (IoCounter::ReadTimeMs, d.read_time_ms),
+            (IoCounter::WritesCompleted, d.writes_completed),
+            (IoCounter::WriteBytes, d.write_bytes),
+            (IoCounter::WriteTimeMs, d.write_time_ms),
+            (IoCounter::IoTimeMs, d.io_time_ms),
+        ]
+        .map(|(counter, value)| ((d.name.clone(), d.dev.clone(), counter), value))
+    });
+    let per_second = rates.update(current);
+
+    devices
+        .into_iter()
+        .map(|d| {
+            let rate = |counter| per_second.get(&(d.name.clone(), d.dev.clone(), counter)).copied();
+
+            let read_iops = rate(IoCounter::ReadsCompleted);
+            let write_iops = rate(IoCounter::WritesCompleted);
+
+            DiskIoMetrics {
+                read_iops,
+                write_iops,
+                read_bytes_per_sec: rate(IoCounter::ReadBytes),
+                write_bytes_per_sec: rate(IoCounter::WriteBytes),
+                read_await_ms: await_ms(rate(IoCounter::ReadTimeMs), read_iops),
+                write_await_ms: await_ms(rate(IoCounter::WriteTimeMs), write_iops),
+                // io_ticks grows by up to 1000ms per second while the device is busy
+                utilization: rate(IoCounter::IoTimeMs).map(|busy| (busy / 10.0).min(100.0)),
+                is_leaf: d.is_leaf,
+                device: d.name,
+                reads_completed: d.reads_completed,
+                reads_merged: d.reads_merged,
+                read_bytes: d.read_bytes,
+                read_time_ms: d.read_time_ms,
+                writes_completed: d.writes_completed,
+                writes_merged: d.writes_merged,
+                write_bytes: d.write_bytes,
+                write_time_ms: d.write_time_ms,
+                in_flight: d.in_flight,
+                io_time_ms: d.io_time_ms,
+            }
+        })
+        .collect()
+}
+
+/// Average time per completed request, in ms (like `iostat`'s await)
+fn await_ms(time_ms_per_sec: Option<f64>, ops_per_sec: Option<f64>) -> Option<f64> {
+    let (time, ops) = (time_ms_per_sec?, ops_per_sec?);
+    Some(if ops > 0.0 { time / ops } else { 0.0 })
+}
+
+/// Read per-device I/O counters for the block devices in /sys/block (Linux only)
+///
+/// /sys/block only lists whole devices (never partitions), including md,
+/// dm, nbd and mmcblk devices. Devices with a size of zero (unattached
+/// loop or nbd devices) are skipped.
+///
+/// /sys/block/<dev>/stat format (fields are space-separated):
+/// 1. reads completed
+/// 2. reads merged
+/// 3. sectors read
+/// 4. time spent reading (ms)
+/// 5. writes completed
+/// 6. writes merged
+/// 7. sectors written
+/// 8. time spent writing (ms)
+/// 9. I/Os currently in progress
+/// 10. time spent doing I/Os (ms), followed by more fields in newer kernels
+///
+/// Each sector is 512 bytes, regardless of the device's block size.
+#[cfg(target_os = "linux")]
+pub fn read_device_io_counters(filter: &DiskFilter) -> Vec<DeviceIoCounters> {
+    use std::fs;
+    use std::path::Path;
+
+    let entries = match fs::read_dir("/sys/block") {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut devices = Vec::new();
+
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !filter.devices.matches(&name) {
+            continue;
+        }
+
+        let dir = Path::new("/sys/block").join(&name);
+
+        let size = fs::read_to_string(dir.join("size"))
+            .ok()
+            .and_then(|s| s.trim().parse::<u64>().ok())
+            .unwrap_or(0);
+        if size == 0 {
+            continue;
+        }
+
+        let Some(counters) = fs::read_to_string(dir.join("stat"))
+            .ok()
+            .and_then(|stat| parse_block_stat(&stat))
+        else {
+            continue;
+        };
+
+        let is_leaf = fs::read_dir(dir.join("slaves"))
+            .map(|mut slaves| slaves.next().is_none())
+            .unwrap_or(true);
+
+        let dev = fs::read_to_string(dir.join("dev"))
+            .map(|dev| dev.trim().to_string())
+            .unwrap_or_default();
+
+        devices.push(DeviceIoCounters {
+            name,
+            dev,
+            is_leaf,
+            ..counters
+        });
+    }
+
+    devices.sort_by(|a, b| a.name.cmp(&b.name));
+    devices
+}
+
+/// Parse the counters in a block device's stat file
+///
+/// Returns `None` with fewer than 10 fields (up to time spent doing I/Os).
+#[cfg(target_os = "linux")]
+fn parse_block_stat(stat: &str) -> Option<DeviceIoCounters> {
+    const SECTOR_SIZE: u64 = 512;
+
+    let fields: Vec<u64> = stat
+        .split_whitespace()
+        .map(|f| f.parse().unwrap_or(0))
+        .collect();
+    if fields.len() < 10 {
+        return None;
+    }
+
+    Some(DeviceIoCounters {
+        reads_completed: fields[0],
+        reads_merged: fields[1],
+        read_bytes: fields[2].wrapping_mul(SECTOR_SIZE),
+        read_time_ms: fields[3],
+        writes_completed: fields[4],
+        writes_merged: fields[5],
+        write_bytes: fields[6].wrapping_mul(SECTOR_SIZE),
+        write_time_ms: fields[7],
+        in_flight: fields[8],
+        io_time_ms: fields[9],
+        ..Default::default()
+    })
 }
 
 /// Fallback for non-Linux systems
 #[cfg(not(target_os = "linux"))]
-fn read_disk_io_stats() -> (i64, i64) {
+pub fn read_device_io_counters(_filter: &DiskFilter) -> Vec<DeviceIoCounters> {
     // Not supported on macOS/Windows via this method
-    (0, 0)
+    Vec::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn parses_block_stat() {
+        // Linux 5.5+ appends discard and flush counters
+        let stat = "    9326     5948  3026018    12031    22137    31657 26069328    77511        0    14696    89542        0        0        0        0     1200     7     \n";
+        let counters = parse_block_stat(stat).unwrap();
+        assert_eq!(counters.reads_completed, 9326);
+        assert_eq!(counters.reads_merged, 5948);
+        assert_eq!(counters.read_bytes, 3026018 * 512);
+        assert_eq!(counters.read_time_ms, 12031);
+        assert_eq!(counters.writes_completed, 22137);
+        assert_eq!(counters.write_bytes, 26069328 * 512);
+        assert_eq!(counters.write_time_ms, 77511);
+        assert_eq!(counters.in_flight, 0);
+        assert_eq!(counters.io_time_ms, 14696);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn block_stat_needs_ten_fields() {
+        assert!(parse_block_stat("1 2 3 4 5 6 7 8 9\n").is_none());
+        assert!(parse_block_stat("").is_none());
+    }
+
+    #[test]
+    fn await_is_time_per_request() {
+        assert_eq!(await_ms(Some(50.0), Some(10.0)), Some(5.0));
+        assert_eq!(await_ms(Some(0.0), Some(0.0)), Some(0.0));
+        assert_eq!(await_ms(None, Some(10.0)), None);
+    }
 }
@@ -0,0 +1,928 @@
+//! Docker container collector
+//!
+//! Talks to the Docker Engine API over its Unix socket and reports every
+//! container with its state, restart count, health and resource usage, plus
+//! the start/stop/die events since the previous collection. The socket path
+//! is configurable, so rootless Docker, Podman's Docker-compatible socket or a
+//! stand-in server all work. Access to the socket is equivalent to root;
+//! the agent only ever reads from it.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use super::rate::{Counter, CounterRates, CounterWidth};
+use super::Availability;
+use crate::config::DockerConfig;
+use crate::models::{ContainerEvent, ContainerMetrics, ServiceInfo, ServiceStatus, ServiceType};
+
+/// Container events that are reported
+const CONTAINER_EVENTS: &[&str] = &["start", "stop", "die"];
+
+/// Read-only client for the Docker Engine API on a Unix socket
+///
+/// Requests are unversioned, so the daemon answers with its own API version.
+/// Each request uses a new connection. `timeout` bounds a whole collection,
+/// not each request, since a collection makes two requests per container.
+#[derive(Debug, Clone)]
+pub struct DockerClient {
+    socket: PathBuf,
+    timeout: Duration,
+}
+
+impl DockerClient {
+    pub fn new(socket: impl Into<PathBuf>, timeout: Duration) -> Self {
+        Self {
+            socket: socket.into(),
+            timeout,
+        }
+    }
+
+    /// Build the client from the `[docker]` config section
+    pub fn from_config(config: &DockerConfig) -> Self {
+        Self::new(&config.socket_path, Duration::from_secs(config.timeout_seconds))
+    }
+
+    /// When a collection starting now has to be done
+    fn deadline(&self) -> Instant {
+        Instant::now() + self.timeout
+    }
+
+    /// GET a JSON document from the API
+    fn get<T: DeserializeOwned>(&self, path: &str, deadline: Instant) -> Result<T> {
+        let body = self.request(path, deadline)?;
+        serde_json::from_slice(&body).with_context(|| format!("Invalid response from Docker for {}", path))
+    }
+
+    /// Send a GET request and return the response body, giving up at `deadline`
+    #[cfg(unix)]
+    fn request(&self, path: &str, deadline: Instant) -> Result<Vec<u8>> {
+        use std::io::{Read, Write};
+        use std::os::unix::net::UnixStream;
+
+        let remaining = || {
+            deadline
+                .checked_duration_since(Instant::now())
+                .filter(|remaining| !remaining.is_zero())
+                .with_context(|| format!("Timeout after {}s waiting for Docker", self.timeout.as_secs()))
+        };
+
+        remaining()?;
+        let mut stream = UnixStream::connect(&self.socket)
+            .with_context(|| format!("Failed to connect to {}", self.socket.display()))?;
+        stream.set_write_timeout(Some(remaining()?))?;
+
+        write!(
+            stream,
+            "GET {} HTTP/1.1\r\nHost: docker\r\nUser-Agent: nubilus-agent/{}\r\nConnection: close\r\n\r\n",
+            path,
+            env!("CARGO_PKG_VERSION")
+        )?;
+        let mut response = Vec::new();
+        let mut buf = [0u8; 8192];
+        loop {
+            // A daemon trickling data can't keep the read going past the deadline
+            stream.set_read_timeout(Some(remaining()?))?;
+            let len = stream
+                .read(&mut buf)
+                .with_context(|| format!("Failed to read Docker response for {}", path))?;
+            if len == 0 {
+                break;
+            }
+            response.extend_from_slice(&buf[..len]);
+        }
+
+        let (status, body) = parse_response(&response)?;
+        if !(200..300).contains(&status) {
+            // Errors come as {"message": "..."}
+            let message = serde_json::from_slice::<serde_json::Value>(&body)
+                .ok()
+                .and_then(|value| value.get("message")?.as_str().map(str::to_string))
+                .unwrap_or_default();
+            anyhow::bail!("Docker returned {} for {}: {}", status, path, message);
+        }
+        Ok(body)
+    }
+
+    /// Fallback for systems without Unix sockets
+    #[cfg(not(unix))]
+    fn request(&self, _path: &str, _deadline: Instant) -> Result<Vec<u8>> {
+        anyhow::bail!("Docker monitoring needs Unix sockets")
+    }
+}
+
+/// Split an HTTP/1.1 response into its status code and decoded body
+fn parse_response(response: &[u8]) -> Result<(u16, Vec<u8>)> {
+    let header_end = response
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .context("Incomplete HTTP response from Docker")?;
+    let head = String::from_utf8_lossy(&response[..header_end]);
+    let body = &response[header_end + 4..];
+
+    let mut lines = head.lines();
+    // "HTTP/1.1 200 OK"
+    let status = lines
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|code| code.parse().ok())
+        .context("Invalid HTTP status line from Docker")?;
+
+    let mut chunked = false;
+    let mut content_length = None;
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("transfer-encoding") {
+            chunked = value.eq_ignore_ascii_case("chunked");
+        } else if name.eq_ignore_ascii_case("content-length") {
+            content_length = value.parse::<usize>().ok();
+        }
+    }
+
+    let body = if chunked {
+        decode_chunked(body)?
+    } else {
+        let len = content_length.unwrap_or(body.len()).min(body.len());
+        body[..len].to_vec()
+    };
+    Ok((status, body))
+}
+
+/// Decode a `Transfer-Encoding: chunked` body
+fn decode_chunked(mut data: &[u8]) -> Result<Vec<u8>> {
+    let mut body = Vec::new();
+    loop {
+        let line_end = data
+            .windows(2)
+            .position(|window| window == b"\r\n")
+            .context("Truncated chunked response from Docker")?;
+        let size_line = String::from_utf8_lossy(&data[..line_end]);
+        // Chunk extensions after ';' are ignored
+        let size_hex = size_line.split(';').next().unwrap_or_default().trim();
+        let size = usize::from_str_radix(size_hex, 16).context("Invalid chunk size from Docker")?;
+        data = &data[line_end + 2..];
+        if size == 0 {
+            return Ok(body);
+        }
+        let chunk = data.get(..size).context("Truncated chunked response from Docker")?;
+        body.extend_from_slice(chunk);
+        data = data.get(size + 2..).unwrap_or_default();
+    }
+}
+
+/// Entry of `GET /containers/json`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ContainerSummary {
+    id: String,
+    #[serde(default)]
+    names: Vec<String>,
+    #[serde(default)]
+    image: String,
+    /// created, running, paused, restarting, removing, exited or dead
+    #[serde(default)]
+    state: String,
+    /// Human-readable status, e.g. "Up 2 hours (healthy)" or "Exited (1) 5 minutes ago"
+    #[serde(default)]
+    status: String,
+    #[serde(default)]
+    ports: Vec<PortBinding>,
+}
+
+impl ContainerSummary {
+    /// Container name without the leading '/'
+    fn name(&self) -> String {
+        self.names
+            .first()
+            .map(|name| name.trim_start_matches('/').to_string())
+            .unwrap_or_else(|| short_id(&self.id))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct PortBinding {
+    public_port: Option<u16>,
+}
+
+/// The parts of `GET /containers/{id}/json` that are reported
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ContainerInspect {
+    #[serde(default)]
+    restart_count: u64,
+    state: InspectState,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct InspectState {
+    exit_code: Option<i64>,
+    started_at: Option<String>,
+    health: Option<InspectHealth>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct InspectHealth {
+    /// starting, healthy or unhealthy
+    status: String,
+}
+
+/// The parts of `GET /containers/{id}/stats` that are reported
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ContainerStats {
+    cpu_stats: CpuStats,
+    memory_stats: MemoryStats,
+    networks: Option<HashMap<String, NetworkStats>>,
+    blkio_stats: BlkioStats,
+    pids_stats: PidsStats,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct CpuStats {
+    cpu_usage: CpuUsage,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct CpuUsage {
+    /// Nanoseconds of CPU time
+    total_usage: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct MemoryStats {
+    usage: Option<u64>,
+    limit: Option<u64>,
+    stats: HashMap<String, u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct NetworkStats {
+    rx_bytes: u64,
+    tx_bytes: u64,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct BlkioStats {
+    io_service_bytes_recursive: Option<Vec<BlkioEntry>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct BlkioEntry {
+    /// "read"/"write" on cgroup v2, "Read"/"Write" on v1
+    op: String,
+    value: u64,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct PidsStats {
+    current: Option<u64>,
+}
+
+/// Entry of `GET /events`
+#[derive(Debug, Deserialize)]
+struct Event {
+    #[serde(rename = "Action", default)]
+    action: String,
+    #[serde(rename = "Actor")]
+    actor: EventActor,
+    #[serde(rename = "timeNano")]
+    time_nano: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct EventActor {
+    #[serde(rename = "ID")]
+    id: String,
+    #[serde(rename = "Attributes", default)]
+    attributes: HashMap<String, String>,
+}
+
+/// Cumulative per-container counters that are turned into rates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ContainerCounter {
+    CpuNanos,
+    NetworkRxBytes,
+    NetworkTxBytes,
+    BlockReadBytes,
+    BlockWriteBytes,
+}
+
+impl Counter for ContainerCounter {
+    /// Docker sums the kernel counters across CPUs, interfaces and devices
+    fn width(&self) -> CounterWidth {
+        CounterWidth::Unbounded
+    }
+}
+
+/// Previous counters of each container
+pub type ContainerRates = CounterRates<(String, ContainerCounter)>;
+
+/// State carried between collections
+pub struct DockerState {
+    client: DockerClient,
+    rates: ContainerRates,
+    /// End of the window of the last successful events request
+    events_since: DateTime<Utc>,
+    /// Whether the daemon answered last time, so only changes are logged
+    availability: Availability,
+}
+
+impl DockerState {
+    /// Start monitoring; events are reported from now on
+    pub fn new(client: DockerClient) -> Self {
+        Self {
+            client,
+            rates: ContainerRates::new(),
+            events_since: Utc::now(),
+            availability: Availability::default(),
+        }
+    }
+}
+
+/// Containers and container events since the previous collection
+#[derive(Default)]
+pub struct DockerMetrics {
+    pub containers: Vec<ContainerMetrics>,
+    pub events: Vec<ContainerEvent>,
+}
+
+/// Collect all containers and the events since the previous call
+///
+/// All requests share one deadline. When the daemon can't be reached nothing
+/// is reported; a warning is logged once until it's back. Containers whose
+/// details didn't arrive in time are reported without them.
+pub fn collect(state: &mut DockerState) -> DockerMetrics {
+    let deadline = state.client.deadline();
+    // Events first, as a slow daemon may not get through every container
+    let result = collect_events(state, deadline).and_then(|(events, until)| {
+        let containers = collect_containers(state, deadline)?;
+        state.events_since = until;
+        Ok(DockerMetrics { containers, events })
+    });
+    state.availability.update("Docker", result).unwrap_or_default()
+}
+
+/// List every container with details and, for running ones, resource usage
+fn collect_containers(state: &mut DockerState, deadline: Instant) -> Result<Vec<ContainerMetrics>> {
+    let summaries: Vec<ContainerSummary> = state.client.get("/containers/json?all=true", deadline)?;
+
+    let mut counters = Vec::new();
+    let mut containers = Vec::new();
+    for summary in summaries {
+        // A container removed since the listing simply has no details
+        let inspect: Option<ContainerInspect> =
+            state.client.get(&format!("/containers/{}/json", summary.id), deadline).ok();
+        // one-shot skips the second sample the daemon would otherwise wait a second for
+        let stats: Option<ContainerStats> = if summary.state == "running" {
+            state
+                .client
+                .get(
+                    &format!("/containers/{}/stats?stream=false&one-shot=true", summary.id),
+                    deadline,
+                )
+                .ok()
+        } else {
+            None
+        };
+
+        let mut container = ContainerMetrics {
+            id: short_id(&summary.id),
+            name: summary.name(),
+            image: summary.image.clone(),
+            state: summary.state.clone(),
+            status: summary.status.clone(),
+            restart_count: inspect.as_ref().map(|i| i.restart_count),
+            health: inspect
+                .as_ref()
+                .and_then(|i| i.state.health.as_ref())
+                .map(|h| h.status.clone()),
+            exit_code: inspect
+                .as_ref()
+                .and_then(|i| i.state.exit_code)
+                .filter(|_| summary.state == "exited" || summary.state == "dead"),
+            started_at: inspect
+                .as_ref()
+                .and_then(|i| i.state.started_at.as_deref())
+                .and_then(|started| DateTime::parse_from_rfc3339(started).ok())
+                .map(|started| started.with_timezone(&Utc))
+                // Never-started containers report year 1
+                .filter(|started| started.timestamp() > 0),
+            cpu_usage_percent: None,
+            memory_usage_bytes: None,
+            memory_limit: None,
+            memory_usage: None,
+            network_rx_bytes_per_sec: None,
+            network_tx_bytes_per_sec: None,
+            block_read_bytes_per_sec: None,
+            block_write_bytes_per_sec: None,
+            pids: None,
+        };
+
+        if let Some(stats) = stats {
+            let key = |counter| (summary.id.clone(), counter);
+            if let Some(total) = stats.cpu_stats.cpu_usage.total_usage {
+                counters.push((key(ContainerCounter::CpuNanos), total));
+            }
+            if let Some(networks) = &stats.networks {
+                let rx = networks.values().map(|n| n.rx_bytes).sum();
+                let tx = networks.values().map(|n| n.tx_bytes).sum();
+                counters.push((key(ContainerCounter::NetworkRxBytes), rx));
+                counters.push((key(ContainerCounter::NetworkTxBytes), tx));
+            }
+            if let Some(entries) = &stats.blkio_stats.io_service_bytes_recursive {
+                let total = |op: &str| {
+                    entries
+                        .iter()
+                        .filter(|entry| entry.op.eq_ignore_ascii_case(op))
+                        .map(|entry| entry.value)
+                        .sum()
+                };
+                counters.push((key(ContainerCounter::BlockReadBytes), total("read")));
+                counters.push((key(ContainerCounter::BlockWriteBytes), total("write")));
+            }
+
+            // Page cache that can be reclaimed doesn't count, like `docker stats`
+            let memory = &stats.memory_stats;
+            let inactive_file = memory
+                .stats
+                .get("inactive_file")
+                .or_else(|| memory.stats.get("total_inactive_file"))
+                .copied()
+                .unwrap_or(0);
+            container.memory_usage_bytes = memory.usage.map(|usage| usage.saturating_sub(inactive_file));
+            container.memory_limit = memory.limit.filter(|&limit| limit > 0);
+            container.memory_usage = match (container.memory_usage_bytes, container.memory_limit) {
+                (Some(used), Some(limit)) => Some((used as f64 / limit as f64) * 100.0),
+                _ => None,
+            };
+            container.pids = stats.pids_stats.current;
+        }
+
+        containers.push((summary.id, container));
+    }
+
+    let rates = state.rates.update(counters);
+    Ok(containers
+        .into_iter()
+        .map(|(id, mut container)| {
+            let rate = |counter| rates.get(&(id.clone(), counter)).copied();
+            // nanoseconds per second, as a percentage of one CPU
+            container.cpu_usage_percent = rate(ContainerCounter::CpuNanos).map(|ns| ns / 10_000_000.0);
+            container.network_rx_bytes_per_sec = rate(ContainerCounter::NetworkRxBytes);
+            container.network_tx_bytes_per_sec = rate(ContainerCounter::NetworkTxBytes);
+            container.block_read_bytes_per_sec = rate(ContainerCounter::BlockReadBytes);
+            container.block_write_bytes_per_sec = rate(ContainerCounter::BlockWriteBytes);
+            container
+        })
+        .collect())
+}
+
+/// Read container start/stop/die events since the previous collection
+///
+/// The request is bounded with `until`, so the daemon returns the buffered
+/// events and closes the stream instead of following it. Returns the events
+/// and the end of the window, where the next collection picks up.
+fn collect_events(state: &DockerState, deadline: Instant) -> Result<(Vec<ContainerEvent>, DateTime<Utc>)> {
+    let since = state.events_since;
+    let until = Utc::now();
+    let filters = format!(
+        r#"{{"type":["container"],"event":[{}]}}"#,
+        CONTAINER_EVENTS
+            .iter()
+            .map(|event| format!("\"{}\"", event))
+            .collect::<Vec<_>>()
+            .join(",")
+    );
+    let path = format!(
+        "/events?since={}&until={}&filters={}",
+        api_timestamp(since),
+        api_timestamp(until),
+        percent_encode(&filters)
+    );
+    let body = state.client.request(&path, deadline)?;
+
+    let since_nanos = since.timestamp_nanos_opt().unwrap_or(i64::MIN);
+    let until_nanos = until.timestamp_nanos_opt().unwrap_or(i64::MAX);
+    let mut events = Vec::new();
+    // The body is a sequence of JSON objects, one per event
+    for event in serde_json::Deserializer::from_slice(&body).into_iter::<Event>() {
+        let event = event.context("Invalid event from Docker")?;
+        // Both ends of the window are inclusive; don't report an event twice
+        if event.time_nano <= since_nanos || event.time_nano > until_nanos {
+            continue;
+        }
+        if !CONTAINER_EVENTS.contains(&event.action.as_str()) {
+            continue;
+        }
+        let attribute = |name: &str| event.actor.attributes.get(name).cloned();
+        events.push(ContainerEvent {
+            time: DateTime::from_timestamp_nanos(event.time_nano),
+            container_id: short_id(&event.actor.id),
+            name: attribute("name").unwrap_or_else(|| short_id(&event.actor.id)),
+            image: attribute("image"),
+            action: event.action.clone(),
+            exit_code: attribute("exitCode").and_then(|code| code.parse().ok()),
+        });
+    }
+
+    Ok((events, until))
+}
+
+/// List containers as services for the server's service inventory
+///
+/// Containers are named after the container and carry their lowest published
+/// port, if any. Running containers that fail their health check, containers
+/// that exited with an error, and restarting or dead ones are reported as
+/// errors.
+pub fn discover(client: &DockerClient) -> Result<Vec<ServiceInfo>> {
+    let summaries: Vec<ContainerSummary> = client.get("/containers/json?all=true", client.deadline())?;
+
+    let mut services: Vec<ServiceInfo> = summaries
+        .iter()
+        .map(|summary| ServiceInfo {
+            service_type: ServiceType::Docker,
+            name: summary.name(),
+            port: summary.ports.iter().filter_map(|p| p.public_port).min(),
+            status: service_status(summary),
+        })
+        .collect();
+    services.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(services)
+}
+
+/// Map a container's state to a service status
+fn service_status(summary: &ContainerSummary) -> ServiceStatus {
+    match summary.state.as_str() {
+        "running" if summary.status.contains("(unhealthy)") => ServiceStatus::Error,
+        "running" => ServiceStatus::Running,
+        "restarting" | "dead" => ServiceStatus::Error,
+        // "Exited (137) 5 minutes ago"
+        "exited" => {
+            let exit_code = summary
+                .status
+                .split_once('(')
+                .and_then(|(_, rest)| rest.split_once(')'))
+                .and_then(|(code, _)| code.parse::<i64>().ok());
+            match exit_code {
+                Some(0) | None => ServiceStatus::Stopped,
+                Some(_) => ServiceStatus::Error,
+            }
+        }
+        _ => ServiceStatus::Stopped,
+    }
+}
+
+/// The 12-character container ID shown by the docker CLI
+fn short_id(id: &str) -> String {
+    id.chars().take(12).collect()
+}
+
+/// Format a time as the API's `<seconds>.<nanoseconds>` timestamp
+fn api_timestamp(time: DateTime<Utc>) -> String {
+    format!("{}.{:09}", time.timestamp(), time.timestamp_subsec_nanos())
+}
+
+/// Percent-encode a query parameter value
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::os::unix::net::UnixListener;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+
+    const WEB_ID: &str = "4f2a6c1b9e8d7f6a5b4c3d2e1f0a9b8c7d6e5f4a3b2c1d0e9f8a7b6c5d4e3f2a";
+    const WORKER_ID: &str = "9b8c7d6e5f4a3b2c1d0e9f8a7b6c5d4e3f2a4f2a6c1b9e8d7f6a5b4c3d2e1f0a";
+
+    /// Serve `handler(path)` on a Unix socket, one connection at a time
+    fn serve(
+        timeout: Duration,
+        handler: impl Fn(&str) -> Vec<u8> + Send + 'static,
+    ) -> (tempfile::TempDir, DockerClient) {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("docker.sock");
+        let listener = UnixListener::bind(&socket).unwrap();
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut request = Vec::new();
+                let mut buf = [0u8; 1024];
+                while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                    match stream.read(&mut buf) {
+                        Ok(0) | Err(_) => break,
+                        Ok(len) => request.extend_from_slice(&buf[..len]),
+                    }
+                }
+                // "GET /path HTTP/1.1"
+                let request = String::from_utf8_lossy(&request);
+                let path = request.split_whitespace().nth(1).unwrap_or_default();
+                let _ = stream.write_all(&handler(path));
+            }
+        });
+        (dir, DockerClient::new(socket, timeout))
+    }
+
+    fn with_length(body: &str) -> Vec<u8> {
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        )
+        .into_bytes()
+    }
+
+    /// Send the body in chunks of a few bytes, like a streaming daemon
+    fn chunked(body: &str) -> Vec<u8> {
+        let mut response = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n".to_vec();
+        for chunk in body.as_bytes().chunks(7) {
+            response.extend_from_slice(format!("{:x}\r\n", chunk.len()).as_bytes());
+            response.extend_from_slice(chunk);
+            response.extend_from_slice(b"\r\n");
+        }
+        response.extend_from_slice(b"0\r\n\r\n");
+        response
+    }
+
+    /// Nanoseconds of an API timestamp in a query string
+    fn query_nanos(path: &str, name: &str) -> i64 {
+        let value = path
+            .split(['?', '&'])
+            .find_map(|param| param.strip_prefix(&format!("{}=", name)))
+            .unwrap();
+        let (seconds, nanos) = value.split_once('.').unwrap();
+        seconds.parse::<i64>().unwrap() * 1_000_000_000 + nanos.parse::<i64>().unwrap()
+    }
+
+    fn event(action: &str, time_nano: i64) -> String {
+        serde_json::json!({
+            "Type": "container",
+            "Action": action,
+            "Actor": {"ID": WEB_ID, "Attributes": {"name": "web", "image": "nginx:1.25", "exitCode": "137"}},
+            "time": time_nano / 1_000_000_000,
+            "timeNano": time_nano,
+        })
+        .to_string()
+    }
+
+    /// A daemon with a healthy running container and one that exited with an error
+    fn fake_daemon() -> (tempfile::TempDir, DockerClient) {
+        let stats_requests = Arc::new(AtomicU64::new(0));
+        serve(Duration::from_secs(5), move |path| {
+            if path.starts_with("/containers/json") {
+                chunked(
+                    &serde_json::json!([
+                        {"Id": WEB_ID, "Names": ["/web"], "Image": "nginx:1.25", "State": "running",
+                         "Status": "Up 2 hours (healthy)", "Ports": [{"PublicPort": 8443}, {"PublicPort": 8080}]},
+                        {"Id": WORKER_ID, "Names": ["/worker"], "Image": "worker:latest", "State": "exited",
+                         "Status": "Exited (1) 5 minutes ago", "Ports": []},
+                    ])
+                    .to_string(),
+                )
+            } else if path == format!("/containers/{}/json", WEB_ID) {
+                with_length(
+                    r#"{"RestartCount": 2, "State": {"ExitCode": 0, "StartedAt": "2024-05-01T10:00:00.5Z",
+                        "Health": {"Status": "healthy"}}}"#,
+                )
+            } else if path == format!("/containers/{}/json", WORKER_ID) {
+                with_length(r#"{"RestartCount": 0, "State": {"ExitCode": 1, "StartedAt": "0001-01-01T00:00:00Z"}}"#)
+            } else if path.starts_with(&format!("/containers/{}/stats", WEB_ID)) {
+                let n = stats_requests.fetch_add(1, Ordering::SeqCst) + 1;
+                chunked(
+                    &serde_json::json!({
+                        "cpu_stats": {"cpu_usage": {"total_usage": n * 1_000_000_000}},
+                        "memory_stats": {"usage": 300, "limit": 1000, "stats": {"inactive_file": 100}},
+                        "networks": {
+                            "eth0": {"rx_bytes": n * 3000, "tx_bytes": n * 1000},
+                            "eth1": {"rx_bytes": n * 1000, "tx_bytes": n * 1000},
+                        },
+                        "blkio_stats": {"io_service_bytes_recursive": [
+                            {"major": 8, "minor": 0, "op": "read", "value": n * 4096},
+                            {"major": 8, "minor": 0, "op": "write", "value": n * 8192},
+                        ]},
+                        "pids_stats": {"current": 7},
+                    })
+                    .to_string(),
+                )
+            } else if path.starts_with("/events") {
+                // Events at both ends of the window and just outside it
+                let since = query_nanos(path, "since");
+                let until = query_nanos(path, "until");
+                chunked(
+                    &[
+                        event("die", since),
+                        event("start", since + 1),
+                        event("create", since + 2),
+                        event("die", until),
+                        event("stop", until + 1),
+                    ]
+                    .join("\n"),
+                )
+            } else {
+                b"HTTP/1.1 404 Not Found\r\nContent-Length: 27\r\n\r\n{\"message\":\"no such route\"}".to_vec()
+            }
+        })
+    }
+
+    #[test]
+    fn reports_containers_with_details() {
+        let (_dir, client) = fake_daemon();
+        let mut state = DockerState::new(client);
+        let metrics = collect(&mut state);
+
+        assert_eq!(metrics.containers.len(), 2);
+        let web = &metrics.containers[0];
+        assert_eq!(web.id, "4f2a6c1b9e8d");
+        assert_eq!(web.name, "web");
+        assert_eq!(web.image, "nginx:1.25");
+        assert_eq!(web.state, "running");
+        assert_eq!(web.restart_count, Some(2));
+        assert_eq!(web.health.as_deref(), Some("healthy"));
+        assert_eq!(web.exit_code, None);
+        assert_eq!(web.started_at.unwrap().to_rfc3339(), "2024-05-01T10:00:00.500+00:00");
+        // Inactive page cache doesn't count
+        assert_eq!(web.memory_usage_bytes, Some(200));
+        assert_eq!(web.memory_limit, Some(1000));
+        assert_eq!(web.memory_usage, Some(20.0));
+        assert_eq!(web.pids, Some(7));
+        // Rates need a second sample
+        assert_eq!(web.cpu_usage_percent, None);
+        assert_eq!(web.network_rx_bytes_per_sec, None);
+
+        let worker = &metrics.containers[1];
+        assert_eq!(worker.name, "worker");
+        assert_eq!(worker.exit_code, Some(1));
+        assert_eq!(worker.started_at, None);
+        assert_eq!(worker.memory_usage_bytes, None);
+    }
+
+    #[test]
+    fn computes_rates_from_two_samples() {
+        let (_dir, client) = fake_daemon();
+        let mut state = DockerState::new(client);
+        collect(&mut state);
+        std::thread::sleep(Duration::from_millis(10));
+        let metrics = collect(&mut state);
+
+        let web = &metrics.containers[0];
+        let rx = web.network_rx_bytes_per_sec.unwrap();
+        let tx = web.network_tx_bytes_per_sec.unwrap();
+        let read = web.block_read_bytes_per_sec.unwrap();
+        let write = web.block_write_bytes_per_sec.unwrap();
+        assert!(web.cpu_usage_percent.unwrap() > 0.0);
+        // Interfaces are added up; every rate covers the same interval
+        assert!((rx / tx - 2.0).abs() < 1e-9);
+        assert!((write / read - 2.0).abs() < 1e-9);
+        assert!((rx / read - 4000.0 / 4096.0).abs() < 1e-9);
+        assert_eq!(metrics.containers[1].cpu_usage_percent, None);
+    }
+
+    #[test]
+    fn reports_events_once() {
+        let (_dir, client) = fake_daemon();
+        let mut state = DockerState::new(client);
+        let since = state.events_since;
+        let metrics = collect(&mut state);
+
+        // `since` is exclusive and `until` inclusive; other actions are dropped
+        let actions: Vec<&str> = metrics.events.iter().map(|e| e.action.as_str()).collect();
+        assert_eq!(actions, ["start", "die"]);
+        let start = &metrics.events[0];
+        assert_eq!(start.time.timestamp_nanos_opt(), Some(since.timestamp_nanos_opt().unwrap() + 1));
+        assert_eq!(start.container_id, "4f2a6c1b9e8d");
+        assert_eq!(start.name, "web");
+        assert_eq!(start.image.as_deref(), Some("nginx:1.25"));
+        assert_eq!(metrics.events[1].exit_code, Some(137));
+        assert_eq!(metrics.events[1].time, state.events_since);
+    }
+
+    #[test]
+    fn reports_nothing_without_a_daemon() {
+        let dir = tempfile::tempdir().unwrap();
+        let client = DockerClient::new(dir.path().join("docker.sock"), Duration::from_secs(1));
+        let mut state = DockerState::new(client);
+        let since = state.events_since;
+
+        let metrics = collect(&mut state);
+        assert!(metrics.containers.is_empty());
+        assert!(metrics.events.is_empty());
+        // Events are picked up where the last successful collection ended
+        assert_eq!(state.events_since, since);
+    }
+
+    #[test]
+    fn one_deadline_covers_every_request() {
+        let ids: Vec<String> = (0..6).map(|i| format!("{:064}", i)).collect();
+        let listing: Vec<_> = ids
+            .iter()
+            .map(|id| serde_json::json!({"Id": id, "State": "exited", "Status": "Exited (0) 1 hour ago"}))
+            .collect();
+        let listing = serde_json::Value::from(listing).to_string();
+        let (_dir, client) = serve(Duration::from_millis(350), move |path| {
+            if path.starts_with("/containers/json") {
+                with_length(&listing)
+            } else if path.starts_with("/events") {
+                with_length("")
+            } else {
+                std::thread::sleep(Duration::from_millis(100));
+                with_length(r#"{"RestartCount": 1, "State": {}}"#)
+            }
+        });
+
+        let start = Instant::now();
+        let metrics = collect(&mut DockerState::new(client));
+        assert!(start.elapsed() < Duration::from_secs(1));
+        // Containers the daemon didn't get to in time are still listed
+        assert_eq!(metrics.containers.len(), 6);
+        assert_eq!(metrics.containers[0].restart_count, Some(1));
+        assert_eq!(metrics.containers[5].restart_count, None);
+    }
+
+    #[test]
+    fn discovers_containers_as_services() {
+        let (_dir, client) = fake_daemon();
+        let services = discover(&client).unwrap();
+        assert_eq!(services.len(), 2);
+        assert_eq!(services[0].name, "web");
+        assert_eq!(services[0].port, Some(8080));
+        assert_eq!(services[0].status, ServiceStatus::Running);
+        assert_eq!(services[1].name, "worker");
+        assert_eq!(services[1].port, None);
+        assert_eq!(services[1].status, ServiceStatus::Error);
+    }
+
+    #[test]
+    fn maps_container_states_to_service_status() {
+        let status = |state: &str, status: &str| {
+            let summary: ContainerSummary =
+                serde_json::from_value(serde_json::json!({"Id": WEB_ID, "State": state, "Status": status})).unwrap();
+            service_status(&summary)
+        };
+        assert_eq!(status("running", "Up 2 hours"), ServiceStatus::Running);
+        assert_eq!(status("running", "Up 2 hours (health: starting)"), ServiceStatus::Running);
+        assert_eq!(status("running", "Up 2 hours (unhealthy)"), ServiceStatus::Error);
+        assert_eq!(status("restarting", "Restarting (1) 3 seconds ago"), ServiceStatus::Error);
+        assert_eq!(status("dead", "Dead"), ServiceStatus::Error);
+        assert_eq!(status("exited", "Exited (0) 5 minutes ago"), ServiceStatus::Stopped);
+        assert_eq!(status("exited", "Exited (137) 5 minutes ago"), ServiceStatus::Error);
+        assert_eq!(status("created", "Created"), ServiceStatus::Stopped);
+        assert_eq!(status("paused", "Up 2 hours (Paused)"), ServiceStatus::Stopped);
+    }
+
+    #[test]
+    fn parses_content_length_responses() {
+        let (status, body) = parse_response(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n{}trailing").unwrap();
+        assert_eq!(status, 200);
+        assert_eq!(body, b"{}");
+
+        // Without a length the body runs until the connection closed
+        let response = b"HTTP/1.0 404 Not Found\r\nServer: Docker\r\n\r\n{\"message\":\"x\"}";
+        let (status, body) = parse_response(response).unwrap();
+        assert_eq!(status, 404);
+        assert_eq!(body, br#"{"message":"x"}"#);
+
+        assert!(parse_response(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n").is_err());
+        assert!(parse_response(b"garbage\r\n\r\n").is_err());
+    }
+
+    #[test]
+    fn decodes_chunked_bodies() {
+        let response = b"HTTP/1.1 200 OK\r\ntransfer-encoding: Chunked\r\n\r\n\
+            4\r\n[1,2\r\na;name=value\r\n,3,4,5,6,7\r\n1\r\n]\r\n0\r\n\r\n";
+        let (status, body) = parse_response(response).unwrap();
+        assert_eq!(status, 200);
+        assert_eq!(body, b"[1,2,3,4,5,6,7]");
+
+        assert_eq!(decode_chunked(b"0\r\n\r\n").unwrap(), b"");
+        // Missing the last chunk, cut off mid-chunk, or not hex
+        assert!(decode_chunked(b"3\r\nabc\r\n").is_err());
+        assert!(decode_chunked(b"a\r\nabc").is_err());
+        assert!(decode_chunked(b"zz\r\nabc\r\n0\r\n\r\n").is_err());
+    }
+}
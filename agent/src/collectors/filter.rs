@@ -0,0 +1,54 @@
+//! Include/exclude filtering for names such as mountpoints and interfaces
+
+use anyhow::{Context, Result};
+use glob::{MatchOptions, Pattern};
+
+/// Glob-based include/exclude filter
+///
+/// A name passes if it matches at least one include pattern (or there are
+/// none) and matches no exclude pattern.
+#[derive(Debug, Clone, Default)]
+pub struct NameFilter {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+    options: MatchOptions,
+}
+
+impl NameFilter {
+    /// Compile a filter from glob patterns
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self> {
+        Ok(Self {
+            include: compile(include)?,
+            exclude: compile(exclude)?,
+            options: MatchOptions::new(),
+        })
+    }
+
+    /// Compile a filter for `/`-separated paths
+    ///
+    /// `*` and `?` stay within one path component; `**` matches any number
+    /// of components.
+    pub fn for_paths(include: &[String], exclude: &[String]) -> Result<Self> {
+        Ok(Self {
+            options: MatchOptions {
+                require_literal_separator: true,
+                ..MatchOptions::new()
+            },
+            ..Self::new(include, exclude)?
+        })
+    }
+
+    /// Whether `name` passes the filter
+    pub fn matches(&self, name: &str) -> bool {
+        let matches = |p: &Pattern| p.matches_with(name, self.options);
+        let included = self.include.is_empty() || self.include.iter().any(matches);
+        included && !self.exclude.iter().any(matches)
+    }
+}
+
+fn compile(patterns: &[String]) -> Result<Vec<Pattern>> {
+    patterns
+        .iter()
+        .map(|p| Pattern::new(p).with_context(|| format!("Invalid pattern: {}", p)))
+        .collect()
+}
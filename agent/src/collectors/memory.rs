@@ -2,6 +2,8 @@
 
 use sysinfo::System;
 
+use super::rate::{Counter, CounterRates, CounterWidth};
+
 /// Memory metrics data
 pub struct MemoryMetrics {
     /// Memory usage percentage (0-100)
@@ -12,10 +14,64 @@ pub struct MemoryMetrics {
     pub used: i64,
     /// Available RAM in bytes
     pub available: i64,
+    /// Total swap in bytes
+    pub swap_total: i64,
+    /// Used swap in bytes
+    pub swap_used: i64,
+    /// Kernel memory breakdown (Linux only)
+    pub details: Option<MemoryDetails>,
+    /// Major page faults per second (Linux only)
+    pub major_faults_per_sec: Option<f64>,
+    /// Pages swapped in per second (Linux only)
+    pub swap_in_pages_per_sec: Option<f64>,
+    /// Pages swapped out per second (Linux only)
+    pub swap_out_pages_per_sec: Option<f64>,
+}
+
+/// Breakdown of memory use from /proc/meminfo, sizes in bytes
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MemoryDetails {
+    /// Page cache, excluding swap cache
+    pub cached: i64,
+    /// Raw block device buffers
+    pub buffers: i64,
+    /// tmpfs and shared memory
+    pub shared: i64,
+    /// Data waiting to be written back to disk
+    pub dirty: i64,
+    /// Data being written back to disk right now
+    pub writeback: i64,
+    /// Kernel slab caches
+    pub slab: i64,
+    /// Part of the slab that can be reclaimed under pressure
+    pub slab_reclaimable: i64,
+    /// Number of preallocated huge pages
+    pub hugepages_total: u64,
+    /// Number of huge pages not in use
+    pub hugepages_free: u64,
+    /// Size of a huge page
+    pub hugepage_size: i64,
+}
+
+/// Cumulative counters from /proc/vmstat that are turned into rates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VmCounter {
+    MajorFaults,
+    SwapIn,
+    SwapOut,
+}
+
+impl Counter for VmCounter {
+    fn width(&self) -> CounterWidth {
+        CounterWidth::LONG
+    }
 }
 
+/// Previous /proc/vmstat counters
+pub type VmstatRates = CounterRates<VmCounter>;
+
 /// Collect memory metrics from the system
-pub fn collect(system: &System) -> MemoryMetrics {
+pub fn collect(system: &System, vmstat_rates: &mut VmstatRates) -> MemoryMetrics {
     let total = system.total_memory() as i64;
     let used = system.used_memory() as i64;
     let available = system.available_memory() as i64;
@@ -26,10 +82,143 @@ pub fn collect(system: &System) -> MemoryMetrics {
         0.0
     };
 
+    let rates = vmstat_rates.update(read_vmstat_counters());
+
     MemoryMetrics {
         usage,
         total,
         used,
         available,
+        swap_total: system.total_swap() as i64,
+        swap_used: system.used_swap() as i64,
+        details: read_meminfo(),
+        major_faults_per_sec: rates.get(&VmCounter::MajorFaults).copied(),
+        swap_in_pages_per_sec: rates.get(&VmCounter::SwapIn).copied(),
+        swap_out_pages_per_sec: rates.get(&VmCounter::SwapOut).copied(),
+    }
+}
+
+/// Read the memory breakdown from /proc/meminfo (Linux only)
+///
+/// Sizes are reported in kB except for the HugePages_* counts.
+#[cfg(target_os = "linux")]
+fn read_meminfo() -> Option<MemoryDetails> {
+    let content = std::fs::read_to_string("/proc/meminfo").ok()?;
+    Some(parse_meminfo(&content))
+}
+
+/// Parse the contents of /proc/meminfo
+#[cfg(target_os = "linux")]
+fn parse_meminfo(content: &str) -> MemoryDetails {
+    let mut details = MemoryDetails::default();
+    for line in content.lines() {
+        let Some((key, rest)) = line.split_once(':') else {
+            continue;
+        };
+        let Some(value) = rest.split_whitespace().next().and_then(|v| v.parse::<u64>().ok()) else {
+            continue;
+        };
+        let bytes = (value * 1024) as i64;
+
+        match key {
+            "Cached" => details.cached = bytes,
+            "Buffers" => details.buffers = bytes,
+            "Shmem" => details.shared = bytes,
+            "Dirty" => details.dirty = bytes,
+            "Writeback" => details.writeback = bytes,
+            "Slab" => details.slab = bytes,
+            "SReclaimable" => details.slab_reclaimable = bytes,
+            "HugePages_Total" => details.hugepages_total = value,
+            "HugePages_Free" => details.hugepages_free = value,
+            "Hugepagesize" => details.hugepage_size = bytes,
+            _ => {}
+        }
+    }
+
+    details
+}
+
+/// Fallback for non-Linux systems
+#[cfg(not(target_os = "linux"))]
+fn read_meminfo() -> Option<MemoryDetails> {
+    None
+}
+
+/// Read paging counters from /proc/vmstat (Linux only)
+#[cfg(target_os = "linux")]
+fn read_vmstat_counters() -> Vec<(VmCounter, u64)> {
+    let Ok(content) = std::fs::read_to_string("/proc/vmstat") else {
+        return Vec::new();
+    };
+    parse_vmstat(&content)
+}
+
+/// Parse the paging counters out of /proc/vmstat (`name value` per line)
+#[cfg(target_os = "linux")]
+fn parse_vmstat(content: &str) -> Vec<(VmCounter, u64)> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.split_once(' ')?;
+            let counter = match key {
+                "pgmajfault" => VmCounter::MajorFaults,
+                "pswpin" => VmCounter::SwapIn,
+                "pswpout" => VmCounter::SwapOut,
+                _ => return None,
+            };
+            Some((counter, value.trim().parse().ok()?))
+        })
+        .collect()
+}
+
+/// Fallback for non-Linux systems
+#[cfg(not(target_os = "linux"))]
+fn read_vmstat_counters() -> Vec<(VmCounter, u64)> {
+    Vec::new()
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_meminfo() {
+        let content = "\
+MemTotal:       16318164 kB
+Buffers:          402112 kB
+Cached:          5318800 kB
+SwapCached:         1024 kB
+Shmem:            812344 kB
+Slab:             612000 kB
+SReclaimable:     400000 kB
+Dirty:               228 kB
+Writeback:             0 kB
+HugePages_Total:       4
+HugePages_Free:        3
+Hugepagesize:       2048 kB
+";
+        let details = parse_meminfo(content);
+        assert_eq!(details.buffers, 402112 * 1024);
+        // SwapCached must not be taken for Cached
+        assert_eq!(details.cached, 5318800 * 1024);
+        assert_eq!(details.shared, 812344 * 1024);
+        assert_eq!(details.slab, 612000 * 1024);
+        assert_eq!(details.slab_reclaimable, 400000 * 1024);
+        assert_eq!(details.dirty, 228 * 1024);
+        assert_eq!(details.writeback, 0);
+        assert_eq!(details.hugepages_total, 4);
+        assert_eq!(details.hugepages_free, 3);
+        assert_eq!(details.hugepage_size, 2048 * 1024);
+    }
+
+    #[test]
+    fn parses_vmstat() {
+        let content = "nr_free_pages 123\npgmajfault 4567\npswpin 12\npswpout 34\npgmajfault_extra x\n";
+        let mut counters = parse_vmstat(content);
+        counters.sort_by_key(|(_, value)| *value);
+        assert_eq!(
+            counters,
+            [(VmCounter::SwapIn, 12), (VmCounter::SwapOut, 34), (VmCounter::MajorFaults, 4567)]
+        );
     }
 }
@@ -1,53 +1,267 @@
 //! System metrics collectors
 //! 
 //! This module provides collectors for various system metrics including
-//! CPU, memory, disk, and network statistics.
+//! CPU, memory, disk, network, socket, sensor and pressure stall statistics.
 
+pub mod cgroups;
 pub mod cpu;
 pub mod disk;
+pub mod docker;
+pub mod filter;
 pub mod memory;
 pub mod network;
+pub mod oom;
+pub mod pressure;
+pub mod process;
+pub mod rate;
+pub mod sensors;
+pub mod services;
+pub mod sockets;
+pub mod systemd;
 
+use crate::checks::process::ProcessChecks;
+use crate::config::{Config, ProcessConfig};
+use cgroups::{CgroupFilter, CgroupRates};
+use anyhow::{Context, Result};
+use chrono::Utc;
+use cpu::StatRates;
+use disk::{DeviceIoRates, DiskFilter, FilesystemState};
+use docker::{DockerClient, DockerState};
+use memory::VmstatRates;
+use network::{InterfaceRates, NetworkFilter};
+use oom::OomState;
+use sockets::SocketRates;
+use systemd::{SystemdState, UnitFilter};
 use crate::models::MetricsPayload;
 use sysinfo::System;
+use tracing::{info, warn};
+
+/// Whether an optional data source, like the Docker daemon, answered last time
+///
+/// Losing the source is logged once as a warning and getting it back once,
+/// rather than on every collection.
+#[derive(Debug, Default)]
+pub struct Availability {
+    available: Option<bool>,
+}
+
+impl Availability {
+    /// Record the outcome of reading `source`, returning the value if it was read
+    pub fn update<T>(&mut self, source: &str, result: Result<T>) -> Option<T> {
+        match result {
+            Ok(value) => {
+                if self.available == Some(false) {
+                    info!("{} is reachable again", source);
+                }
+                self.available = Some(true);
+                Some(value)
+            }
+            Err(e) => {
+                if self.available != Some(false) {
+                    warn!("{} monitoring unavailable: {:#}", source, e);
+                }
+                self.available = Some(false);
+                None
+            }
+        }
+    }
+}
 
 /// Unified metrics collector that aggregates all system metrics
 pub struct MetricsCollector {
     system: System,
+    /// Identifies this run of the agent in every payload
+    boot_id: String,
+    /// Sequence number of the next payload
+    sequence: u64,
+    /// Whether to report usage of every core
+    per_core_cpu: bool,
+    /// Previous /proc/stat counters
+    cpu_stat_rates: StatRates,
+    /// Previous /proc/vmstat counters
+    vmstat_rates: VmstatRates,
+    /// OOM kill counters and kernel log position
+    oom_state: OomState,
+    /// Previous per-device disk counters
+    disk_io_rates: DeviceIoRates,
+    /// Read-only tracking and network filesystem probes
+    filesystem_state: FilesystemState,
+    /// Which filesystems and block devices to report
+    disk_filter: DiskFilter,
+    /// Which network interfaces to report
+    network_filter: NetworkFilter,
+    /// Previous per-interface network counters
+    network_rates: InterfaceRates,
+    /// Previous TCP/UDP protocol counters
+    socket_rates: SocketRates,
+    /// Which cgroups to report, `None` when cgroup collection is disabled
+    cgroup_filter: Option<CgroupFilter>,
+    /// Previous per-cgroup counters
+    cgroup_rates: CgroupRates,
+    /// Docker client and per-container counters, `None` when Docker monitoring is disabled
+    docker: Option<DockerState>,
+    /// systemd connection and unit filter, `None` when unit monitoring is disabled
+    systemd: Option<SystemdState>,
+    /// Process presence checks, `None` when process checks are disabled
+    process_checks: Option<ProcessChecks>,
+    /// Process collection settings, `None` when process collection is disabled
+    processes: Option<ProcessConfig>,
 }
 
 impl MetricsCollector {
-    /// Create a new metrics collector
+    /// Create a new metrics collector with default settings
     pub fn new() -> Self {
         let mut system = System::new_all();
         // Initial refresh to populate data
         system.refresh_all();
-        Self { system }
+        Self {
+            system,
+            boot_id: uuid::Uuid::new_v4().to_string(),
+            sequence: 0,
+            per_core_cpu: false,
+            cpu_stat_rates: StatRates::new(),
+            vmstat_rates: VmstatRates::new(),
+            oom_state: OomState::new(),
+            disk_io_rates: DeviceIoRates::new(),
+            filesystem_state: FilesystemState::new(),
+            disk_filter: DiskFilter::default(),
+            network_filter: NetworkFilter::default(),
+            network_rates: InterfaceRates::new(),
+            socket_rates: SocketRates::new(),
+            cgroup_filter: None,
+            cgroup_rates: CgroupRates::new(),
+            docker: None,
+            systemd: None,
+            process_checks: None,
+            processes: None,
+        }
+    }
+
+    /// Create a metrics collector using the settings from the agent config
+    ///
+    /// Fails if an include or exclude pattern in the config is invalid.
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let mut collector = Self::new();
+        collector.per_core_cpu = config.features.per_core_cpu;
+        collector.disk_filter = DiskFilter::from_config(&config.disk).context("Invalid pattern in [disk]")?;
+        collector.network_filter =
+            NetworkFilter::from_config(&config.network).context("Invalid pattern in [network]")?;
+        if config.features.collect_cgroups {
+            collector.cgroup_filter =
+                Some(CgroupFilter::from_config(&config.cgroups).context("Invalid pattern in [cgroups]")?);
+        }
+        if config.features.collect_docker {
+            collector.docker = Some(DockerState::new(DockerClient::from_config(&config.docker)));
+        }
+        if config.features.monitor_systemd {
+            let filter = UnitFilter::from_config(&config.systemd).context("Invalid pattern in [systemd]")?;
+            collector.systemd = Some(SystemdState::new(filter));
+        }
+        if config.features.process_checks {
+            collector.process_checks = Some(ProcessChecks::from_config(&config.process_checks)?);
+        }
+        if config.features.collect_processes {
+            collector.processes = Some(config.processes.clone());
+        }
+        Ok(collector)
     }
 
     /// Collect all metrics and return a unified payload
     pub fn collect(&mut self) -> MetricsPayload {
         // Refresh system info before collecting
         self.system.refresh_all();
+        let collected_at = Utc::now();
+        let sequence = self.sequence;
+        self.sequence += 1;
 
-        let cpu_metrics = cpu::collect(&self.system);
-        let memory_metrics = memory::collect(&self.system);
-        let disk_metrics = disk::collect(&self.system);
-        let network_metrics = network::collect(&self.system);
+        let cpu_metrics = cpu::collect(&self.system, self.per_core_cpu, &mut self.cpu_stat_rates);
+        let pressure_metrics = pressure::collect();
+        let memory_metrics = memory::collect(&self.system, &mut self.vmstat_rates);
+        let disk_metrics = disk::collect(
+            &self.system,
+            &self.disk_filter,
+            &mut self.disk_io_rates,
+            &mut self.filesystem_state,
+        );
+        let network_metrics = network::collect(&self.system, &self.network_filter, &mut self.network_rates);
+        let sockets = sockets::collect(&mut self.socket_rates);
+        let sensor_metrics = sensors::collect();
+        let cgroups = self
+            .cgroup_filter
+            .as_ref()
+            .map(|filter| cgroups::collect(filter, &mut self.cgroup_rates))
+            .unwrap_or_default();
+        let oom_metrics = oom::collect(&mut self.oom_state, &cgroups);
+        let docker_metrics = self.docker.as_mut().map(docker::collect).unwrap_or_default();
+        let systemd_units = self.systemd.as_mut().map(systemd::collect_units).unwrap_or_default();
+        let process_checks = self
+            .process_checks
+            .as_ref()
+            .map(|checks| checks.run(&self.system))
+            .unwrap_or_default();
+        let processes = self
+            .processes
+            .as_ref()
+            .map(|config| process::collect(&self.system, config))
+            .unwrap_or_default();
 
         MetricsPayload {
+            collected_at,
+            boot_id: self.boot_id.clone(),
+            sequence,
+
             // CPU
             cpu_usage: cpu_metrics.usage,
             cpu_count: cpu_metrics.count,
             load_average_1m: cpu_metrics.load_average_1m,
             load_average_5m: cpu_metrics.load_average_5m,
             load_average_15m: cpu_metrics.load_average_15m,
+            cpu_user: cpu_metrics.times.map(|t| t.user),
+            cpu_nice: cpu_metrics.times.map(|t| t.nice),
+            cpu_system: cpu_metrics.times.map(|t| t.system),
+            cpu_idle: cpu_metrics.times.map(|t| t.idle),
+            cpu_iowait: cpu_metrics.times.map(|t| t.iowait),
+            cpu_irq: cpu_metrics.times.map(|t| t.irq),
+            cpu_softirq: cpu_metrics.times.map(|t| t.softirq),
+            cpu_steal: cpu_metrics.times.map(|t| t.steal),
+            cpu_guest: cpu_metrics.times.map(|t| t.guest),
+            context_switches_per_sec: cpu_metrics.context_switches_per_sec,
+            interrupts_per_sec: cpu_metrics.interrupts_per_sec,
+            forks_per_sec: cpu_metrics.forks_per_sec,
+            procs_running: cpu_metrics.procs_running,
+            procs_blocked: cpu_metrics.procs_blocked,
+            max_core_usage: cpu_metrics.max_core_usage,
+            cpu_cores: cpu_metrics.cores,
+            cpu_temperature: sensor_metrics.cpu_temperature,
+
+            // Pressure
+            cpu_pressure: pressure_metrics.cpu,
+            memory_pressure: pressure_metrics.memory,
+            io_pressure: pressure_metrics.io,
 
             // Memory
             memory_usage: memory_metrics.usage,
             memory_total: memory_metrics.total,
             memory_used: memory_metrics.used,
             memory_available: memory_metrics.available,
+            swap_total: memory_metrics.swap_total,
+            swap_used: memory_metrics.swap_used,
+            memory_cached: memory_metrics.details.map(|d| d.cached),
+            memory_buffers: memory_metrics.details.map(|d| d.buffers),
+            memory_shared: memory_metrics.details.map(|d| d.shared),
+            memory_dirty: memory_metrics.details.map(|d| d.dirty),
+            memory_writeback: memory_metrics.details.map(|d| d.writeback),
+            memory_slab: memory_metrics.details.map(|d| d.slab),
+            memory_slab_reclaimable: memory_metrics.details.map(|d| d.slab_reclaimable),
+            hugepages_total: memory_metrics.details.map(|d| d.hugepages_total),
+            hugepages_free: memory_metrics.details.map(|d| d.hugepages_free),
+            hugepage_size: memory_metrics.details.map(|d| d.hugepage_size),
+            major_faults_per_sec: memory_metrics.major_faults_per_sec,
+            swap_in_pages_per_sec: memory_metrics.swap_in_pages_per_sec,
+            swap_out_pages_per_sec: memory_metrics.swap_out_pages_per_sec,
+            oom_kills: oom_metrics.kills,
+            cgroup_oom_kills: oom_metrics.cgroups,
+            oom_kill_events: oom_metrics.events,
 
             // Disk
             disk_usage: disk_metrics.usage,
@@ -55,10 +269,36 @@ impl MetricsCollector {
             disk_used: disk_metrics.used,
             disk_read_bytes: disk_metrics.read_bytes,
             disk_write_bytes: disk_metrics.write_bytes,
+            filesystems: disk_metrics.filesystems,
+            disk_devices: disk_metrics.devices,
+            disk_read_bytes_per_sec: disk_metrics.read_bytes_per_sec,
+            disk_write_bytes_per_sec: disk_metrics.write_bytes_per_sec,
 
             // Network
             network_in: network_metrics.bytes_in,
             network_out: network_metrics.bytes_out,
+            network_interfaces: network_metrics.interfaces,
+            network_in_bytes_per_sec: network_metrics.bytes_in_per_sec,
+            network_out_bytes_per_sec: network_metrics.bytes_out_per_sec,
+            sockets,
+
+            // cgroups
+            cgroups,
+
+            // Docker
+            containers: docker_metrics.containers,
+            container_events: docker_metrics.events,
+
+            // systemd
+            systemd_units,
+
+            // Sensors
+            temperatures: sensor_metrics.temperatures,
+            fans: sensor_metrics.fans,
+
+            // Processes
+            processes,
+            process_checks,
         }
     }
 }
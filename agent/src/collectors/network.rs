@@ -1,30 +1,222 @@
 //! Network metrics collector
 
-use sysinfo::{Networks, System};
+use anyhow::Result;
+use sysinfo::System;
+
+use super::filter::NameFilter;
+use super::rate::{sum_rates, Counter, CounterRates, CounterWidth};
+use crate::config::NetworkConfig;
+use crate::models::NetworkInterfaceMetrics;
 
 /// Network metrics data
 pub struct NetworkMetrics {
-    /// Cumulative bytes received
+    /// Cumulative bytes received across reported interfaces
     pub bytes_in: i64,
-    /// Cumulative bytes sent
+    /// Cumulative bytes sent across reported interfaces
     pub bytes_out: i64,
+    /// Receive throughput across reported interfaces (`None` until a previous sample exists)
+    pub bytes_in_per_sec: Option<f64>,
+    /// Transmit throughput across reported interfaces
+    pub bytes_out_per_sec: Option<f64>,
+    /// Statistics of every reported interface
+    pub interfaces: Vec<NetworkInterfaceMetrics>,
 }
 
-/// Collect network metrics from the system
-pub fn collect(_system: &System) -> NetworkMetrics {
-    let networks = Networks::new_with_refreshed_list();
-    
-    let mut bytes_in: u64 = 0;
-    let mut bytes_out: u64 = 0;
-
-    // Aggregate across all network interfaces
-    for (_interface_name, data) in networks.list() {
-        bytes_in += data.total_received();
-        bytes_out += data.total_transmitted();
+/// Selects which network interfaces are reported
+#[derive(Debug, Clone)]
+pub struct NetworkFilter {
+    names: NameFilter,
+    include_virtual: bool,
+}
+
+impl NetworkFilter {
+    /// Build the filter from the `[network]` config section
+    pub fn from_config(config: &NetworkConfig) -> Result<Self> {
+        Ok(Self {
+            names: NameFilter::new(&config.include_interfaces, &config.exclude_interfaces)?,
+            include_virtual: config.include_virtual,
+        })
+    }
+
+    fn matches(&self, name: &str, is_virtual: bool) -> bool {
+        (self.include_virtual || !is_virtual) && self.names.matches(name)
     }
+}
+
+impl Default for NetworkFilter {
+    fn default() -> Self {
+        Self::from_config(&NetworkConfig::default()).expect("default network patterns are valid")
+    }
+}
+
+/// Cumulative counters of an interface
+#[derive(Debug, Clone, Default)]
+pub struct InterfaceCounters {
+    pub name: String,
+    /// Changes when an interface is deleted and recreated under the same name
+    pub ifindex: u32,
+    pub operstate: Option<String>,
+    pub mtu: Option<u32>,
+    pub speed_mbps: Option<u64>,
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+    pub rx_packets: u64,
+    pub tx_packets: u64,
+    pub rx_errors: u64,
+    pub tx_errors: u64,
+    pub rx_dropped: Option<u64>,
+    pub tx_dropped: Option<u64>,
+}
+
+/// Counters of an interface that are turned into rates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NetCounter {
+    RxBytes,
+    TxBytes,
+    RxPackets,
+    TxPackets,
+}
+
+impl Counter for NetCounter {
+    fn width(&self) -> CounterWidth {
+        CounterWidth::U64
+    }
+}
+
+/// Previous interface counters, keyed by interface name and ifindex
+pub type InterfaceRates = CounterRates<(String, u32, NetCounter)>;
+
+/// Collect network metrics from the system
+pub fn collect(_system: &System, filter: &NetworkFilter, rates: &mut InterfaceRates) -> NetworkMetrics {
+    let counters = read_interface_counters(filter);
+
+    let per_second = rates.update(counters.iter().flat_map(|i| {
+        [
+            (NetCounter::RxBytes, i.rx_bytes),
+            (NetCounter::TxBytes, i.tx_bytes),
+            (NetCounter::RxPackets, i.rx_packets),
+            (NetCounter::TxPackets, i.tx_packets),
+        ]
+        .map(|(counter, value)| ((i.name.clone(), i.ifindex, counter), value))
+    }));
+
+    let interfaces: Vec<NetworkInterfaceMetrics> = counters
+        .into_iter()
+        .map(|i| {
+            let rate = |counter| per_second.get(&(i.name.clone(), i.ifindex, counter)).copied();
+
+            NetworkInterfaceMetrics {
+                rx_bytes_per_sec: rate(NetCounter::RxBytes),
+                tx_bytes_per_sec: rate(NetCounter::TxBytes),
+                rx_packets_per_sec: rate(NetCounter::RxPackets),
+                tx_packets_per_sec: rate(NetCounter::TxPackets),
+                name: i.name,
+                operstate: i.operstate,
+                mtu: i.mtu,
+                speed_mbps: i.speed_mbps,
+                rx_bytes: i.rx_bytes,
+                tx_bytes: i.tx_bytes,
+                rx_packets: i.rx_packets,
+                tx_packets: i.tx_packets,
+                rx_errors: i.rx_errors,
+                tx_errors: i.tx_errors,
+                rx_dropped: i.rx_dropped,
+                tx_dropped: i.tx_dropped,
+            }
+        })
+        .collect();
 
     NetworkMetrics {
-        bytes_in: bytes_in as i64,
-        bytes_out: bytes_out as i64,
+        bytes_in: interfaces.iter().map(|i| i.rx_bytes).sum::<u64>() as i64,
+        bytes_out: interfaces.iter().map(|i| i.tx_bytes).sum::<u64>() as i64,
+        bytes_in_per_sec: sum_rates(interfaces.iter().map(|i| i.rx_bytes_per_sec)),
+        bytes_out_per_sec: sum_rates(interfaces.iter().map(|i| i.tx_bytes_per_sec)),
+        interfaces,
+    }
+}
+
+/// Read interface counters from /sys/class/net (Linux only)
+///
+/// Virtual interfaces (loopback, bridges, veths, tunnels...) are the ones
+/// whose sysfs entry lives under /sys/devices/virtual.
+#[cfg(target_os = "linux")]
+fn read_interface_counters(filter: &NetworkFilter) -> Vec<InterfaceCounters> {
+    use std::fs;
+
+    let entries = match fs::read_dir("/sys/class/net") {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut interfaces = Vec::new();
+
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let dir = entry.path();
+
+        let is_virtual = fs::canonicalize(&dir)
+            .map(|path| path.starts_with("/sys/devices/virtual"))
+            .unwrap_or(false);
+        if !filter.matches(&name, is_virtual) {
+            continue;
+        }
+
+        let read = |file: &str| {
+            fs::read_to_string(dir.join(file))
+                .ok()
+                .map(|value| value.trim().to_string())
+        };
+        let stat = |counter: &str| {
+            read(&format!("statistics/{}", counter))
+                .and_then(|value| value.parse::<u64>().ok())
+        };
+
+        interfaces.push(InterfaceCounters {
+            ifindex: read("ifindex").and_then(|ifindex| ifindex.parse().ok()).unwrap_or(0),
+            operstate: read("operstate"),
+            mtu: read("mtu").and_then(|mtu| mtu.parse().ok()),
+            // Reading speed fails (or gives -1) while the link is down
+            speed_mbps: read("speed").and_then(|speed| speed.parse().ok()),
+            rx_bytes: stat("rx_bytes").unwrap_or(0),
+            tx_bytes: stat("tx_bytes").unwrap_or(0),
+            rx_packets: stat("rx_packets").unwrap_or(0),
+            tx_packets: stat("tx_packets").unwrap_or(0),
+            rx_errors: stat("rx_errors").unwrap_or(0),
+            tx_errors: stat("tx_errors").unwrap_or(0),
+            rx_dropped: stat("rx_dropped"),
+            tx_dropped: stat("tx_dropped"),
+            name,
+        });
     }
+
+    interfaces.sort_by(|a, b| a.name.cmp(&b.name));
+    interfaces
+}
+
+/// Fallback for non-Linux systems, using sysinfo's interface list
+///
+/// sysinfo can't tell virtual interfaces apart, so every interface is
+/// treated as physical and only the name patterns apply.
+#[cfg(not(target_os = "linux"))]
+fn read_interface_counters(filter: &NetworkFilter) -> Vec<InterfaceCounters> {
+    let networks = sysinfo::Networks::new_with_refreshed_list();
+
+    let mut interfaces: Vec<InterfaceCounters> = networks
+        .list()
+        .iter()
+        .filter(|(name, _)| filter.matches(name, false))
+        .map(|(name, data)| InterfaceCounters {
+            name: name.clone(),
+            rx_bytes: data.total_received(),
+            tx_bytes: data.total_transmitted(),
+            rx_packets: data.total_packets_received(),
+            tx_packets: data.total_packets_transmitted(),
+            rx_errors: data.total_errors_on_received(),
+            tx_errors: data.total_errors_on_transmitted(),
+            ..Default::default()
+        })
+        .collect();
+
+    interfaces.sort_by(|a, b| a.name.cmp(&b.name));
+    interfaces
 }
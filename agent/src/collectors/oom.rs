@@ -0,0 +1,365 @@
+//! OOM-kill event collector
+//!
+//! Counts kills from the `oom_kill` counter in /proc/vmstat and from
+//! `memory.events` of the cgroups the cgroup collector reports, and names the
+//! killed processes by following the kernel log through /dev/kmsg. Reading
+//! the kernel log needs CAP_SYSLOG when `kernel.dmesg_restrict` is set;
+//! without it kills are still counted, just not named.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use super::cgroups::CGROUP_ROOT;
+use crate::models::{CgroupMetrics, CgroupOomKills, OomKillEvent};
+
+/// OOM kills since the previous collection
+pub struct OomMetrics {
+    /// Kills system-wide, `None` on the first sample or when the kernel doesn't count them
+    pub kills: Option<u64>,
+    /// Kills per cgroup, only cgroups with at least one kill
+    pub cgroups: Vec<CgroupOomKills>,
+    /// Individual kills found in the kernel log
+    pub events: Vec<OomKillEvent>,
+}
+
+/// State carried between collections
+pub struct OomState {
+    /// Previous value of `oom_kill` in /proc/vmstat
+    previous_kills: Option<u64>,
+    /// Previous per-cgroup kill counters, `None` before the first sample
+    previous_cgroup_kills: Option<HashMap<String, u64>>,
+    /// Kernel log positioned after the last record read
+    #[cfg(target_os = "linux")]
+    kernel_log: Option<std::fs::File>,
+}
+
+impl OomState {
+    /// Start tracking OOM kills from now on
+    pub fn new() -> Self {
+        Self {
+            previous_kills: None,
+            previous_cgroup_kills: None,
+            #[cfg(target_os = "linux")]
+            kernel_log: open_kernel_log(),
+        }
+    }
+}
+
+impl Default for OomState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Collect OOM kills that happened since the previous call
+///
+/// Kills per cgroup are only counted for `cgroups`, the groups selected by
+/// the cgroup collector, so the hierarchy isn't walked a second time.
+pub fn collect(state: &mut OomState, cgroups: &[CgroupMetrics]) -> OomMetrics {
+    let current_kills = read_vmstat_oom_kills();
+    let kills = match (state.previous_kills, current_kills) {
+        (Some(previous), Some(current)) => Some(current.saturating_sub(previous)),
+        _ => None,
+    };
+    state.previous_kills = current_kills;
+
+    let current_cgroup_kills = read_cgroup_oom_kills(Path::new(CGROUP_ROOT), cgroups);
+    let mut cgroups: Vec<CgroupOomKills> = match &state.previous_cgroup_kills {
+        Some(previous) => current_cgroup_kills
+            .iter()
+            .filter_map(|(cgroup, &current)| {
+                // A cgroup seen for the first time, or recreated under the same
+                // path, counts from zero
+                let count = match previous.get(cgroup) {
+                    Some(&before) if before <= current => current - before,
+                    _ => current,
+                };
+                (count > 0).then(|| CgroupOomKills {
+                    cgroup: cgroup.clone(),
+                    count,
+                })
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+    cgroups.sort_by(|a, b| a.cgroup.cmp(&b.cgroup));
+    state.previous_cgroup_kills = Some(current_cgroup_kills);
+
+    OomMetrics {
+        kills,
+        cgroups,
+        events: read_kernel_log_events(state),
+    }
+}
+
+/// Read the system-wide OOM kill counter (Linux 4.13+)
+#[cfg(target_os = "linux")]
+fn read_vmstat_oom_kills() -> Option<u64> {
+    let content = std::fs::read_to_string("/proc/vmstat").ok()?;
+    content
+        .lines()
+        .find_map(|line| line.strip_prefix("oom_kill "))
+        .and_then(|value| value.trim().parse().ok())
+}
+
+/// Fallback for non-Linux systems
+#[cfg(not(target_os = "linux"))]
+fn read_vmstat_oom_kills() -> Option<u64> {
+    None
+}
+
+/// Read the `oom_kill` counter of the given cgroups under the cgroup v2 mount at `root`
+///
+/// `memory.events.local` (Linux 5.2+) counts only kills inside the group
+/// itself. On older kernels `memory.events` also includes descendants, so it
+/// is only read for leaf cgroups to avoid counting a kill at every level.
+fn read_cgroup_oom_kills(root: &Path, cgroups: &[CgroupMetrics]) -> HashMap<String, u64> {
+    let mut counters = HashMap::new();
+    for cgroup in cgroups {
+        let dir = root.join(cgroup.path.trim_start_matches('/'));
+        let events = std::fs::read_to_string(dir.join("memory.events.local")).ok().or_else(|| {
+            let is_leaf = std::fs::read_dir(&dir).is_ok_and(|entries| {
+                !entries.flatten().any(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+            });
+            is_leaf
+                .then(|| std::fs::read_to_string(dir.join("memory.events")).ok())
+                .flatten()
+        });
+        if let Some(kills) = events.as_deref().and_then(parse_oom_kill) {
+            counters.insert(cgroup.path.clone(), kills);
+        }
+    }
+    counters
+}
+
+/// Find the `oom_kill` line of a memory.events file
+fn parse_oom_kill(events: &str) -> Option<u64> {
+    events
+        .lines()
+        .find_map(|line| line.strip_prefix("oom_kill "))
+        .and_then(|value| value.trim().parse().ok())
+}
+
+/// Open /dev/kmsg without blocking, skipping records logged before startup
+#[cfg(target_os = "linux")]
+fn open_kernel_log() -> Option<std::fs::File> {
+    use std::io::{Seek, SeekFrom};
+    use std::os::unix::fs::OpenOptionsExt;
+
+    let mut file = std::fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open("/dev/kmsg")
+        .ok()?;
+    file.seek(SeekFrom::End(0)).ok()?;
+    Some(file)
+}
+
+/// Read new kernel log records and turn OOM kill messages into events
+///
+/// Each read of /dev/kmsg returns one record:
+/// `<prio>,<seq>,<usec since boot>,<flags>;<message>` followed by optional
+/// continuation lines. A kill logs two messages, both of which are used:
+/// - `oom-kill:constraint=...,task_memcg=/system.slice/x.service,task=stress,pid=1234,uid=0` (Linux 4.19+)
+/// - `Out of memory: Killed process 1234 (stress) total-vm:...`
+#[cfg(target_os = "linux")]
+fn read_kernel_log_events(state: &mut OomState) -> Vec<OomKillEvent> {
+    use std::io::{ErrorKind, Read};
+
+    let Some(file) = state.kernel_log.as_mut() else {
+        return Vec::new();
+    };
+
+    let boot_time_us = sysinfo::System::boot_time() as i64 * 1_000_000;
+    let mut events: Vec<OomKillEvent> = Vec::new();
+    let mut buf = vec![0u8; 8192];
+
+    loop {
+        let len = match file.read(&mut buf) {
+            Ok(0) => break,
+            Ok(len) => len,
+            // Records were overwritten before we read them; carry on with the next one
+            Err(e) if e.raw_os_error() == Some(libc::EPIPE) => continue,
+            Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+            Err(_) => {
+                state.kernel_log = None;
+                break;
+            }
+        };
+
+        let record = String::from_utf8_lossy(&buf[..len]);
+        let Some((prefix, rest)) = record.split_once(';') else {
+            continue;
+        };
+        let message = rest.lines().next().unwrap_or_default();
+        let Some(kill) = parse_kill_message(message) else {
+            continue;
+        };
+        let time = prefix
+            .split(',')
+            .nth(2)
+            .and_then(|usec| usec.parse::<i64>().ok())
+            .and_then(|usec| chrono::DateTime::from_timestamp_micros(boot_time_us + usec))
+            .unwrap_or_else(chrono::Utc::now);
+
+        match events.iter_mut().find(|event| event.pid == kill.pid) {
+            Some(event) => {
+                event.process_name = event.process_name.take().or(kill.process_name);
+                event.cgroup = event.cgroup.take().or(kill.cgroup);
+                event.constraint = event.constraint.take().or(kill.constraint);
+            }
+            None => events.push(OomKillEvent {
+                time,
+                pid: kill.pid,
+                process_name: kill.process_name,
+                cgroup: kill.cgroup,
+                constraint: kill.constraint,
+            }),
+        }
+    }
+
+    events
+}
+
+/// Fallback for non-Linux systems
+#[cfg(not(target_os = "linux"))]
+fn read_kernel_log_events(_state: &mut OomState) -> Vec<OomKillEvent> {
+    Vec::new()
+}
+
+/// Details of a kill taken from one kernel log message
+#[cfg(target_os = "linux")]
+struct KillMessage {
+    pid: i32,
+    process_name: Option<String>,
+    cgroup: Option<String>,
+    constraint: Option<String>,
+}
+
+/// Parse either of the kernel's OOM kill messages
+#[cfg(target_os = "linux")]
+fn parse_kill_message(message: &str) -> Option<KillMessage> {
+    if let Some(fields) = message.strip_prefix("oom-kill:") {
+        let field = |key: &str| {
+            fields
+                .split(',')
+                .find_map(|field| field.strip_prefix(key)?.strip_prefix('='))
+                .map(str::to_string)
+        };
+        return Some(KillMessage {
+            pid: field("pid")?.parse().ok()?,
+            process_name: field("task"),
+            cgroup: field("task_memcg"),
+            constraint: field("constraint"),
+        });
+    }
+
+    // "Out of memory: Killed process 1234 (stress) ..." or
+    // "Memory cgroup out of memory: Killed process 1234 (stress) ..."
+    let (_, rest) = message.split_once("Killed process ")?;
+    let (pid, rest) = rest.split_once(' ')?;
+    let name = rest
+        .strip_prefix('(')
+        .and_then(|rest| rest.split_once(") ").map(|(name, _)| name).or(rest.strip_suffix(')')));
+    Some(KillMessage {
+        pid: pid.parse().ok()?,
+        process_name: name.map(str::to_string),
+        cgroup: None,
+        constraint: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cgroup(path: &str) -> CgroupMetrics {
+        CgroupMetrics {
+            path: path.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn finds_the_oom_kill_counter() {
+        let events = "low 0\nhigh 12\nmax 40\noom 3\noom_kill 2\noom_group_kill 0\n";
+        assert_eq!(parse_oom_kill(events), Some(2));
+        assert_eq!(parse_oom_kill("low 0\nhigh 0\nmax 0\noom 0\n"), None);
+        assert_eq!(parse_oom_kill("oom_kill lots\n"), None);
+    }
+
+    #[test]
+    fn reads_the_selected_cgroups() {
+        let root = tempfile::tempdir().unwrap();
+        let write = |path: &str, content: &str| {
+            let path = root.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        };
+        write("system.slice/nginx.service/memory.events.local", "oom 1\noom_kill 1\n");
+        write("system.slice/nginx.service/memory.events", "oom 4\noom_kill 4\n");
+        // Older kernels without memory.events.local
+        write("docker/abc/memory.events", "oom 2\noom_kill 2\n");
+        write("docker/memory.events", "oom 2\noom_kill 2\n");
+        write("user.slice/memory.events.local", "oom 5\noom_kill 5\n");
+
+        let selected = [cgroup("/system.slice/nginx.service"), cgroup("/docker/abc"), cgroup("/docker")];
+        let counters = read_cgroup_oom_kills(root.path(), &selected);
+        // memory.events of /docker includes its children, so only leaves are read
+        let expected = HashMap::from([("/system.slice/nginx.service".to_string(), 1), ("/docker/abc".to_string(), 2)]);
+        assert_eq!(counters, expected);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn parses_the_kill_summary() {
+        let kill = parse_kill_message(
+            "oom-kill:constraint=CONSTRAINT_MEMCG,nodemask=(null),cpuset=/,mems_allowed=0,\
+             oom_memcg=/system.slice/app.service,task_memcg=/system.slice/app.service,task=java,pid=4321,uid=998",
+        )
+        .unwrap();
+        assert_eq!(kill.pid, 4321);
+        assert_eq!(kill.process_name.as_deref(), Some("java"));
+        assert_eq!(kill.cgroup.as_deref(), Some("/system.slice/app.service"));
+        assert_eq!(kill.constraint.as_deref(), Some("CONSTRAINT_MEMCG"));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn parses_killed_process_messages() {
+        let global = parse_kill_message(
+            "Out of memory: Killed process 1234 (stress) total-vm:1048576kB, anon-rss:1000000kB, \
+             file-rss:0kB, shmem-rss:0kB, UID:0 pgtables:2048kB oom_score_adj:0",
+        )
+        .unwrap();
+        assert_eq!(global.pid, 1234);
+        assert_eq!(global.process_name.as_deref(), Some("stress"));
+        assert_eq!(global.cgroup, None);
+
+        let memcg = parse_kill_message(
+            "Memory cgroup out of memory: Killed process 5678 (Web Content) total-vm:2000kB, anon-rss:1000kB",
+        )
+        .unwrap();
+        assert_eq!(memcg.pid, 5678);
+        assert_eq!(memcg.process_name.as_deref(), Some("Web Content"));
+
+        // Older kernels end the message after the name
+        let short = parse_kill_message("Killed process 42 (worker)").unwrap();
+        assert_eq!(short.process_name.as_deref(), Some("worker"));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn ignores_malformed_messages() {
+        for message in [
+            "usb 1-1: new high-speed USB device number 2 using xhci_hcd",
+            "Out of memory: Killed process abc (stress) total-vm:0kB",
+            "Out of memory: Killed process 1234",
+            "oom-kill:constraint=CONSTRAINT_NONE,task=stress,uid=0",
+            "oom-kill:constraint=CONSTRAINT_NONE,task=stress,pid=-x,uid=0",
+            "",
+        ] {
+            assert!(parse_kill_message(message).is_none(), "{:?}", message);
+        }
+    }
+}
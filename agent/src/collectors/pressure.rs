@@ -0,0 +1,117 @@
+//! Pressure stall information (PSI) collector
+//!
+//! Reads `/proc/pressure/{cpu,memory,io}`, available on Linux 4.20+ when the
+//! kernel is built with `CONFIG_PSI` and not booted with `psi=0`. Everywhere
+//! else each resource is simply reported as `None`.
+
+use crate::models::{PressureStall, ResourcePressure};
+
+/// Pressure stall data for each resource
+pub struct PressureMetrics {
+    pub cpu: Option<ResourcePressure>,
+    pub memory: Option<ResourcePressure>,
+    pub io: Option<ResourcePressure>,
+}
+
+/// Collect pressure stall information
+pub fn collect() -> PressureMetrics {
+    PressureMetrics {
+        cpu: read_pressure("cpu"),
+        memory: read_pressure("memory"),
+        io: read_pressure("io"),
+    }
+}
+
+/// Read `/proc/pressure/<resource>` (Linux only)
+///
+/// Format:
+/// ```text
+/// some avg10=0.88 avg60=1.55 avg300=1.70 total=49177004
+/// full avg10=0.00 avg60=0.00 avg300=0.00 total=0
+/// ```
+/// The `full` line is missing for cpu on kernels older than 5.13. Reading
+/// fails with EOPNOTSUPP when PSI is compiled in but disabled.
+#[cfg(target_os = "linux")]
+fn read_pressure(resource: &str) -> Option<ResourcePressure> {
+    let content = std::fs::read_to_string(format!("/proc/pressure/{}", resource)).ok()?;
+    parse_pressure(&content)
+}
+
+/// Parse the contents of a /proc/pressure file
+#[cfg(target_os = "linux")]
+fn parse_pressure(content: &str) -> Option<ResourcePressure> {
+    let mut some = None;
+    let mut full = None;
+    for line in content.lines() {
+        let mut fields = line.split_whitespace();
+        match fields.next() {
+            Some("some") => some = parse_stall(fields),
+            Some("full") => full = parse_stall(fields),
+            _ => {}
+        }
+    }
+
+    Some(ResourcePressure { some: some?, full })
+}
+
+/// Fallback for non-Linux systems
+#[cfg(not(target_os = "linux"))]
+fn read_pressure(_resource: &str) -> Option<ResourcePressure> {
+    None
+}
+
+/// Parse the `key=value` fields of a single PSI line
+#[cfg(target_os = "linux")]
+fn parse_stall<'a>(fields: impl Iterator<Item = &'a str>) -> Option<PressureStall> {
+    let (mut avg10, mut avg60, mut avg300, mut total) = (None, None, None, None);
+    for field in fields {
+        let (key, value) = field.split_once('=')?;
+        match key {
+            "avg10" => avg10 = value.parse().ok(),
+            "avg60" => avg60 = value.parse().ok(),
+            "avg300" => avg300 = value.parse().ok(),
+            "total" => total = value.parse().ok(),
+            _ => {}
+        }
+    }
+
+    Some(PressureStall {
+        avg10: avg10?,
+        avg60: avg60?,
+        avg300: avg300?,
+        total_us: total?,
+    })
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_some_and_full() {
+        let content = "\
+some avg10=0.88 avg60=1.55 avg300=1.70 total=49177004
+full avg10=0.10 avg60=0.20 avg300=0.30 total=1234
+";
+        let pressure = parse_pressure(content).unwrap();
+        assert_eq!(pressure.some.avg10, 0.88);
+        assert_eq!(pressure.some.avg60, 1.55);
+        assert_eq!(pressure.some.avg300, 1.70);
+        assert_eq!(pressure.some.total_us, 49177004);
+        let full = pressure.full.unwrap();
+        assert_eq!(full.avg300, 0.30);
+        assert_eq!(full.total_us, 1234);
+    }
+
+    #[test]
+    fn full_line_is_optional() {
+        let pressure = parse_pressure("some avg10=0.00 avg60=0.00 avg300=0.00 total=0\n").unwrap();
+        assert!(pressure.full.is_none());
+    }
+
+    #[test]
+    fn some_line_is_required() {
+        assert!(parse_pressure("").is_none());
+        assert!(parse_pressure("some avg10=0.00 avg60=0.00\n").is_none());
+    }
+}
@@ -0,0 +1,71 @@
+//! Process metrics collector
+
+use std::collections::HashSet;
+
+use sysinfo::{Process, ProcessStatus, System};
+
+use crate::config::{ProcessConfig, ProcessSort};
+use crate::models::ProcessMetrics;
+
+/// Collect the top processes according to the configured ranking
+///
+/// With `ProcessSort::Both` the CPU ranking comes first, followed by any
+/// memory-heavy processes that weren't already in it.
+pub fn collect(system: &System, config: &ProcessConfig) -> Vec<ProcessMetrics> {
+    // Skip threads; their usage is already accounted to the owning process
+    let processes: Vec<&Process> = system
+        .processes()
+        .values()
+        .filter(|process| process.thread_kind().is_none())
+        .collect();
+
+    let mut selected: Vec<&Process> = Vec::new();
+
+    if matches!(config.sort_by, ProcessSort::Cpu | ProcessSort::Both) {
+        selected.extend(top_by(&processes, config.top_n, |p| p.cpu_usage() as f64));
+    }
+
+    if matches!(config.sort_by, ProcessSort::Memory | ProcessSort::Both) {
+        let seen: HashSet<_> = selected.iter().map(|p| p.pid()).collect();
+        selected.extend(
+            top_by(&processes, config.top_n, |p| p.memory() as f64)
+                .into_iter()
+                .filter(|p| !seen.contains(&p.pid())),
+        );
+    }
+
+    selected
+        .into_iter()
+        .map(|process| ProcessMetrics {
+            process_name: process.name().to_string(),
+            pid: process.pid().as_u32() as i32,
+            cpu_percent: process.cpu_usage() as f64,
+            memory_mb: process.memory() as f64 / (1024.0 * 1024.0),
+            status: status_name(process.status()).to_string(),
+        })
+        .collect()
+}
+
+/// Return the `n` processes with the highest value of `key`, highest first
+fn top_by<'a, F>(processes: &[&'a Process], n: usize, key: F) -> Vec<&'a Process>
+where
+    F: Fn(&Process) -> f64,
+{
+    let mut ranked = processes.to_vec();
+    ranked.sort_by(|a, b| key(b).total_cmp(&key(a)));
+    ranked.truncate(n);
+    ranked
+}
+
+/// Map a process status to the names stored by the backend
+fn status_name(status: ProcessStatus) -> &'static str {
+    match status {
+        ProcessStatus::Run => "running",
+        ProcessStatus::Sleep | ProcessStatus::Idle => "sleeping",
+        ProcessStatus::UninterruptibleDiskSleep => "disk_sleep",
+        ProcessStatus::Stop | ProcessStatus::Tracing => "stopped",
+        ProcessStatus::Zombie => "zombie",
+        ProcessStatus::Dead => "dead",
+        _ => "unknown",
+    }
+}
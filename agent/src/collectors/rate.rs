@@ -0,0 +1,217 @@
+//! Per-second rates for cumulative counters
+//!
+//! Kernel counters such as bytes read or received only ever go up, until
+//! they wrap around or are reset (reboot, interface or device recreated).
+//! This module keeps the previous sample and turns the difference into a
+//! per-second rate, skipping samples where the counter was reset.
+//!
+//! Counters are keyed by what they belong to, including an identity that
+//! changes when the object is recreated (an interface's ifindex, a device's
+//! major:minor number, a cgroup's inode). A recreated object then shows up
+//! as a new key instead of as a counter that went backwards.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+/// Range of a kernel counter, which decides where it wraps around
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CounterWidth {
+    /// Wraps after `u32::MAX`, e.g. `unsigned int` counters
+    U32,
+    /// Wraps after `u64::MAX`
+    U64,
+    /// Not a single fixed-width kernel counter (a sum of several counters or
+    /// a value scaled from one), so it has no wrap point and any decrease is
+    /// a reset
+    Unbounded,
+}
+
+impl CounterWidth {
+    /// Width of the kernel's `unsigned long` counters
+    pub const LONG: Self = if cfg!(target_pointer_width = "64") {
+        Self::U64
+    } else {
+        Self::U32
+    };
+
+    /// Largest value before the counter wraps, `None` if it doesn't wrap
+    fn max(self) -> Option<u64> {
+        match self {
+            Self::U32 => Some(u32::MAX as u64),
+            Self::U64 => Some(u64::MAX),
+            Self::Unbounded => None,
+        }
+    }
+}
+
+/// A kind of cumulative counter tracked by [`CounterRates`]
+pub trait Counter {
+    /// Range of the counter as reported by its source
+    fn width(&self) -> CounterWidth;
+}
+
+/// Counters keyed by what they belong to, e.g. `(device, IoCounter)`
+impl<A, C: Counter> Counter for (A, C) {
+    fn width(&self) -> CounterWidth {
+        self.1.width()
+    }
+}
+
+/// Counters keyed by what they belong to and its identity,
+/// e.g. `(interface, ifindex, NetCounter)`
+impl<A, B, C: Counter> Counter for (A, B, C) {
+    fn width(&self) -> CounterWidth {
+        self.2.width()
+    }
+}
+
+/// Tracks the previous value of a set of counters to compute rates
+pub struct CounterRates<K> {
+    previous: HashMap<K, u64>,
+    taken_at: Option<Instant>,
+}
+
+impl<K: Counter + Eq + Hash + Clone> CounterRates<K> {
+    pub fn new() -> Self {
+        Self {
+            previous: HashMap::new(),
+            taken_at: None,
+        }
+    }
+
+    /// Record a new sample and return the per-second rate of every counter
+    ///
+    /// Counters that weren't in the previous sample, or that were reset since,
+    /// have no rate this time.
+    pub fn update<I>(&mut self, counters: I) -> HashMap<K, f64>
+    where
+        I: IntoIterator<Item = (K, u64)>,
+    {
+        let now = Instant::now();
+        let elapsed = self.taken_at.map(|taken_at| now.duration_since(taken_at));
+        let mut rates = HashMap::new();
+        let mut current = HashMap::new();
+
+        for (key, value) in counters {
+            if let (Some(elapsed), Some(&previous)) = (elapsed, self.previous.get(&key)) {
+                if let Some(rate) = per_second(previous, value, key.width(), elapsed) {
+                    rates.insert(key.clone(), rate);
+                }
+            }
+            current.insert(key, value);
+        }
+
+        // Counters that disappeared are forgotten, so a recreated one starts fresh
+        self.previous = current;
+        self.taken_at = Some(now);
+        rates
+    }
+}
+
+impl<K: Counter + Eq + Hash + Clone> Default for CounterRates<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Per-second rate between two readings of a cumulative counter
+pub fn per_second(previous: u64, current: u64, width: CounterWidth, elapsed: Duration) -> Option<f64> {
+    let seconds = elapsed.as_secs_f64();
+    if seconds <= 0.0 {
+        return None;
+    }
+    counter_delta(previous, current, width).map(|delta| delta as f64 / seconds)
+}
+
+/// Increase of a cumulative counter between two readings
+///
+/// When the counter went backwards it either wrapped or was reset. It only
+/// counts as a wrap if the counter has a wrap point and the previous reading
+/// was in the upper half of its range; otherwise it's a reset and there is
+/// no meaningful delta.
+pub fn counter_delta(previous: u64, current: u64, width: CounterWidth) -> Option<u64> {
+    if current >= previous {
+        return Some(current - previous);
+    }
+
+    let max = width.max()?;
+    if previous > max || current > max || previous < max / 2 {
+        return None;
+    }
+
+    // Distance to the top of the range, then from zero up to the new value
+    (max - previous).checked_add(current)?.checked_add(1)
+}
+
+/// Sum rates of several counters, ignoring counters without a rate
+///
+/// Returns `None` only if none of them has a rate yet.
+pub fn sum_rates<I>(rates: I) -> Option<f64>
+where
+    I: IntoIterator<Item = Option<f64>>,
+{
+    rates.into_iter().flatten().fold(None, |sum, rate| Some(sum.unwrap_or(0.0) + rate))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delta_of_increasing_counter() {
+        assert_eq!(counter_delta(100, 250, CounterWidth::U32), Some(150));
+        assert_eq!(counter_delta(100, 100, CounterWidth::U64), Some(0));
+        assert_eq!(counter_delta(100, 250, CounterWidth::Unbounded), Some(150));
+    }
+
+    #[test]
+    fn delta_across_u32_wrap() {
+        let previous = u32::MAX as u64 - 9;
+        assert_eq!(counter_delta(previous, 5, CounterWidth::U32), Some(15));
+    }
+
+    #[test]
+    fn delta_across_u64_wrap() {
+        assert_eq!(counter_delta(u64::MAX - 9, 5, CounterWidth::U64), Some(15));
+    }
+
+    #[test]
+    fn reset_has_no_delta() {
+        assert_eq!(counter_delta(1_000, 5, CounterWidth::U32), None);
+        assert_eq!(counter_delta(1_000, 5, CounterWidth::U64), None);
+        assert_eq!(counter_delta(1_000, 5, CounterWidth::Unbounded), None);
+    }
+
+    #[test]
+    fn reset_of_large_counter_has_no_delta() {
+        // High in the 32-bit range, but a 64-bit counter can't have wrapped there
+        let previous = u32::MAX as u64 - 9;
+        assert_eq!(counter_delta(previous, 5, CounterWidth::U64), None);
+        // Beyond the 32-bit range, so not a 32-bit counter at all
+        assert_eq!(counter_delta(u64::MAX - 9, 5, CounterWidth::U32), None);
+        // Sums never wrap, however large they got
+        assert_eq!(counter_delta(u64::MAX - 9, 5, CounterWidth::Unbounded), None);
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    struct Wrapping32;
+
+    impl Counter for Wrapping32 {
+        fn width(&self) -> CounterWidth {
+            CounterWidth::U32
+        }
+    }
+
+    #[test]
+    fn rates_need_a_previous_sample_of_the_same_key() {
+        let mut rates = CounterRates::new();
+        assert!(rates.update([(("eth0", 2, Wrapping32), 100)]).is_empty());
+
+        std::thread::sleep(Duration::from_millis(10));
+        let per_second = rates.update([(("eth0", 2, Wrapping32), 200), (("eth0", 3, Wrapping32), 50)]);
+        assert!(per_second[&("eth0", 2, Wrapping32)] > 0.0);
+        // Same name, new identity: no rate until the next sample
+        assert!(!per_second.contains_key(&("eth0", 3, Wrapping32)));
+    }
+}
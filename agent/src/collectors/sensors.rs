@@ -0,0 +1,303 @@
+//! Hardware temperature and fan sensor collector
+//!
+//! Reads hwmon chips from `<sysfs>/class/hwmon` and thermal zones from
+//! `<sysfs>/class/thermal`. The sysfs root is a parameter so the collector
+//! can run against a copy of the tree. Virtual machines usually expose no
+//! sensors at all, in which case nothing is reported.
+
+use std::path::Path;
+
+use crate::models::{FanSensor, TemperatureSensor};
+
+/// Where sysfs is mounted
+pub const SYSFS_ROOT: &str = "/sys";
+
+/// hwmon chips that measure CPU temperatures
+const CPU_CHIPS: &[&str] = &["coretemp", "k10temp", "k8temp", "zenpower", "cpu_thermal", "soc_thermal"];
+
+/// Sensor readings
+pub struct SensorMetrics {
+    pub temperatures: Vec<TemperatureSensor>,
+    pub fans: Vec<FanSensor>,
+    /// Hottest CPU reading: the package temperature, or the hottest core
+    pub cpu_temperature: Option<f64>,
+}
+
+/// Collect sensors from the system's sysfs
+pub fn collect() -> SensorMetrics {
+    collect_from(Path::new(SYSFS_ROOT))
+}
+
+/// Collect sensors from a sysfs tree rooted at `sysfs`
+pub fn collect_from(sysfs: &Path) -> SensorMetrics {
+    let mut temperatures = Vec::new();
+    let mut fans = Vec::new();
+
+    for chip_dir in sorted_entries(&sysfs.join("class/hwmon"), "hwmon") {
+        read_hwmon_chip(&chip_dir, &mut temperatures, &mut fans);
+    }
+
+    // Thermal zones backed by an hwmon chip (e.g. acpitz) are already covered
+    for zone_dir in sorted_entries(&sysfs.join("class/thermal"), "thermal_zone") {
+        let Some(zone_type) = read_trimmed(&zone_dir.join("type")) else {
+            continue;
+        };
+        if temperatures.iter().any(|t: &TemperatureSensor| t.chip == zone_type) {
+            continue;
+        }
+        let Some(celsius) = read_millidegrees(&zone_dir.join("temp")) else {
+            continue;
+        };
+        temperatures.push(TemperatureSensor {
+            chip: zone_type.clone(),
+            device: file_name(&zone_dir),
+            label: zone_type,
+            celsius,
+            max: None,
+            critical: None,
+        });
+    }
+
+    let cpu_temperature = cpu_temperature(&temperatures);
+
+    SensorMetrics {
+        temperatures,
+        fans,
+        cpu_temperature,
+    }
+}
+
+/// Read every `tempN_*` and `fanN_*` sensor of one hwmon chip
+///
+/// Temperatures are in millidegrees Celsius and fan speeds in RPM. Sensors
+/// without a `_label` file are named after the sensor (`temp1`, `fan2`).
+fn read_hwmon_chip(chip_dir: &Path, temperatures: &mut Vec<TemperatureSensor>, fans: &mut Vec<FanSensor>) {
+    // Older drivers keep their files under device/
+    let dir = if chip_dir.join("name").exists() {
+        chip_dir.to_path_buf()
+    } else {
+        chip_dir.join("device")
+    };
+    let Some(chip) = read_trimmed(&dir.join("name")) else {
+        return;
+    };
+    // e.g. "nvme0" or "coretemp.0", to tell identical chips apart
+    let device = std::fs::canonicalize(chip_dir.join("device"))
+        .ok()
+        .and_then(|path| file_name(&path));
+
+    let Ok(entries) = std::fs::read_dir(&dir) else {
+        return;
+    };
+    let mut inputs: Vec<String> = entries
+        .flatten()
+        .filter_map(|entry| entry.file_name().to_str().map(str::to_string))
+        .filter(|name| name.ends_with("_input"))
+        .collect();
+    inputs.sort_by_key(|name| sensor_sort_key(name));
+
+    for input in inputs {
+        let sensor = input.trim_end_matches("_input");
+        let label = read_trimmed(&dir.join(format!("{}_label", sensor))).unwrap_or_else(|| sensor.to_string());
+
+        if sensor.starts_with("temp") {
+            let Some(celsius) = read_millidegrees(&dir.join(&input)) else {
+                continue;
+            };
+            temperatures.push(TemperatureSensor {
+                chip: chip.clone(),
+                device: device.clone(),
+                label,
+                celsius,
+                max: read_millidegrees(&dir.join(format!("{}_max", sensor))),
+                critical: read_millidegrees(&dir.join(format!("{}_crit", sensor))),
+            });
+        } else if sensor.starts_with("fan") {
+            let Some(rpm) = read_trimmed(&dir.join(&input)).and_then(|v| v.parse().ok()) else {
+                continue;
+            };
+            fans.push(FanSensor {
+                chip: chip.clone(),
+                device: device.clone(),
+                label,
+                rpm,
+            });
+        }
+    }
+}
+
+/// The CPU package temperature if there is one, otherwise the hottest CPU sensor
+fn cpu_temperature(temperatures: &[TemperatureSensor]) -> Option<f64> {
+    let cpu_sensors = || temperatures.iter().filter(|t| CPU_CHIPS.contains(&t.chip.as_str()));
+
+    cpu_sensors()
+        .filter(|t| t.label.starts_with("Package") || t.label == "Tctl" || t.label == "Tdie")
+        .map(|t| t.celsius)
+        .reduce(f64::max)
+        .or_else(|| cpu_sensors().map(|t| t.celsius).reduce(f64::max))
+}
+
+/// Entries of a sysfs class directory whose names start with `prefix`, in numeric order
+fn sorted_entries(dir: &Path, prefix: &str) -> Vec<std::path::PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<_> = entries
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with(prefix))
+        .map(|entry| entry.path())
+        .collect();
+    paths.sort_by_key(|path| file_name(path).map(|name| sensor_sort_key(&name)));
+    paths
+}
+
+/// Sort `temp2_input` before `temp10_input` and `hwmon2` before `hwmon10`
+fn sensor_sort_key(name: &str) -> (String, u32) {
+    let prefix: String = name.chars().take_while(|c| !c.is_ascii_digit()).collect();
+    let number = name[prefix.len()..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect::<String>()
+        .parse()
+        .unwrap_or(0);
+    (prefix, number)
+}
+
+fn file_name(path: &Path) -> Option<String> {
+    path.file_name().map(|name| name.to_string_lossy().to_string())
+}
+
+/// Read a sysfs attribute; fails for sensors that are present but not readable
+fn read_trimmed(path: &Path) -> Option<String> {
+    std::fs::read_to_string(path)
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+/// Read a millidegree Celsius value as degrees
+fn read_millidegrees(path: &Path) -> Option<f64> {
+    read_trimmed(path)?
+        .parse::<i64>()
+        .ok()
+        .map(|millidegrees| millidegrees as f64 / 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// Write sysfs attributes under `dir`, creating directories as needed
+    fn write_files(dir: &Path, files: &[(&str, &str)]) {
+        for (name, content) in files {
+            let path = dir.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, format!("{}\n", content)).unwrap();
+        }
+    }
+
+    /// A fake sysfs with a CPU chip, an old-style chip keeping its files
+    /// under device/, an hwmon-backed thermal zone and a standalone one
+    fn fake_sysfs() -> tempfile::TempDir {
+        let sysfs = tempfile::tempdir().unwrap();
+        let hwmon = sysfs.path().join("class/hwmon");
+
+        write_files(
+            &hwmon.join("hwmon0"),
+            &[
+                ("name", "coretemp"),
+                ("temp1_input", "61000"),
+                ("temp1_label", "Package id 0"),
+                ("temp1_crit", "100000"),
+                ("temp2_input", "72500"),
+                ("temp2_label", "Core 0"),
+                ("temp10_input", "58000"),
+                ("temp10_label", "Core 8"),
+            ],
+        );
+        write_files(
+            &hwmon.join("hwmon1/device"),
+            &[("name", "it8728"), ("temp1_input", "41000"), ("fan2_input", "1200")],
+        );
+        write_files(&hwmon.join("hwmon2"), &[("name", "acpitz"), ("temp1_input", "27800")]);
+
+        let thermal = sysfs.path().join("class/thermal");
+        write_files(&thermal.join("thermal_zone0"), &[("type", "acpitz"), ("temp", "27800")]);
+        write_files(&thermal.join("thermal_zone1"), &[("type", "x86_pkg_temp"), ("temp", "-1500")]);
+        sysfs
+    }
+
+    #[test]
+    fn reads_hwmon_chips_and_thermal_zones() {
+        let sysfs = fake_sysfs();
+        let metrics = collect_from(sysfs.path());
+
+        let temperatures: Vec<(&str, &str, f64)> = metrics
+            .temperatures
+            .iter()
+            .map(|t| (t.chip.as_str(), t.label.as_str(), t.celsius))
+            .collect();
+        assert_eq!(
+            temperatures,
+            [
+                ("coretemp", "Package id 0", 61.0),
+                ("coretemp", "Core 0", 72.5),
+                ("coretemp", "Core 8", 58.0),
+                ("it8728", "temp1", 41.0),
+                ("acpitz", "temp1", 27.8),
+                ("x86_pkg_temp", "x86_pkg_temp", -1.5),
+            ]
+        );
+
+        let package = &metrics.temperatures[0];
+        assert_eq!(package.critical, Some(100.0));
+        assert_eq!(package.max, None);
+        assert_eq!(metrics.temperatures[5].device.as_deref(), Some("thermal_zone1"));
+
+        assert_eq!(metrics.fans.len(), 1);
+        assert_eq!(metrics.fans[0].chip, "it8728");
+        assert_eq!(metrics.fans[0].label, "fan2");
+        assert_eq!(metrics.fans[0].rpm, 1200);
+    }
+
+    #[test]
+    fn cpu_temperature_prefers_the_package_sensor() {
+        let sysfs = fake_sysfs();
+        // The hottest core is ignored while there is a package reading
+        assert_eq!(collect_from(sysfs.path()).cpu_temperature, Some(61.0));
+
+        fs::remove_file(sysfs.path().join("class/hwmon/hwmon0/temp1_input")).unwrap();
+        assert_eq!(collect_from(sysfs.path()).cpu_temperature, Some(72.5));
+    }
+
+    #[test]
+    fn cpu_temperature_uses_tctl_on_amd() {
+        let sensor = |label: &str, celsius| TemperatureSensor {
+            chip: "k10temp".to_string(),
+            device: None,
+            label: label.to_string(),
+            celsius,
+            max: None,
+            critical: None,
+        };
+        let temperatures = [sensor("Tccd1", 80.0), sensor("Tctl", 65.0)];
+        assert_eq!(cpu_temperature(&temperatures), Some(65.0));
+
+        // Sensors of other chips never count as the CPU
+        let nvme = TemperatureSensor {
+            chip: "nvme".to_string(),
+            ..sensor("Composite", 40.0)
+        };
+        assert_eq!(cpu_temperature(&[nvme]), None);
+    }
+
+    #[test]
+    fn missing_sysfs_reports_nothing() {
+        let sysfs = tempfile::tempdir().unwrap();
+        let metrics = collect_from(sysfs.path());
+        assert!(metrics.temperatures.is_empty());
+        assert!(metrics.fans.is_empty());
+        assert_eq!(metrics.cpu_temperature, None);
+    }
+}
@@ -0,0 +1,283 @@
+//! Service discovery from listening sockets
+//!
+//! Finds TCP sockets in the LISTEN state in /proc/net/tcp{,6}, maps them to
+//! their owning processes through the `socket:[inode]` links in
+//! /proc/<pid>/fd, and classifies well-known services by process name and
+//! port. Mapping sockets of other users' processes requires root. Docker
+//! containers and systemd units are added to the inventory by their
+//! collectors.
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use tracing::warn;
+
+use super::docker::{self, DockerClient};
+use super::systemd::{self, SystemBus, UnitFilter};
+use crate::config::Config;
+use crate::models::{ServiceInfo, ServiceInventory, ServiceStatus, ServiceType};
+
+/// Well-known services: process names (as in /proc/<pid>/comm), default
+/// ports, the name reported for them and their type
+const KNOWN_SERVICES: &[(&[&str], &[u16], &str, ServiceType)] = &[
+    (&["postgres", "postmaster"], &[5432], "postgres", ServiceType::Database),
+    (&["mysqld", "mariadbd"], &[3306], "mysql", ServiceType::Database),
+    (&["mongod", "mongos"], &[27017], "mongodb", ServiceType::Database),
+    (&["redis-server"], &[6379], "redis", ServiceType::Database),
+    (&["valkey-server"], &[], "valkey", ServiceType::Database),
+    (&["memcached"], &[11211], "memcached", ServiceType::Database),
+    (&["clickhouse-serv", "clickhouse"], &[8123, 9000], "clickhouse", ServiceType::Database),
+    (&["influxd"], &[8086], "influxdb", ServiceType::Database),
+    (&["etcd"], &[2379], "etcd", ServiceType::Database),
+    (&["nginx"], &[], "nginx", ServiceType::Http),
+    (&["apache2", "httpd"], &[], "apache", ServiceType::Http),
+    (&["caddy"], &[], "caddy", ServiceType::Http),
+    (&["haproxy"], &[], "haproxy", ServiceType::Http),
+    (&["traefik"], &[], "traefik", ServiceType::Http),
+    (&["envoy"], &[], "envoy", ServiceType::Http),
+    (&["sshd"], &[22], "ssh", ServiceType::Process),
+];
+
+/// Processes that listen on behalf of something else, such as published
+/// container ports; their ports are classified by number alone
+const PROXY_PROCESSES: &[&str] = &["docker-proxy", "rootlesskit"];
+
+/// Ports that are HTTP when nothing more specific is known about them
+const HTTP_PORTS: &[u16] = &[80, 443, 3000, 5000, 8000, 8080, 8443, 8888];
+
+/// Service types produced by listening-port discovery
+pub const SERVICE_TYPES: &[ServiceType] = &[ServiceType::Http, ServiceType::Database, ServiceType::Process];
+
+/// Where the service inventory comes from
+#[derive(Debug, Clone, Default)]
+pub struct ServiceSources {
+    /// Listening TCP ports (`features.discover_services`)
+    pub listening_ports: bool,
+    /// Docker containers (`features.collect_docker`)
+    pub docker: Option<DockerClient>,
+    /// systemd units (`features.monitor_systemd`)
+    pub systemd_units: Option<UnitFilter>,
+}
+
+impl ServiceSources {
+    /// Pick the sources enabled in the agent config
+    pub fn from_config(config: &Config) -> Result<Self> {
+        Ok(Self {
+            listening_ports: config.features.discover_services,
+            docker: config
+                .features
+                .collect_docker
+                .then(|| DockerClient::from_config(&config.docker)),
+            systemd_units: if config.features.monitor_systemd {
+                Some(UnitFilter::from_config(&config.systemd)?)
+            } else {
+                None
+            },
+        })
+    }
+
+    /// Whether no source is enabled
+    pub fn is_empty(&self) -> bool {
+        !self.listening_ports && self.docker.is_none() && self.systemd_units.is_none()
+    }
+}
+
+/// Build the service inventory from the enabled sources
+///
+/// When a source can't be read its service type is left out of the
+/// inventory, so the backend keeps the services it already knows about
+/// instead of marking them stopped. systemd units share the process type
+/// with listening ports, so both are left out if systemd can't be read.
+pub fn inventory(sources: &ServiceSources) -> ServiceInventory {
+    let mut inventory = ServiceInventory {
+        service_types: Vec::new(),
+        services: Vec::new(),
+    };
+
+    if sources.listening_ports {
+        inventory.service_types.extend_from_slice(SERVICE_TYPES);
+        inventory.services = discover();
+    }
+    if let Some(client) = &sources.docker {
+        match docker::discover(client) {
+            Ok(containers) => {
+                inventory.service_types.push(ServiceType::Docker);
+                inventory.services.extend(containers);
+            }
+            Err(e) => warn!("Skipping Docker containers in the service inventory: {:#}", e),
+        }
+    }
+    if let Some(filter) = &sources.systemd_units {
+        let units = SystemBus::connect().and_then(|bus| systemd::discover(&bus, filter));
+        match units {
+            Ok(units) => {
+                if !inventory.service_types.contains(&ServiceType::Process) {
+                    inventory.service_types.push(ServiceType::Process);
+                }
+                inventory.services.extend(units);
+            }
+            Err(e) => {
+                warn!("Skipping systemd units in the service inventory: {:#}", e);
+                inventory.service_types.retain(|t| *t != ServiceType::Process);
+                inventory.services.retain(|s| s.service_type != ServiceType::Process);
+            }
+        }
+    }
+
+    inventory
+}
+
+/// A TCP socket in the LISTEN state
+struct ListeningSocket {
+    port: u16,
+    inode: u64,
+}
+
+/// Discover services listening on TCP ports
+///
+/// A service listening on several addresses (e.g. IPv4 and IPv6) is
+/// reported once per port.
+pub fn discover() -> Vec<ServiceInfo> {
+    let sockets = read_listening_sockets();
+    let owners = socket_owners(&sockets.iter().map(|s| s.inode).collect());
+
+    let mut services: Vec<ServiceInfo> = Vec::new();
+    for socket in sockets {
+        let process = owners.get(&socket.inode).map(String::as_str);
+        let service = classify(socket.port, process);
+        let duplicate = services
+            .iter()
+            .any(|s| s.port == service.port && s.name == service.name);
+        if !duplicate {
+            services.push(service);
+        }
+    }
+
+    services.sort_by(|a, b| a.port.cmp(&b.port).then_with(|| a.name.cmp(&b.name)));
+    services
+}
+
+/// Name and type a listening port by its owning process, falling back to the port number
+fn classify(port: u16, process: Option<&str>) -> ServiceInfo {
+    let by_name = process.and_then(|process| {
+        KNOWN_SERVICES
+            .iter()
+            .find(|(names, _, _, _)| names.contains(&process))
+    });
+    // Only trust the port number when the real owner is unknown
+    let by_port = || {
+        KNOWN_SERVICES
+            .iter()
+            .find(|(_, ports, _, _)| ports.contains(&port))
+    };
+    let known = match process {
+        Some(process) if !PROXY_PROCESSES.contains(&process) => by_name,
+        _ => by_port(),
+    };
+
+    let (name, service_type) = match (known, process) {
+        (Some((_, _, name, service_type)), _) => (name.to_string(), *service_type),
+        (None, Some(process)) if HTTP_PORTS.contains(&port) => (process.to_string(), ServiceType::Http),
+        (None, Some(process)) => (process.to_string(), ServiceType::Process),
+        (None, None) if HTTP_PORTS.contains(&port) => (format!("port-{}", port), ServiceType::Http),
+        (None, None) => (format!("port-{}", port), ServiceType::Process),
+    };
+
+    ServiceInfo {
+        service_type,
+        name,
+        port: Some(port),
+        status: ServiceStatus::Running,
+    }
+}
+
+/// Read TCP sockets in the LISTEN state from /proc/net/tcp and /proc/net/tcp6 (Linux only)
+///
+/// Format (after a header line):
+/// `sl local_address rem_address st ... uid timeout inode ...`
+/// where `local_address` is `<hex ip>:<hex port>` and `st` is `0A` for LISTEN.
+#[cfg(target_os = "linux")]
+fn read_listening_sockets() -> Vec<ListeningSocket> {
+    const TCP_LISTEN: &str = "0A";
+
+    let mut sockets = Vec::new();
+    for path in ["/proc/net/tcp", "/proc/net/tcp6"] {
+        let Ok(content) = std::fs::read_to_string(path) else {
+            continue;
+        };
+        for line in content.lines().skip(1) {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 10 || fields[3] != TCP_LISTEN {
+                continue;
+            }
+            let port = fields[1]
+                .rsplit_once(':')
+                .and_then(|(_, port)| u16::from_str_radix(port, 16).ok());
+            let inode = fields[9].parse().ok();
+            if let (Some(port), Some(inode)) = (port, inode) {
+                sockets.push(ListeningSocket { port, inode });
+            }
+        }
+    }
+    sockets
+}
+
+/// Fallback for non-Linux systems
+#[cfg(not(target_os = "linux"))]
+fn read_listening_sockets() -> Vec<ListeningSocket> {
+    Vec::new()
+}
+
+/// Map the given socket inodes to the name of the process holding them (Linux only)
+///
+/// Processes that exit or deny access while being scanned are skipped.
+#[cfg(target_os = "linux")]
+fn socket_owners(inodes: &HashSet<u64>) -> HashMap<u64, String> {
+    let mut owners = HashMap::new();
+
+    let Ok(proc_entries) = std::fs::read_dir("/proc") else {
+        return owners;
+    };
+    for entry in proc_entries.flatten() {
+        let file_name = entry.file_name();
+        if !file_name.to_string_lossy().bytes().all(|b| b.is_ascii_digit()) {
+            continue;
+        }
+        let Ok(fds) = std::fs::read_dir(entry.path().join("fd")) else {
+            continue;
+        };
+
+        let mut name = None;
+        for fd in fds.flatten() {
+            let Ok(target) = std::fs::read_link(fd.path()) else {
+                continue;
+            };
+            let Some(inode) = target
+                .to_str()
+                .and_then(|t| t.strip_prefix("socket:["))
+                .and_then(|t| t.strip_suffix(']'))
+                .and_then(|t| t.parse::<u64>().ok())
+                .filter(|inode| inodes.contains(inode))
+            else {
+                continue;
+            };
+            let name = name.get_or_insert_with(|| {
+                std::fs::read_to_string(entry.path().join("comm"))
+                    .map(|comm| comm.trim().to_string())
+                    .unwrap_or_default()
+            });
+            if !name.is_empty() {
+                // Forked workers share the listener; the first process found wins
+                owners.entry(inode).or_insert_with(|| name.clone());
+            }
+        }
+    }
+
+    owners
+}
+
+/// Fallback for non-Linux systems
+#[cfg(not(target_os = "linux"))]
+fn socket_owners(_inodes: &HashSet<u64>) -> HashMap<u64, String> {
+    HashMap::new()
+}
@@ -0,0 +1,322 @@
+//! TCP/UDP socket statistics collector
+//!
+//! Combines socket counts from /proc/net/sockstat, connection states from
+//! /proc/net/tcp{,6} and protocol counters from /proc/net/snmp{,6} and
+//! /proc/net/netstat. The counters are cumulative, so they are reported as
+//! per-second rates.
+
+use std::collections::HashMap;
+
+use super::rate::{Counter, CounterRates, CounterWidth};
+use crate::models::{SocketMetrics, TcpStateCounts};
+
+/// Cumulative protocol counters that are turned into rates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SocketCounter {
+    TcpActiveOpens,
+    TcpPassiveOpens,
+    TcpAttemptFails,
+    TcpEstabResets,
+    TcpOutSegs,
+    TcpRetransSegs,
+    TcpInErrs,
+    TcpOutRsts,
+    TcpTimeouts,
+    ListenOverflows,
+    ListenDrops,
+    UdpNoPorts,
+    UdpInErrors,
+    UdpRcvbufErrors,
+    UdpSndbufErrors,
+}
+
+impl Counter for SocketCounter {
+    fn width(&self) -> CounterWidth {
+        match self {
+            // Sums of the IPv4 and IPv6 counters
+            Self::UdpNoPorts | Self::UdpInErrors | Self::UdpRcvbufErrors | Self::UdpSndbufErrors => {
+                CounterWidth::Unbounded
+            }
+            _ => CounterWidth::LONG,
+        }
+    }
+}
+
+/// Previous protocol counters
+pub type SocketRates = CounterRates<SocketCounter>;
+
+/// Collect socket statistics (Linux only, `None` elsewhere)
+pub fn collect(rates: &mut SocketRates) -> Option<SocketMetrics> {
+    let sockstat = read_sockstat()?;
+    let tcp_states = read_tcp_states();
+    let rates = rates.update(read_protocol_counters());
+    let rate = |counter| rates.get(&counter).copied();
+
+    // Share of sent segments that were retransmissions
+    let retransmit_ratio = match (rate(SocketCounter::TcpRetransSegs), rate(SocketCounter::TcpOutSegs)) {
+        (Some(retrans), Some(out)) if out > 0.0 => Some((retrans / out) * 100.0),
+        (Some(_), Some(_)) => Some(0.0),
+        _ => None,
+    };
+
+    Some(SocketMetrics {
+        sockets_used: sockstat.get("sockets:used").copied(),
+        tcp_inuse: sockstat.get("TCP:inuse").copied(),
+        tcp6_inuse: sockstat.get("TCP6:inuse").copied(),
+        tcp_orphan: sockstat.get("TCP:orphan").copied(),
+        tcp_time_wait: sockstat.get("TCP:tw").copied(),
+        tcp_alloc: sockstat.get("TCP:alloc").copied(),
+        tcp_mem_pages: sockstat.get("TCP:mem").copied(),
+        udp_inuse: sockstat.get("UDP:inuse").copied(),
+        udp6_inuse: sockstat.get("UDP6:inuse").copied(),
+        udp_mem_pages: sockstat.get("UDP:mem").copied(),
+        tcp_states,
+        tcp_active_opens_per_sec: rate(SocketCounter::TcpActiveOpens),
+        tcp_passive_opens_per_sec: rate(SocketCounter::TcpPassiveOpens),
+        tcp_attempt_fails_per_sec: rate(SocketCounter::TcpAttemptFails),
+        tcp_estab_resets_per_sec: rate(SocketCounter::TcpEstabResets),
+        tcp_out_segs_per_sec: rate(SocketCounter::TcpOutSegs),
+        tcp_retransmits_per_sec: rate(SocketCounter::TcpRetransSegs),
+        tcp_retransmit_ratio: retransmit_ratio,
+        tcp_in_errors_per_sec: rate(SocketCounter::TcpInErrs),
+        tcp_resets_sent_per_sec: rate(SocketCounter::TcpOutRsts),
+        tcp_timeouts_per_sec: rate(SocketCounter::TcpTimeouts),
+        listen_overflows_per_sec: rate(SocketCounter::ListenOverflows),
+        listen_drops_per_sec: rate(SocketCounter::ListenDrops),
+        udp_no_ports_per_sec: rate(SocketCounter::UdpNoPorts),
+        udp_in_errors_per_sec: rate(SocketCounter::UdpInErrors),
+        udp_rcvbuf_errors_per_sec: rate(SocketCounter::UdpRcvbufErrors),
+        udp_sndbuf_errors_per_sec: rate(SocketCounter::UdpSndbufErrors),
+    })
+}
+
+/// Read socket counts from /proc/net/sockstat{,6}, keyed as `PROTO:field`
+///
+/// Format: `TCP: inuse 5 orphan 0 tw 5 alloc 5 mem 0`
+#[cfg(target_os = "linux")]
+fn read_sockstat() -> Option<HashMap<String, u64>> {
+    let content = std::fs::read_to_string("/proc/net/sockstat").ok()?;
+    // sockstat6 is missing when IPv6 is disabled
+    let content6 = std::fs::read_to_string("/proc/net/sockstat6").unwrap_or_default();
+    Some(parse_sockstat(&content, &content6))
+}
+
+/// Parse the contents of /proc/net/sockstat and /proc/net/sockstat6
+#[cfg(target_os = "linux")]
+fn parse_sockstat(content: &str, content6: &str) -> HashMap<String, u64> {
+    let mut counts = HashMap::new();
+    for line in content.lines().chain(content6.lines()) {
+        let Some((protocol, fields)) = line.split_once(':') else {
+            continue;
+        };
+        let fields: Vec<&str> = fields.split_whitespace().collect();
+        for pair in fields.chunks_exact(2) {
+            if let Ok(value) = pair[1].parse() {
+                counts.insert(format!("{}:{}", protocol, pair[0]), value);
+            }
+        }
+    }
+
+    counts
+}
+
+/// Fallback for non-Linux systems
+#[cfg(not(target_os = "linux"))]
+fn read_sockstat() -> Option<HashMap<String, u64>> {
+    None
+}
+
+/// Count TCP sockets by state from /proc/net/tcp and /proc/net/tcp6
+///
+/// The fourth column is the state as a hex number, in the kernel's
+/// `TCP_ESTABLISHED` (1) ... `TCP_NEW_SYN_RECV` (12) order.
+#[cfg(target_os = "linux")]
+fn read_tcp_states() -> TcpStateCounts {
+    let mut states = TcpStateCounts::default();
+
+    for path in ["/proc/net/tcp", "/proc/net/tcp6"] {
+        let Ok(content) = std::fs::read_to_string(path) else {
+            continue;
+        };
+        for line in content.lines().skip(1) {
+            let Some(state) = line
+                .split_whitespace()
+                .nth(3)
+                .and_then(|state| u8::from_str_radix(state, 16).ok())
+            else {
+                continue;
+            };
+            match state {
+                0x01 => states.established += 1,
+                0x02 => states.syn_sent += 1,
+                // Pending connections show up as NEW_SYN_RECV on Linux 4.4+
+                0x03 | 0x0C => states.syn_recv += 1,
+                0x04 => states.fin_wait1 += 1,
+                0x05 => states.fin_wait2 += 1,
+                0x06 => states.time_wait += 1,
+                0x07 => states.close += 1,
+                0x08 => states.close_wait += 1,
+                0x09 => states.last_ack += 1,
+                0x0A => states.listen += 1,
+                0x0B => states.closing += 1,
+                _ => {}
+            }
+        }
+    }
+
+    states
+}
+
+/// Fallback for non-Linux systems
+#[cfg(not(target_os = "linux"))]
+fn read_tcp_states() -> TcpStateCounts {
+    TcpStateCounts::default()
+}
+
+/// Read protocol counters from /proc/net/snmp, /proc/net/snmp6 and /proc/net/netstat
+///
+/// snmp and netstat come as pairs of lines, a header naming the fields and a
+/// line with their values:
+/// ```text
+/// Tcp: RtoAlgorithm RtoMin ... RetransSegs InErrs OutRsts InCsumErrors
+/// Tcp: 1 200 ... 1 0 16 0
+/// ```
+/// snmp6 has one `Name value` pair per line. The TCP counters cover IPv4 and
+/// IPv6 already; UDP is counted separately for each, so the two are added up.
+#[cfg(target_os = "linux")]
+fn read_protocol_counters() -> Vec<(SocketCounter, u64)> {
+    let read = |path| std::fs::read_to_string(path).unwrap_or_default();
+    parse_protocol_counters(&read("/proc/net/snmp"), &read("/proc/net/netstat"), &read("/proc/net/snmp6"))
+}
+
+/// Parse the counters out of the contents of snmp, netstat and snmp6
+#[cfg(target_os = "linux")]
+fn parse_protocol_counters(snmp: &str, netstat: &str, snmp6: &str) -> Vec<(SocketCounter, u64)> {
+    let mut values: HashMap<String, u64> = HashMap::new();
+
+    for content in [snmp, netstat] {
+        let lines: Vec<&str> = content.lines().collect();
+        for pair in lines.chunks_exact(2) {
+            let (Some((protocol, names)), Some((_, numbers))) =
+                (pair[0].split_once(':'), pair[1].split_once(':'))
+            else {
+                continue;
+            };
+            for (name, value) in names.split_whitespace().zip(numbers.split_whitespace()) {
+                if let Ok(value) = value.parse() {
+                    values.insert(format!("{}:{}", protocol, name), value);
+                }
+            }
+        }
+    }
+
+    for line in snmp6.lines() {
+        let mut fields = line.split_whitespace();
+        if let (Some(name), Some(Ok(value))) = (fields.next(), fields.next().map(str::parse::<u64>)) {
+            if let Some(name) = name.strip_prefix("Udp6") {
+                *values.entry(format!("Udp:{}", name)).or_default() += value;
+            }
+        }
+    }
+
+    const COUNTERS: [(SocketCounter, &str); 15] = [
+        (SocketCounter::TcpActiveOpens, "Tcp:ActiveOpens"),
+        (SocketCounter::TcpPassiveOpens, "Tcp:PassiveOpens"),
+        (SocketCounter::TcpAttemptFails, "Tcp:AttemptFails"),
+        (SocketCounter::TcpEstabResets, "Tcp:EstabResets"),
+        (SocketCounter::TcpOutSegs, "Tcp:OutSegs"),
+        (SocketCounter::TcpRetransSegs, "Tcp:RetransSegs"),
+        (SocketCounter::TcpInErrs, "Tcp:InErrs"),
+        (SocketCounter::TcpOutRsts, "Tcp:OutRsts"),
+        (SocketCounter::TcpTimeouts, "TcpExt:TCPTimeouts"),
+        (SocketCounter::ListenOverflows, "TcpExt:ListenOverflows"),
+        (SocketCounter::ListenDrops, "TcpExt:ListenDrops"),
+        (SocketCounter::UdpNoPorts, "Udp:NoPorts"),
+        (SocketCounter::UdpInErrors, "Udp:InErrors"),
+        (SocketCounter::UdpRcvbufErrors, "Udp:RcvbufErrors"),
+        (SocketCounter::UdpSndbufErrors, "Udp:SndbufErrors"),
+    ];
+
+    COUNTERS
+        .iter()
+        .filter_map(|(counter, key)| Some((*counter, *values.get(*key)?)))
+        .collect()
+}
+
+/// Fallback for non-Linux systems
+#[cfg(not(target_os = "linux"))]
+fn read_protocol_counters() -> Vec<(SocketCounter, u64)> {
+    Vec::new()
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_sockstat() {
+        let content = "\
+sockets: used 21
+TCP: inuse 5 orphan 1 tw 7 alloc 6 mem 2
+UDP: inuse 3 mem 1
+UDPLITE: inuse 0
+RAW: inuse 0
+FRAG: inuse 0 memory 0
+";
+        let content6 = "TCP6: inuse 2\nUDP6: inuse 1\n";
+        let counts = parse_sockstat(content, content6);
+        assert_eq!(counts["sockets:used"], 21);
+        assert_eq!(counts["TCP:inuse"], 5);
+        assert_eq!(counts["TCP:orphan"], 1);
+        assert_eq!(counts["TCP:tw"], 7);
+        assert_eq!(counts["TCP:mem"], 2);
+        assert_eq!(counts["UDP:inuse"], 3);
+        assert_eq!(counts["TCP6:inuse"], 2);
+        assert_eq!(counts["UDP6:inuse"], 1);
+    }
+
+    #[test]
+    fn parses_protocol_counters() {
+        let snmp = "\
+Ip: Forwarding DefaultTTL
+Ip: 1 64
+Tcp: RtoAlgorithm RtoMin RtoMax MaxConn ActiveOpens PassiveOpens AttemptFails EstabResets CurrEstab InSegs OutSegs RetransSegs InErrs OutRsts InCsumErrors
+Tcp: 1 200 120000 -1 100 50 3 4 2 5000 6000 70 8 9 0
+Udp: InDatagrams NoPorts InErrors OutDatagrams RcvbufErrors SndbufErrors InCsumErrors IgnoredMulti MemErrors
+Udp: 10 11 12 13 14 15 0 0 0
+";
+        let netstat = "\
+TcpExt: SyncookiesSent ListenOverflows ListenDrops TCPTimeouts
+TcpExt: 0 21 22 23
+";
+        let snmp6 = "Ip6InReceives 5\nUdp6NoPorts 100\nUdp6InErrors 200\n";
+
+        let counters: HashMap<SocketCounter, u64> = parse_protocol_counters(snmp, netstat, snmp6).into_iter().collect();
+        assert_eq!(counters[&SocketCounter::TcpActiveOpens], 100);
+        assert_eq!(counters[&SocketCounter::TcpPassiveOpens], 50);
+        assert_eq!(counters[&SocketCounter::TcpOutSegs], 6000);
+        assert_eq!(counters[&SocketCounter::TcpRetransSegs], 70);
+        assert_eq!(counters[&SocketCounter::TcpOutRsts], 9);
+        assert_eq!(counters[&SocketCounter::ListenOverflows], 21);
+        assert_eq!(counters[&SocketCounter::ListenDrops], 22);
+        assert_eq!(counters[&SocketCounter::TcpTimeouts], 23);
+        // IPv4 and IPv6 UDP counters are added up
+        assert_eq!(counters[&SocketCounter::UdpNoPorts], 111);
+        assert_eq!(counters[&SocketCounter::UdpInErrors], 212);
+        assert_eq!(counters[&SocketCounter::UdpRcvbufErrors], 14);
+    }
+
+    #[test]
+    fn summed_udp_counters_never_wrap() {
+        // A reset of the IPv4 or IPv6 counter must not look like a wrap of the sum
+        for counter in [
+            SocketCounter::UdpNoPorts,
+            SocketCounter::UdpInErrors,
+            SocketCounter::UdpRcvbufErrors,
+            SocketCounter::UdpSndbufErrors,
+        ] {
+            assert_eq!(counter.width(), CounterWidth::Unbounded, "{:?}", counter);
+        }
+        assert_eq!(SocketCounter::TcpOutSegs.width(), CounterWidth::LONG);
+    }
+}
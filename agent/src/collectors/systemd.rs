@@ -0,0 +1,554 @@
+//! systemd unit health collector
+//!
+//! Reads unit states from systemd's D-Bus API on the system bus and reports
+//! the units selected in the `[systemd]` config section with their active
+//! state, sub-state, restart count and last state change. Reading unit
+//! states needs no privileges. The bus is behind the `SystemdBus` trait, so
+//! the collector can run against a stand-in.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use tracing::debug;
+
+use super::filter::NameFilter;
+use super::Availability;
+use crate::config::SystemdConfig;
+use crate::models::{ServiceInfo, ServiceStatus, ServiceType, SystemdUnitMetrics};
+
+/// A unit as listed by systemd's manager
+#[derive(Debug, Clone)]
+pub struct ListedUnit {
+    pub name: String,
+    pub description: String,
+    /// loaded, not-found, masked, ...
+    pub load_state: String,
+    /// active, reloading, inactive, failed, activating or deactivating
+    pub active_state: String,
+    /// Unit type specific state, e.g. running, exited, dead or auto-restart
+    pub sub_state: String,
+    /// D-Bus object of the unit
+    pub object_path: String,
+}
+
+/// Details that systemd only exposes as unit properties
+#[derive(Debug, Clone, Default)]
+pub struct UnitDetails {
+    /// Automatic restarts since the unit was last started manually (services only, systemd 235+)
+    pub restarts: Option<u32>,
+    /// When the unit last changed its active state
+    pub state_changed_at: Option<DateTime<Utc>>,
+}
+
+/// Read access to systemd's manager
+pub trait SystemdBus {
+    /// Every unit systemd currently has loaded
+    fn list_units(&self) -> Result<Vec<ListedUnit>>;
+    /// The named units, loaded from disk if needed, so units that are stopped
+    /// and not referenced by anything are found too (systemd 227+)
+    ///
+    /// Units without a unit file come back with the `not-found` load state.
+    fn list_units_by_names(&self, names: &[String]) -> Result<Vec<ListedUnit>>;
+    /// Restart count and last state change of a unit
+    fn unit_details(&self, unit: &ListedUnit) -> Result<UnitDetails>;
+}
+
+/// Selects which units are reported
+#[derive(Debug, Clone)]
+pub struct UnitFilter {
+    units: NameFilter,
+    /// Include entries without glob characters, reported even when systemd doesn't know them
+    exact_names: Vec<String>,
+    /// Whether any include entry is a glob, which needs the full unit list
+    has_patterns: bool,
+}
+
+impl UnitFilter {
+    /// Build the filter from the `[systemd]` config section
+    pub fn from_config(config: &SystemdConfig) -> Result<Self> {
+        let is_pattern = |name: &String| name.contains(['*', '?', '[']);
+        Ok(Self {
+            units: NameFilter::new(&config.include, &config.exclude)?,
+            exact_names: config
+                .include
+                .iter()
+                .filter(|name| !is_pattern(name))
+                .cloned()
+                .collect(),
+            has_patterns: config.include.iter().any(is_pattern),
+        })
+    }
+}
+
+/// Report the selected units, sorted by name
+///
+/// Units named explicitly in the config are looked up by name, so they're
+/// reported even when stopped and unloaded; the ones systemd doesn't know
+/// are reported as `not-found`, so a unit that was removed or never
+/// installed shows up instead of silently disappearing.
+pub fn collect(bus: &dyn SystemdBus, filter: &UnitFilter) -> Result<Vec<SystemdUnitMetrics>> {
+    let mut listed: Vec<ListedUnit> = if filter.has_patterns {
+        bus.list_units()?
+            .into_iter()
+            .filter(|unit| filter.units.matches(&unit.name))
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    let missing: Vec<String> = filter
+        .exact_names
+        .iter()
+        .filter(|name| filter.units.matches(name) && !listed.iter().any(|unit| &unit.name == *name))
+        .cloned()
+        .collect();
+    if !missing.is_empty() {
+        match bus.list_units_by_names(&missing) {
+            Ok(units) => listed.extend(units.into_iter().filter(|unit| missing.contains(&unit.name))),
+            // Older systemd; units it hasn't loaded are reported as not-found below
+            Err(e) => debug!("Failed to look up systemd units by name: {:#}", e),
+        }
+    }
+
+    let mut units: Vec<SystemdUnitMetrics> = listed
+        .into_iter()
+        .map(|unit| {
+            // A unit unloaded since the listing has no details
+            let details = if unit.load_state == "not-found" {
+                UnitDetails::default()
+            } else {
+                bus.unit_details(&unit).unwrap_or_default()
+            };
+            SystemdUnitMetrics {
+                name: unit.name,
+                description: unit.description,
+                load_state: unit.load_state,
+                active_state: unit.active_state,
+                sub_state: unit.sub_state,
+                restarts: details.restarts,
+                state_changed_at: details.state_changed_at,
+            }
+        })
+        .collect();
+
+    for name in missing {
+        if !units.iter().any(|unit| unit.name == name) {
+            units.push(SystemdUnitMetrics {
+                name,
+                description: String::new(),
+                load_state: "not-found".to_string(),
+                active_state: "inactive".to_string(),
+                sub_state: "dead".to_string(),
+                restarts: None,
+                state_changed_at: None,
+            });
+        }
+    }
+
+    units.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(units)
+}
+
+/// List the selected units as `process` services for the server's service inventory
+pub fn discover(bus: &dyn SystemdBus, filter: &UnitFilter) -> Result<Vec<ServiceInfo>> {
+    Ok(collect(bus, filter)?
+        .into_iter()
+        .map(|unit| ServiceInfo {
+            service_type: ServiceType::Process,
+            status: service_status(&unit),
+            name: unit.name,
+            port: None,
+        })
+        .collect())
+}
+
+/// Map a unit's state to a service status
+///
+/// A unit waiting to be restarted is in a restart loop as far as the
+/// dashboard is concerned.
+fn service_status(unit: &SystemdUnitMetrics) -> ServiceStatus {
+    if unit.load_state == "not-found" || unit.sub_state == "auto-restart" {
+        return ServiceStatus::Error;
+    }
+    match unit.active_state.as_str() {
+        "active" | "reloading" | "activating" | "deactivating" => ServiceStatus::Running,
+        "failed" => ServiceStatus::Error,
+        _ => ServiceStatus::Stopped,
+    }
+}
+
+/// State carried between collections
+pub struct SystemdState {
+    filter: UnitFilter,
+    /// Connection to the system bus, re-established after failures
+    bus: Option<Box<dyn SystemdBus + Send>>,
+    /// Whether systemd could be read last time, so only changes are logged
+    availability: Availability,
+}
+
+impl SystemdState {
+    pub fn new(filter: UnitFilter) -> Self {
+        Self {
+            filter,
+            bus: None,
+            availability: Availability::default(),
+        }
+    }
+}
+
+/// Collect the selected units, connecting to the system bus when needed
+///
+/// When systemd can't be read nothing is reported; a warning is logged
+/// once until it's back.
+pub fn collect_units(state: &mut SystemdState) -> Vec<SystemdUnitMetrics> {
+    let result = match state.bus.take() {
+        Some(bus) => Ok(bus),
+        None => SystemBus::connect().map(|bus| Box::new(bus) as Box<dyn SystemdBus + Send>),
+    }
+    .and_then(|bus| {
+        let units = collect(bus.as_ref(), &state.filter)?;
+        state.bus = Some(bus);
+        Ok(units)
+    });
+    state.availability.update("systemd", result).unwrap_or_default()
+}
+
+/// systemd's manager on the system D-Bus (Linux only)
+pub struct SystemBus {
+    #[cfg(target_os = "linux")]
+    connection: zbus::blocking::Connection,
+}
+
+#[cfg(target_os = "linux")]
+impl SystemBus {
+    const DESTINATION: &'static str = "org.freedesktop.systemd1";
+    const MANAGER_PATH: &'static str = "/org/freedesktop/systemd1";
+    const MANAGER_INTERFACE: &'static str = "org.freedesktop.systemd1.Manager";
+
+    /// Connect to the system bus
+    pub fn connect() -> Result<Self> {
+        Ok(Self {
+            connection: zbus::blocking::Connection::system()?,
+        })
+    }
+
+    /// Call a manager method that returns units in the `ListUnits` format
+    fn call_list<B>(&self, method: &str, body: &B) -> Result<Vec<ListedUnit>>
+    where
+        B: serde::Serialize + zbus::zvariant::DynamicType,
+    {
+        use zbus::zvariant::OwnedObjectPath;
+
+        // (name, description, load state, active state, sub state, followed unit,
+        //  unit path, job id, job type, job path)
+        type Unit = (
+            String,
+            String,
+            String,
+            String,
+            String,
+            String,
+            OwnedObjectPath,
+            u32,
+            String,
+            OwnedObjectPath,
+        );
+
+        let reply = self.connection.call_method(
+            Some(Self::DESTINATION),
+            Self::MANAGER_PATH,
+            Some(Self::MANAGER_INTERFACE),
+            method,
+            body,
+        )?;
+        let units: Vec<Unit> = reply.body().deserialize()?;
+        Ok(units
+            .into_iter()
+            .map(|(name, description, load_state, active_state, sub_state, _, path, _, _, _)| ListedUnit {
+                name,
+                description,
+                load_state,
+                active_state,
+                sub_state,
+                object_path: path.to_string(),
+            })
+            .collect())
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl SystemdBus for SystemBus {
+    fn list_units(&self) -> Result<Vec<ListedUnit>> {
+        self.call_list("ListUnits", &())
+    }
+
+    fn list_units_by_names(&self, names: &[String]) -> Result<Vec<ListedUnit>> {
+        self.call_list("ListUnitsByNames", &(names,))
+    }
+
+    fn unit_details(&self, unit: &ListedUnit) -> Result<UnitDetails> {
+        use anyhow::Context;
+        use std::collections::HashMap;
+        use zbus::zvariant::OwnedValue;
+
+        // An empty interface name returns the properties of every interface
+        // of the unit, so both come back in one call
+        let reply = self.connection.call_method(
+            Some(Self::DESTINATION),
+            unit.object_path.as_str(),
+            Some("org.freedesktop.DBus.Properties"),
+            "GetAll",
+            &("",),
+        )?;
+        let mut properties: HashMap<String, OwnedValue> = reply.body().deserialize()?;
+
+        // Services only, and not before systemd 235
+        let restarts = properties
+            .remove("NRestarts")
+            .and_then(|value| u32::try_from(value).ok());
+        // Microseconds since the epoch, 0 if the state never changed
+        let changed_us = u64::try_from(
+            properties
+                .remove("StateChangeTimestamp")
+                .context("Unit has no StateChangeTimestamp")?,
+        )?;
+        let state_changed_at = (changed_us > 0)
+            .then(|| DateTime::from_timestamp_micros(changed_us as i64))
+            .flatten();
+
+        Ok(UnitDetails {
+            restarts,
+            state_changed_at,
+        })
+    }
+}
+
+/// Fallback for non-Linux systems
+#[cfg(not(target_os = "linux"))]
+impl SystemBus {
+    pub fn connect() -> Result<Self> {
+        anyhow::bail!("systemd monitoring is only supported on Linux")
+    }
+}
+
+/// Fallback for non-Linux systems
+#[cfg(not(target_os = "linux"))]
+impl SystemdBus for SystemBus {
+    fn list_units(&self) -> Result<Vec<ListedUnit>> {
+        Ok(Vec::new())
+    }
+
+    fn list_units_by_names(&self, _names: &[String]) -> Result<Vec<ListedUnit>> {
+        Ok(Vec::new())
+    }
+
+    fn unit_details(&self, _unit: &ListedUnit) -> Result<UnitDetails> {
+        Ok(UnitDetails::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    fn unit(name: &str, load_state: &str, active_state: &str, sub_state: &str) -> ListedUnit {
+        ListedUnit {
+            name: name.to_string(),
+            description: format!("{} description", name),
+            load_state: load_state.to_string(),
+            active_state: active_state.to_string(),
+            sub_state: sub_state.to_string(),
+            object_path: format!("/org/freedesktop/systemd1/unit/{}", name.replace(['.', '-'], "_")),
+        }
+    }
+
+    /// A systemd with some loaded units and some that are only on disk
+    #[derive(Default)]
+    struct FakeBus {
+        loaded: Vec<ListedUnit>,
+        /// Units ListUnitsByNames can load
+        on_disk: Vec<ListedUnit>,
+        list_fails: bool,
+        /// Like systemd before 227
+        by_names_fails: bool,
+        /// Units that are unloaded before their details are read
+        details_fail: Vec<String>,
+        list_calls: Cell<u32>,
+    }
+
+    impl SystemdBus for FakeBus {
+        fn list_units(&self) -> Result<Vec<ListedUnit>> {
+            self.list_calls.set(self.list_calls.get() + 1);
+            if self.list_fails {
+                anyhow::bail!("Connection reset by peer");
+            }
+            Ok(self.loaded.clone())
+        }
+
+        fn list_units_by_names(&self, names: &[String]) -> Result<Vec<ListedUnit>> {
+            if self.by_names_fails {
+                anyhow::bail!("Unknown method ListUnitsByNames");
+            }
+            Ok(names
+                .iter()
+                .map(|name| {
+                    self.loaded
+                        .iter()
+                        .chain(&self.on_disk)
+                        .find(|unit| &unit.name == name)
+                        .cloned()
+                        .unwrap_or_else(|| unit(name, "not-found", "inactive", "dead"))
+                })
+                .collect())
+        }
+
+        fn unit_details(&self, unit: &ListedUnit) -> Result<UnitDetails> {
+            if self.details_fail.contains(&unit.name) {
+                anyhow::bail!("Unit {} not loaded", unit.name);
+            }
+            Ok(UnitDetails {
+                restarts: unit.name.ends_with(".service").then_some(3),
+                state_changed_at: DateTime::from_timestamp(1_700_000_000, 0),
+            })
+        }
+    }
+
+    fn fake_bus() -> FakeBus {
+        FakeBus {
+            loaded: vec![
+                unit("nginx.service", "loaded", "active", "running"),
+                unit("myapp-api.service", "loaded", "active", "running"),
+                unit("myapp-worker.service", "loaded", "activating", "auto-restart"),
+                unit("myapp-tmp.mount", "loaded", "active", "mounted"),
+                unit("ssh.service", "loaded", "active", "running"),
+            ],
+            on_disk: vec![unit("backup.service", "loaded", "inactive", "dead")],
+            ..FakeBus::default()
+        }
+    }
+
+    fn filter(include: &[&str], exclude: &[&str]) -> UnitFilter {
+        UnitFilter::from_config(&SystemdConfig {
+            include: include.iter().map(|name| name.to_string()).collect(),
+            exclude: exclude.iter().map(|name| name.to_string()).collect(),
+        })
+        .unwrap()
+    }
+
+    fn names(units: &[SystemdUnitMetrics]) -> Vec<&str> {
+        units.iter().map(|unit| unit.name.as_str()).collect()
+    }
+
+    #[test]
+    fn reports_matching_units() {
+        let bus = fake_bus();
+        let units = collect(&bus, &filter(&["nginx.service", "myapp-*.service"], &["*-worker.service"])).unwrap();
+        assert_eq!(names(&units), ["myapp-api.service", "nginx.service"]);
+
+        let nginx = &units[1];
+        assert_eq!(nginx.description, "nginx.service description");
+        assert_eq!(nginx.load_state, "loaded");
+        assert_eq!(nginx.active_state, "active");
+        assert_eq!(nginx.sub_state, "running");
+        assert_eq!(nginx.restarts, Some(3));
+        assert_eq!(nginx.state_changed_at, DateTime::from_timestamp(1_700_000_000, 0));
+    }
+
+    #[test]
+    fn looks_up_named_units() {
+        let bus = fake_bus();
+        let units = collect(&bus, &filter(&["nginx.service", "backup.service", "gone.service"], &[])).unwrap();
+        // Exact names alone don't need the full unit list
+        assert_eq!(bus.list_calls.get(), 0);
+        assert_eq!(names(&units), ["backup.service", "gone.service", "nginx.service"]);
+
+        // Stopped but installed
+        let backup = &units[0];
+        assert_eq!(backup.load_state, "loaded");
+        assert_eq!(backup.active_state, "inactive");
+        assert_eq!(backup.restarts, Some(3));
+
+        let gone = &units[1];
+        assert_eq!(gone.load_state, "not-found");
+        assert_eq!(gone.sub_state, "dead");
+        assert_eq!(gone.restarts, None);
+        assert_eq!(gone.state_changed_at, None);
+    }
+
+    #[test]
+    fn falls_back_to_not_found_without_lookup_by_name() {
+        let bus = FakeBus {
+            by_names_fails: true,
+            ..fake_bus()
+        };
+        let units = collect(&bus, &filter(&["nginx*", "backup.service", "ssh.service"], &["ssh.service"])).unwrap();
+        assert_eq!(names(&units), ["backup.service", "nginx.service"]);
+        assert_eq!(units[0].load_state, "not-found");
+        assert_eq!(units[0].description, "");
+    }
+
+    #[test]
+    fn reports_units_without_details() {
+        let bus = FakeBus {
+            details_fail: vec!["nginx.service".to_string()],
+            ..fake_bus()
+        };
+        let units = collect(&bus, &filter(&["nginx.service", "ssh.service"], &[])).unwrap();
+        assert_eq!(names(&units), ["nginx.service", "ssh.service"]);
+        assert_eq!(units[0].active_state, "active");
+        assert_eq!(units[0].restarts, None);
+        assert_eq!(units[0].state_changed_at, None);
+        assert_eq!(units[1].restarts, Some(3));
+    }
+
+    #[test]
+    fn reports_nothing_when_systemd_fails() {
+        let bus = FakeBus {
+            list_fails: true,
+            ..fake_bus()
+        };
+        assert!(collect(&bus, &filter(&["*.service"], &[])).is_err());
+
+        let mut state = SystemdState::new(filter(&["*.service"], &[]));
+        state.bus = Some(Box::new(bus));
+        assert!(collect_units(&mut state).is_empty());
+        // Reconnected on the next collection
+        assert!(state.bus.is_none());
+    }
+
+    #[test]
+    fn maps_unit_states_to_service_status() {
+        let bus = fake_bus();
+        let services = discover(&bus, &filter(&["myapp-*.service", "backup.service", "gone.service"], &[])).unwrap();
+        let statuses: Vec<(&str, ServiceStatus)> = services
+            .iter()
+            .map(|service| (service.name.as_str(), service.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            [
+                ("backup.service", ServiceStatus::Stopped),
+                ("gone.service", ServiceStatus::Error),
+                ("myapp-api.service", ServiceStatus::Running),
+                ("myapp-worker.service", ServiceStatus::Error),
+            ]
+        );
+
+        let status = |active_state: &str, sub_state: &str| {
+            let unit = unit("app.service", "loaded", active_state, sub_state);
+            service_status(&SystemdUnitMetrics {
+                name: unit.name,
+                description: unit.description,
+                load_state: unit.load_state,
+                active_state: unit.active_state,
+                sub_state: unit.sub_state,
+                restarts: None,
+                state_changed_at: None,
+            })
+        };
+        assert_eq!(status("failed", "failed"), ServiceStatus::Error);
+        assert_eq!(status("inactive", "dead"), ServiceStatus::Stopped);
+        assert_eq!(status("reloading", "reload"), ServiceStatus::Running);
+        assert_eq!(status("deactivating", "stop-sigterm"), ServiceStatus::Running);
+    }
+}
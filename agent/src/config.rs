@@ -1,8 +1,11 @@
 //! Configuration loading and management
 
 use anyhow::{Context, Result};
-use serde::{Deserialize, Serialize};
-use std::path::Path;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+
+use crate::checks::process::ProcessChecks;
 
 /// Main configuration structure matching agent.toml
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -11,6 +14,40 @@ pub struct Config {
     pub agent: AgentConfig,
     #[serde(default)]
     pub features: FeaturesConfig,
+    /// Local spool for metrics that could not be delivered
+    #[serde(default)]
+    pub spool: SpoolConfig,
+    /// Filesystem usage collection settings
+    #[serde(default)]
+    pub disk: DiskConfig,
+    /// Network interface collection settings
+    #[serde(default)]
+    pub network: NetworkConfig,
+    /// cgroup collection settings (requires `features.collect_cgroups`)
+    #[serde(default)]
+    pub cgroups: CgroupConfig,
+    /// Docker monitoring settings (requires `features.collect_docker`)
+    #[serde(default)]
+    pub docker: DockerConfig,
+    /// systemd unit monitoring settings (requires `features.monitor_systemd`)
+    #[serde(default)]
+    pub systemd: SystemdConfig,
+    /// Process collection settings (requires `features.collect_processes`)
+    #[serde(default)]
+    pub processes: ProcessConfig,
+    /// Service discovery settings (requires `features.discover_services`)
+    #[serde(default)]
+    pub services: ServicesConfig,
+    /// Agent-side health checks (requires `features.health_checks`)
+    #[serde(
+        default,
+        skip_serializing_if = "Vec::is_empty",
+        deserialize_with = "deserialize_health_checks"
+    )]
+    pub health_checks: Vec<HealthCheckConfig>,
+    /// Process presence and count checks (requires `features.process_checks`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub process_checks: Vec<ProcessCheckConfig>,
 }
 
 /// Server connection configuration
@@ -41,9 +78,471 @@ pub struct FeaturesConfig {
     /// Whether to collect process information
     #[serde(default)]
     pub collect_processes: bool,
-    /// Whether to perform HTTP health checks
+    /// Whether to perform the agent-side `[[health_checks]]` (HTTP, TCP, DNS and ping)
+    #[serde(default, alias = "http_health_checks")]
+    pub health_checks: bool,
+    /// Whether to run the `[[process_checks]]` on every metrics collection
+    #[serde(default)]
+    pub process_checks: bool,
+    /// Whether to report resource usage of individual cgroups (cgroup v2 only)
+    #[serde(default)]
+    pub collect_cgroups: bool,
+    /// Whether to report Docker containers and container events
+    #[serde(default)]
+    pub collect_docker: bool,
+    /// Whether to report the state of systemd units
+    #[serde(default)]
+    pub monitor_systemd: bool,
+    /// Whether to report listening services to the server's service inventory
+    #[serde(default)]
+    pub discover_services: bool,
+    /// Whether to report usage and frequency of every CPU core
+    #[serde(default)]
+    pub per_core_cpu: bool,
+}
+
+/// On-disk spool for metrics that failed to submit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpoolConfig {
+    /// Whether to keep undelivered metrics on disk and replay them later
+    #[serde(default = "default_spool_enabled")]
+    pub enabled: bool,
+    /// Directory holding spooled payloads
+    #[serde(default = "default_spool_directory")]
+    pub directory: PathBuf,
+    /// Maximum total size of the spool (in megabytes); oldest payloads are dropped first
+    #[serde(default = "default_spool_max_size")]
+    pub max_size_mb: u64,
+    /// Maximum age of a spooled payload (in hours)
+    #[serde(default = "default_spool_max_age")]
+    pub max_age_hours: u64,
+}
+
+impl Default for SpoolConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_spool_enabled(),
+            directory: default_spool_directory(),
+            max_size_mb: default_spool_max_size(),
+            max_age_hours: default_spool_max_age(),
+        }
+    }
+}
+
+fn default_spool_enabled() -> bool {
+    true
+}
+
+fn default_spool_directory() -> PathBuf {
+    if cfg!(target_os = "windows") {
+        PathBuf::from("C:\\ProgramData\\nubilus\\spool")
+    } else {
+        PathBuf::from("/var/lib/nubilus/spool")
+    }
+}
+
+fn default_spool_max_size() -> u64 {
+    50
+}
+
+fn default_spool_max_age() -> u64 {
+    24
+}
+
+/// Filesystem usage configuration
+///
+/// All lists are glob patterns (e.g., "/mnt/*", "loop*"). Empty include lists
+/// mean "everything".
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiskConfig {
+    /// Only report these mountpoints
+    #[serde(default)]
+    pub include_mountpoints: Vec<String>,
+    /// Never report these mountpoints
+    #[serde(default)]
+    pub exclude_mountpoints: Vec<String>,
+    /// Only report these filesystem types
+    #[serde(default)]
+    pub include_fs_types: Vec<String>,
+    /// Never report these filesystem types
+    #[serde(default = "default_exclude_fs_types")]
+    pub exclude_fs_types: Vec<String>,
+    /// Only report I/O for these block devices (names from /sys/block)
+    #[serde(default)]
+    pub include_devices: Vec<String>,
+    /// Never report I/O for these block devices
+    #[serde(default = "default_exclude_devices")]
+    pub exclude_devices: Vec<String>,
+}
+
+impl Default for DiskConfig {
+    fn default() -> Self {
+        Self {
+            include_mountpoints: Vec::new(),
+            exclude_mountpoints: Vec::new(),
+            include_fs_types: Vec::new(),
+            exclude_fs_types: default_exclude_fs_types(),
+            include_devices: Vec::new(),
+            exclude_devices: default_exclude_devices(),
+        }
+    }
+}
+
+fn default_exclude_fs_types() -> Vec<String> {
+    ["tmpfs", "devtmpfs", "overlay", "squashfs"]
+        .iter()
+        .map(|fs_type| fs_type.to_string())
+        .collect()
+}
+
+fn default_exclude_devices() -> Vec<String> {
+    ["loop*", "ram*", "zram*"]
+        .iter()
+        .map(|device| device.to_string())
+        .collect()
+}
+
+/// Network interface configuration
+///
+/// Interface lists are glob patterns (e.g., "eth*"). An empty include list
+/// means "every interface".
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkConfig {
+    /// Only report these interfaces
+    #[serde(default)]
+    pub include_interfaces: Vec<String>,
+    /// Never report these interfaces
+    #[serde(default = "default_exclude_interfaces")]
+    pub exclude_interfaces: Vec<String>,
+    /// Whether to report virtual interfaces (loopback, bridges, veths, tunnels...)
+    #[serde(default)]
+    pub include_virtual: bool,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            include_interfaces: Vec::new(),
+            exclude_interfaces: default_exclude_interfaces(),
+            include_virtual: false,
+        }
+    }
+}
+
+fn default_exclude_interfaces() -> Vec<String> {
+    vec!["lo".to_string()]
+}
+
+/// cgroup collection configuration
+///
+/// Lists are glob patterns matched against cgroup paths relative to
+/// /sys/fs/cgroup, such as "/system.slice/nginx.service". `*` matches
+/// within one path level, so "/docker/*" doesn't also select the child
+/// cgroups of each container; `**` matches any number of levels.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CgroupConfig {
+    /// Only report these cgroups
+    #[serde(default = "default_cgroup_include")]
+    pub include: Vec<String>,
+    /// Never report these cgroups
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Upper bound on the number of cgroups in one payload; the ones that
+    /// used the most CPU time are kept
+    #[serde(default = "default_max_cgroups")]
+    pub max_cgroups: usize,
+}
+
+impl Default for CgroupConfig {
+    fn default() -> Self {
+        Self {
+            include: default_cgroup_include(),
+            exclude: Vec::new(),
+            max_cgroups: default_max_cgroups(),
+        }
+    }
+}
+
+fn default_cgroup_include() -> Vec<String> {
+    // systemd services, plus Docker containers under the cgroupfs and systemd drivers
+    ["/system.slice/*.service", "/docker/*", "/system.slice/docker-*.scope"]
+        .iter()
+        .map(|pattern| pattern.to_string())
+        .collect()
+}
+
+fn default_max_cgroups() -> usize {
+    50
+}
+
+/// Docker monitoring configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DockerConfig {
+    /// Unix socket of the Docker Engine API
+    #[serde(default = "default_docker_socket")]
+    pub socket_path: PathBuf,
+    /// How long one collection may spend waiting for the daemon (in seconds)
+    #[serde(default = "default_docker_timeout")]
+    pub timeout_seconds: u64,
+}
+
+impl Default for DockerConfig {
+    fn default() -> Self {
+        Self {
+            socket_path: default_docker_socket(),
+            timeout_seconds: default_docker_timeout(),
+        }
+    }
+}
+
+fn default_docker_socket() -> PathBuf {
+    PathBuf::from("/var/run/docker.sock")
+}
+
+fn default_docker_timeout() -> u64 {
+    5
+}
+
+/// systemd unit monitoring configuration
+///
+/// Lists are glob patterns matched against unit names such as "nginx.service".
+/// There is no default selection; reporting every loaded service would mostly
+/// report systemd's own oneshot units.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SystemdConfig {
+    /// Only report these units; required when systemd monitoring is enabled
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Never report these units
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+/// Process collection configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessConfig {
+    /// How many processes to report for each ranking
+    #[serde(default = "default_process_top_n")]
+    pub top_n: usize,
+    /// Which ranking(s) to report processes from
+    #[serde(default)]
+    pub sort_by: ProcessSort,
+}
+
+/// Ranking used to pick the top processes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ProcessSort {
+    /// Top N by CPU usage
+    Cpu,
+    /// Top N by resident memory
+    Memory,
+    /// Top N by CPU plus top N by memory
+    #[default]
+    Both,
+}
+
+impl Default for ProcessConfig {
+    fn default() -> Self {
+        Self {
+            top_n: default_process_top_n(),
+            sort_by: ProcessSort::default(),
+        }
+    }
+}
+
+fn default_process_top_n() -> usize {
+    5
+}
+
+/// Service discovery configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServicesConfig {
+    /// How often to report the service inventory (in seconds)
+    #[serde(default = "default_services_interval")]
+    pub interval_seconds: u64,
+}
+
+impl Default for ServicesConfig {
+    fn default() -> Self {
+        Self {
+            interval_seconds: default_services_interval(),
+        }
+    }
+}
+
+fn default_services_interval() -> u64 {
+    300
+}
+
+/// A single agent-side health check
+///
+/// `kind` selects what is checked and which other fields apply. Checks
+/// without a `kind` are HTTP checks, as they were before the other kinds.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthCheckConfig {
+    /// ID of the endpoint in the Nubilus dashboard that results are reported against
+    pub endpoint_id: String,
+    /// What to check, with the settings of that kind of check
+    #[serde(flatten)]
+    pub kind: CheckKind,
+    /// Request timeout (in seconds)
+    #[serde(default = "default_check_timeout")]
+    pub timeout_seconds: u64,
+    /// How often to run the check (in seconds)
+    #[serde(default = "default_check_interval")]
+    pub interval_seconds: u64,
+}
+
+/// Kind of agent-side health check
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum CheckKind {
+    /// Request a URL and compare the status code
+    Http(HttpCheckConfig),
+    /// Open a TCP connection, optionally exchanging data
+    Tcp(TcpCheckConfig),
+    /// Look up a DNS record and compare the answers
+    Dns(DnsCheckConfig),
+    /// Send ICMP echo requests and measure round trip times
+    Ping(PingCheckConfig),
+}
+
+/// Settings of an HTTP check
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpCheckConfig {
+    /// URL to request
+    pub url: String,
+    /// HTTP method to use (e.g., "GET", "HEAD")
+    #[serde(default = "default_check_method")]
+    pub method: String,
+    /// Status code that marks the endpoint as up
+    #[serde(default = "default_expected_status")]
+    pub expected_status: u16,
+}
+
+/// Settings of a TCP check
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TcpCheckConfig {
+    /// Host name or IP address to connect to
+    pub host: String,
+    /// Port to connect to
+    pub port: u16,
+    /// Data to send once connected
+    pub send: Option<String>,
+    /// Text the response must contain, e.g. an SMTP banner
+    pub expect: Option<String>,
+}
+
+/// Settings of a DNS check
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DnsCheckConfig {
+    /// Name to look up
+    pub record: String,
+    /// Record type to look up
+    #[serde(default)]
+    pub record_type: DnsRecordType,
+    /// Resolver to query as "ip" or "ip:port", the first nameserver in /etc/resolv.conf if unset
+    pub resolver: Option<String>,
+    /// Answers the lookup must return, in any order; any answer is accepted if empty
     #[serde(default)]
-    pub http_health_checks: bool,
+    pub expected: Vec<String>,
+}
+
+/// Settings of a ping check
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PingCheckConfig {
+    /// Host name or IP address to ping
+    pub host: String,
+    /// Echo requests to send, one per second
+    #[serde(default = "default_ping_count")]
+    pub count: u32,
+    /// Highest packet loss (in percent) before the host counts as down; only total loss if unset
+    pub max_packet_loss: Option<f64>,
+}
+
+/// Deserialize the `[[health_checks]]`, treating entries without a `kind` as HTTP checks
+fn deserialize_health_checks<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<HealthCheckConfig>, D::Error> {
+    Vec::<toml::Table>::deserialize(deserializer)?
+        .into_iter()
+        .map(|mut table| {
+            table.entry("kind").or_insert_with(|| "http".into());
+            toml::Value::Table(table).try_into().map_err(serde::de::Error::custom)
+        })
+        .collect()
+}
+
+/// A check that the expected number of matching processes is running
+///
+/// Every matcher that is set must match; at least one is required.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessCheckConfig {
+    /// Name of the check, shown in the dashboard
+    pub name: String,
+    /// Process name (glob pattern), e.g. "gunicorn" or "php-fpm*"
+    pub process_name: Option<String>,
+    /// Regular expression matched against the full command line
+    pub cmdline: Option<String>,
+    /// User the process runs as (name or numeric UID)
+    pub user: Option<String>,
+    /// File holding the PID of the process
+    pub pidfile: Option<PathBuf>,
+    /// Fewest matching processes for the check to pass
+    #[serde(default = "default_process_min_count")]
+    pub min_count: usize,
+    /// Most matching processes for the check to pass, unlimited if unset
+    pub max_count: Option<usize>,
+    /// Also report the result as a health check of this dashboard endpoint
+    pub endpoint_id: Option<String>,
+}
+
+fn default_process_min_count() -> usize {
+    1
+}
+
+/// DNS record type a DNS check looks up
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum DnsRecordType {
+    #[default]
+    A,
+    Aaaa,
+    Cname,
+    Mx,
+    Txt,
+}
+
+impl std::fmt::Display for DnsRecordType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::A => "A",
+            Self::Aaaa => "AAAA",
+            Self::Cname => "CNAME",
+            Self::Mx => "MX",
+            Self::Txt => "TXT",
+        };
+        f.write_str(name)
+    }
+}
+
+fn default_check_method() -> String {
+    "GET".to_string()
+}
+
+fn default_expected_status() -> u16 {
+    200
+}
+
+fn default_check_timeout() -> u64 {
+    10
+}
+
+fn default_check_interval() -> u64 {
+    60
+}
+
+fn default_ping_count() -> u32 {
+    5
 }
 
 fn default_metrics_interval() -> u64 {
@@ -88,6 +587,37 @@ impl Config {
         if self.agent.heartbeat_interval_seconds < 10 {
             anyhow::bail!("agent.heartbeat_interval_seconds must be at least 10");
         }
+        if self.spool.enabled && self.spool.max_size_mb == 0 {
+            anyhow::bail!("spool.max_size_mb must be at least 1");
+        }
+        if self.spool.enabled && self.spool.max_age_hours == 0 {
+            anyhow::bail!("spool.max_age_hours must be at least 1");
+        }
+        if self.cgroups.max_cgroups == 0 || self.cgroups.max_cgroups > 500 {
+            anyhow::bail!("cgroups.max_cgroups must be between 1 and 500");
+        }
+        if self.docker.timeout_seconds == 0 || self.docker.timeout_seconds > 30 {
+            anyhow::bail!("docker.timeout_seconds must be between 1 and 30");
+        }
+        if self.processes.top_n == 0 || self.processes.top_n > 50 {
+            anyhow::bail!("processes.top_n must be between 1 and 50");
+        }
+        if self.features.monitor_systemd && self.systemd.include.is_empty() {
+            anyhow::bail!("systemd.include must list the units to monitor");
+        }
+        let reports_services =
+            self.features.discover_services || self.features.collect_docker || self.features.monitor_systemd;
+        if reports_services && self.services.interval_seconds < 60 {
+            anyhow::bail!("services.interval_seconds must be at least 60");
+        }
+        if self.features.health_checks {
+            for check in &self.health_checks {
+                check.validate()?;
+            }
+        }
+        if self.features.process_checks {
+            ProcessChecks::from_config(&self.process_checks)?;
+        }
         Ok(())
     }
 
@@ -115,12 +645,230 @@ heartbeat_interval_seconds = 30
 [features]
 # Include top process information in metrics
 collect_processes = true
-# Enable agent-side HTTP endpoint health checks
-http_health_checks = false
+# Run the agent-side [[health_checks]] below (HTTP, TCP, DNS and ping)
+health_checks = false
+# Report usage and frequency of every CPU core (the busiest core is always reported)
+per_core_cpu = false
+# Report CPU, memory, I/O, task counts and OOM kills per cgroup (cgroup v2 hosts only)
+collect_cgroups = false
+# Report Docker containers, their resource usage and start/stop/die events
+collect_docker = false
+# Run the [[process_checks]] below with every metrics collection
+process_checks = false
+# Report the state and restart count of systemd units
+monitor_systemd = false
+# Report listening services (postgres, redis, nginx, ...) to the dashboard
+discover_services = false
+
+[spool]
+# Keep metrics on disk while the backend is unreachable and replay them later
+enabled = true
+directory = "/var/lib/nubilus/spool"
+# Oldest metrics are dropped once either limit is reached
+max_size_mb = 50
+max_age_hours = 24
+
+[disk]
+# Mountpoints to report (glob patterns, empty = all real filesystems)
+include_mountpoints = []
+exclude_mountpoints = []
+# Filesystem types to report (empty = all)
+include_fs_types = []
+# Network filesystems are probed with a timeout and reported as stale if they hang
+exclude_fs_types = ["tmpfs", "devtmpfs", "overlay", "squashfs"]
+# Block devices to report I/O for (names from /sys/block, empty = all)
+include_devices = []
+exclude_devices = ["loop*", "ram*", "zram*"]
+
+[network]
+# Interfaces to report (glob patterns, empty = all)
+include_interfaces = []
+exclude_interfaces = ["lo"]
+# Also report virtual interfaces (bridges, veths, tunnels...)
+include_virtual = false
+
+[cgroups]
+# cgroups to report (glob patterns on paths under /sys/fs/cgroup;
+# "*" stays within one level, "**" matches any depth)
+include = ["/system.slice/*.service", "/docker/*", "/system.slice/docker-*.scope"]
+exclude = []
+# Report at most this many cgroups, keeping the busiest (1-500)
+max_cgroups = 50
+
+[docker]
+# Docker Engine API socket (used when collect_docker = true)
+socket_path = "/var/run/docker.sock"
+# Seconds one collection may spend waiting for the daemon (1-30)
+timeout_seconds = 5
+
+[systemd]
+# Units to report (used when monitor_systemd = true), by name or glob pattern,
+# e.g. ["nginx.service", "myapp-*.service"]
+include = []
+exclude = []
+
+[processes]
+# How many processes to report per ranking (1-50)
+top_n = 5
+# Rank processes by "cpu", "memory" or "both"
+sort_by = "both"
+
+[services]
+# How often to report discovered services, containers and units (in seconds, minimum 60)
+interval_seconds = 300
+
+# Agent-side health checks (one [[health_checks]] block per endpoint, requires health_checks = true).
+# The endpoint_id comes from the endpoint's page in the dashboard.
+# [[health_checks]]
+# endpoint_id = "00000000-0000-0000-0000-000000000000"
+# url = "http://127.0.0.1:8080/health"
+# method = "GET"
+# expected_status = 200
+# timeout_seconds = 10
+# interval_seconds = 60
+#
+# TCP checks connect to host:port, optionally send data and expect a reply.
+# [[health_checks]]
+# endpoint_id = "00000000-0000-0000-0000-000000000000"
+# kind = "tcp"
+# host = "smtp.internal"
+# port = 25
+# expect = "220"
+#
+# DNS checks look up a record and compare the answers with an expected set.
+# [[health_checks]]
+# endpoint_id = "00000000-0000-0000-0000-000000000000"
+# kind = "dns"
+# record = "app.internal"
+# record_type = "A"
+# resolver = "10.0.0.2"
+# expected = ["10.0.0.10"]
+#
+# Ping checks send ICMP echo requests and report round trip times and packet
+# loss (needs the agent's group in the net.ipv4.ping_group_range sysctl).
+# [[health_checks]]
+# endpoint_id = "00000000-0000-0000-0000-000000000000"
+# kind = "ping"
+# host = "10.0.0.1"
+# count = 5
+
+# Process checks (one [[process_checks]] block per check, requires process_checks = true).
+# Every matcher that is set must match: process_name (glob), cmdline (regex),
+# user and pidfile.
+# [[process_checks]]
+# name = "postgres checkpointer"
+# cmdline = "^postgres: .*checkpointer"
+# min_count = 1
+# max_count = 1
 "#, api_url)
     }
 }
 
+impl HealthCheckConfig {
+    /// What the check connects to, for log messages
+    pub fn target(&self) -> String {
+        match &self.kind {
+            CheckKind::Http(http) => http.url.clone(),
+            CheckKind::Tcp(tcp) => format!("{}:{}", tcp.host, tcp.port),
+            CheckKind::Dns(dns) => match &dns.resolver {
+                Some(resolver) => format!("{} {} @{}", dns.record_type, dns.record, resolver),
+                None => format!("{} {}", dns.record_type, dns.record),
+            },
+            CheckKind::Ping(ping) => ping.host.clone(),
+        }
+    }
+
+    /// Validate a single health check definition
+    fn validate(&self) -> Result<()> {
+        if self.endpoint_id.is_empty() {
+            anyhow::bail!("health_checks.endpoint_id cannot be empty");
+        }
+        // The backend rejects results for IDs that aren't UUIDs
+        if uuid::Uuid::parse_str(&self.endpoint_id).is_err() {
+            anyhow::bail!("health_checks.endpoint_id must be the endpoint's UUID ({})", self.endpoint_id);
+        }
+        match &self.kind {
+            CheckKind::Http(http) => {
+                if !http.url.starts_with("http://") && !http.url.starts_with("https://") {
+                    anyhow::bail!("health_checks.url must start with http:// or https:// ({})", http.url);
+                }
+                if reqwest::Method::from_bytes(http.method.to_uppercase().as_bytes()).is_err() {
+                    anyhow::bail!("health_checks.method is not a valid HTTP method ({})", http.method);
+                }
+            }
+            CheckKind::Tcp(tcp) => {
+                if tcp.host.is_empty() {
+                    anyhow::bail!("health_checks.host cannot be empty");
+                }
+                if tcp.port == 0 {
+                    anyhow::bail!("health_checks.port cannot be 0 ({})", tcp.host);
+                }
+                if tcp.expect.as_deref() == Some("") {
+                    anyhow::bail!("health_checks.expect cannot be empty");
+                }
+            }
+            CheckKind::Dns(dns) => {
+                if dns.record.is_empty() {
+                    anyhow::bail!("health_checks.record cannot be empty");
+                }
+                dns.resolver_addr().transpose()?;
+                for answer in &dns.expected {
+                    let valid = match dns.record_type {
+                        DnsRecordType::A => answer.parse::<Ipv4Addr>().is_ok(),
+                        DnsRecordType::Aaaa => answer.parse::<Ipv6Addr>().is_ok(),
+                        _ => !answer.is_empty(),
+                    };
+                    if !valid {
+                        anyhow::bail!(
+                            "health_checks.expected has an invalid {} answer ({:?})",
+                            dns.record_type,
+                            answer
+                        );
+                    }
+                }
+            }
+            CheckKind::Ping(ping) => {
+                if ping.host.is_empty() {
+                    anyhow::bail!("health_checks.host cannot be empty");
+                }
+                if ping.count == 0 || ping.count > 100 {
+                    anyhow::bail!("health_checks.count must be between 1 and 100");
+                }
+                if u64::from(ping.count) > self.timeout_seconds {
+                    anyhow::bail!(
+                        "health_checks.count cannot exceed timeout_seconds, ping checks send one packet per second"
+                    );
+                }
+                if ping.max_packet_loss.is_some_and(|loss| !(0.0..100.0).contains(&loss)) {
+                    anyhow::bail!("health_checks.max_packet_loss must be at least 0 and below 100");
+                }
+            }
+        }
+        if self.timeout_seconds == 0 {
+            anyhow::bail!("health_checks.timeout_seconds must be at least 1");
+        }
+        if self.interval_seconds < 10 {
+            anyhow::bail!("health_checks.interval_seconds must be at least 10");
+        }
+        if self.timeout_seconds > self.interval_seconds {
+            anyhow::bail!("health_checks.timeout_seconds cannot exceed interval_seconds");
+        }
+        Ok(())
+    }
+}
+
+impl DnsCheckConfig {
+    /// Address of the configured resolver, port 53 unless given
+    pub fn resolver_addr(&self) -> Option<Result<SocketAddr>> {
+        self.resolver.as_deref().map(|resolver| {
+            resolver
+                .parse::<SocketAddr>()
+                .or_else(|_| resolver.parse::<IpAddr>().map(|ip| SocketAddr::new(ip, 53)))
+                .map_err(|_| anyhow::anyhow!("health_checks.resolver must be an IP address ({})", resolver))
+        })
+    }
+}
+
 /// Default API URL - reads from API_URL env var at runtime, falls back to production URL
 pub const DEFAULT_API_URL: &str = "https://nubilus.akashtwt.me/api";
 
@@ -138,3 +886,150 @@ pub fn default_config_path() -> &'static str {
         "/etc/nubilus/agent.toml"
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENDPOINT: &str = "3f2b8c1a-6d4e-4f0a-9b7c-1e5d8a2f6c90";
+
+    fn parse(extra: &str) -> Result<Config> {
+        let content = format!(
+            "[server]\napi_url = \"http://localhost:3000\"\napi_key = \"nub_test\"\n\n\
+             [agent]\nname = \"test\"\n\n{}",
+            extra
+        );
+        let config: Config = toml::from_str(&content)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    #[test]
+    fn reads_checks_without_kind_as_http() {
+        let config = parse(
+            r#"
+[features]
+http_health_checks = true
+
+[[health_checks]]
+endpoint_id = "3f2b8c1a-6d4e-4f0a-9b7c-1e5d8a2f6c90"
+url = "http://127.0.0.1:8080/health"
+"#,
+        )
+        .unwrap();
+
+        assert!(config.features.health_checks);
+        let check = &config.health_checks[0];
+        let CheckKind::Http(http) = &check.kind else {
+            panic!("expected an HTTP check, got {:?}", check.kind);
+        };
+        assert_eq!(http.url, "http://127.0.0.1:8080/health");
+        assert_eq!(http.method, "GET");
+        assert_eq!(http.expected_status, 200);
+        assert_eq!(check.timeout_seconds, 10);
+    }
+
+    #[test]
+    fn reads_each_kind_with_its_settings() {
+        let config = parse(
+            r#"
+[features]
+health_checks = true
+
+[[health_checks]]
+endpoint_id = "8a1c4e7f-2b9d-4c3e-a6f1-5d0b7e9c2a48"
+kind = "tcp"
+host = "smtp.internal"
+port = 25
+expect = "250"
+
+[[health_checks]]
+endpoint_id = "c5e9a2d7-4f1b-4b8e-8d3a-9e6c1f0b7a25"
+kind = "dns"
+record = "app.internal"
+record_type = "AAAA"
+resolver = "10.0.0.2:5353"
+
+[[health_checks]]
+endpoint_id = "1d7f3b9e-8c2a-4e6d-b5f0-a4c8e2d6f913"
+kind = "ping"
+host = "10.0.0.1"
+max_packet_loss = 20
+"#,
+        )
+        .unwrap();
+
+        let targets: Vec<String> = config.health_checks.iter().map(HealthCheckConfig::target).collect();
+        assert_eq!(targets, ["smtp.internal:25", "AAAA app.internal @10.0.0.2:5353", "10.0.0.1"]);
+        let CheckKind::Ping(ping) = &config.health_checks[2].kind else {
+            panic!("expected a ping check");
+        };
+        assert_eq!(ping.count, 5);
+        assert_eq!(ping.max_packet_loss, Some(20.0));
+    }
+
+    #[test]
+    fn requires_the_settings_of_the_kind() {
+        let missing_port = parse(
+            r#"
+[[health_checks]]
+endpoint_id = "8a1c4e7f-2b9d-4c3e-a6f1-5d0b7e9c2a48"
+kind = "tcp"
+host = "smtp.internal"
+"#,
+        );
+        assert!(format!("{:#}", missing_port.unwrap_err()).contains("missing field `port`"));
+
+        let unknown_kind = parse(
+            r#"
+[[health_checks]]
+endpoint_id = "6b0e8d4a-1f7c-4a9b-9e2d-3c5a7f1b8e64"
+kind = "ftp"
+host = "ftp.internal"
+"#,
+        );
+        assert!(format!("{:#}", unknown_kind.unwrap_err()).contains("unknown variant `ftp`"));
+    }
+
+    #[test]
+    fn validates_enabled_checks() {
+        let error = |settings: &str| {
+            let content = format!("[features]\nhealth_checks = true\n\n[[health_checks]]\n{}", settings);
+            parse(&content).unwrap_err().to_string()
+        };
+        let check_error = |settings: &str| error(&format!("endpoint_id = \"{}\"\n{}", ENDPOINT, settings));
+
+        assert_eq!(
+            error("endpoint_id = \"web\"\nurl = \"http://web\""),
+            "health_checks.endpoint_id must be the endpoint's UUID (web)"
+        );
+
+        assert_eq!(
+            check_error("url = \"ftp://files\""),
+            "health_checks.url must start with http:// or https:// (ftp://files)"
+        );
+        assert_eq!(
+            check_error("kind = \"tcp\"\nhost = \"smtp.internal\"\nport = 0"),
+            "health_checks.port cannot be 0 (smtp.internal)"
+        );
+        assert_eq!(
+            check_error("kind = \"dns\"\nrecord = \"app.internal\"\nexpected = [\"10.0.0\"]"),
+            "health_checks.expected has an invalid A answer (\"10.0.0\")"
+        );
+        assert_eq!(
+            check_error("kind = \"ping\"\nhost = \"10.0.0.1\"\ncount = 20"),
+            "health_checks.count cannot exceed timeout_seconds, ping checks send one packet per second"
+        );
+
+        // Checks are only validated when they're enabled
+        parse("[[health_checks]]\nendpoint_id = \"web\"\nurl = \"ftp://files\"").unwrap();
+    }
+
+    #[test]
+    fn template_is_a_valid_config() {
+        let template = Config::template().replace("nub_your_api_key_here", "nub_test");
+        let config: Config = toml::from_str(&template).unwrap();
+        config.validate().unwrap();
+        assert!(!config.features.health_checks);
+    }
+}
@@ -4,9 +4,11 @@
 //! to the Nubilus monitoring platform.
 
 mod api;
+mod checks;
 mod collectors;
 mod config;
 mod models;
+mod spool;
 
 use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
@@ -18,8 +20,9 @@ use tracing_subscriber::FmtSubscriber;
 
 use crate::api::{backoff_duration, ApiClient, ApiError};
 use crate::collectors::MetricsCollector;
-use crate::config::{default_config_path, Config};
-use crate::models::RegisterRequest;
+use crate::config::{default_config_path, Config, HealthCheckConfig};
+use crate::models::{MetricsPayload, RegisterRequest};
+use crate::spool::Spool;
 
 /// Nubilus Agent - Server monitoring daemon
 #[derive(Parser)]
@@ -101,7 +104,7 @@ async fn main() -> Result<()> {
             configure_agent(&cli.config, api_key, api_url, name)
         }
         Commands::Test => test_connection(&cli.config).await,
-        Commands::Metrics => show_metrics(),
+        Commands::Metrics => show_metrics(&cli.config),
         Commands::Update => update_agent().await,
         Commands::Uninstall { keep_config } => uninstall_agent(keep_config),
     }
@@ -122,7 +125,23 @@ async fn run_agent(config_path: &PathBuf) -> Result<()> {
     let api_client = ApiClient::new(&config)?;
 
     // Create metrics collector
-    let mut metrics_collector = MetricsCollector::new();
+    let metrics_collector = MetricsCollector::from_config(&config)?;
+
+    // Open the spool for metrics that can't be delivered right away
+    let spool = if config.spool.enabled {
+        match Spool::open(&config.spool) {
+            Ok(spool) => {
+                info!("Spooling undelivered metrics to: {}", config.spool.directory.display());
+                Some(spool)
+            }
+            Err(e) => {
+                warn!("Metrics spool disabled: {:#}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
 
     // Register this server
     let server_id = register_with_retry(&api_client, &config).await?;
@@ -137,14 +156,31 @@ async fn run_agent(config_path: &PathBuf) -> Result<()> {
         config.agent.metrics_interval_seconds, config.agent.heartbeat_interval_seconds
     );
 
-    // Run metrics and heartbeat loops concurrently
+    let health_checks = if config.features.health_checks {
+        info!("Running {} agent-side health check(s)", config.health_checks.len());
+        config.health_checks.clone()
+    } else {
+        Vec::new()
+    };
+
+    let service_sources = collectors::services::ServiceSources::from_config(&config)?;
+    let services_interval =
+        (!service_sources.is_empty()).then(|| Duration::from_secs(config.services.interval_seconds));
+
+    // Run metrics, heartbeat, health check and service discovery loops concurrently
     tokio::select! {
-        result = metrics_loop(&api_client, &mut metrics_collector, metrics_interval) => {
+        result = metrics_loop(&api_client, metrics_collector, spool, metrics_interval, &config.agent.name) => {
             error!("Metrics loop exited: {:?}", result);
         }
         result = heartbeat_loop(&api_client, heartbeat_interval) => {
             error!("Heartbeat loop exited: {:?}", result);
         }
+        result = health_check_loop(&api_client, health_checks, config.agent.name.clone()) => {
+            error!("Health check loop exited: {:?}", result);
+        }
+        result = service_discovery_loop(&api_client, services_interval, service_sources) => {
+            error!("Service discovery loop exited: {:?}", result);
+        }
         _ = tokio::signal::ctrl_c() => {
             info!("Received shutdown signal, exiting...");
         }
@@ -195,11 +231,16 @@ async fn register_with_retry(api_client: &ApiClient, config: &Config) -> Result<
     }
 }
 
+/// Maximum number of spooled payloads replayed per metrics tick
+const SPOOL_REPLAY_BATCH: usize = 100;
+
 /// Main metrics collection and submission loop
 async fn metrics_loop(
     api_client: &ApiClient,
-    collector: &mut MetricsCollector,
+    mut collector: MetricsCollector,
+    mut spool: Option<Spool>,
     interval_duration: Duration,
+    checked_from: &str,
 ) -> Result<()> {
     let mut ticker = interval(interval_duration);
     let mut consecutive_failures = 0u32;
@@ -207,13 +248,44 @@ async fn metrics_loop(
     loop {
         ticker.tick().await;
 
-        let metrics = collector.collect();
-        
+        // Collection reads /proc and /sys, waits for network filesystem
+        // probes and queries Docker and systemd, so keep it off the runtime
+        let (returned, metrics) = tokio::task::spawn_blocking(move || {
+            let metrics = collector.collect();
+            (collector, metrics)
+        })
+        .await
+        .context("Metrics collection panicked")?;
+        collector = returned;
+
         debug!(
-            "Collected: CPU={:.1}%, Mem={:.1}%, Disk={:.1}%",
-            metrics.cpu_usage, metrics.memory_usage, metrics.disk_usage
+            "Collected #{}: CPU={:.1}%, Mem={:.1}%, Disk={:.1}%",
+            metrics.sequence, metrics.cpu_usage, metrics.memory_usage, metrics.disk_usage
         );
 
+        // Process checks tied to an endpoint also count as its health checks.
+        // They're sent in the background so a slow backend doesn't hold up the metrics.
+        for result in &metrics.process_checks {
+            let Some(payload) = checks::process::health_payload(result, checked_from) else {
+                continue;
+            };
+            let api_client = api_client.clone();
+            let name = result.name.clone();
+            tokio::spawn(async move {
+                if let Err(e) = api_client.submit_health_check(&payload).await {
+                    warn!("Failed to submit process check '{}': {}", name, e);
+                }
+            });
+        }
+
+        // Older spooled metrics go first so the backend receives them in order
+        if let Some(spool) = spool.as_mut() {
+            if !replay_spool(api_client, spool).await? {
+                spool_metrics(spool, &metrics);
+                continue;
+            }
+        }
+
         match api_client.submit_metrics(&metrics).await {
             Ok(()) => {
                 consecutive_failures = 0;
@@ -222,20 +294,91 @@ async fn metrics_loop(
                 error!("API key became invalid. Stopping agent.");
                 anyhow::bail!("Authentication failed");
             }
-            Err(ApiError::NotRegistered) => {
-                warn!("Server not found. Agent may need to restart to re-register.");
-            }
             Err(e) => {
                 consecutive_failures += 1;
-                warn!(
-                    "Failed to submit metrics (failure #{}): {}",
-                    consecutive_failures, e
-                );
+                if let ApiError::NotRegistered = e {
+                    warn!("Server not found. Agent may need to restart to re-register.");
+                } else {
+                    warn!(
+                        "Failed to submit metrics (failure #{}): {}",
+                        consecutive_failures, e
+                    );
+                }
+
+                if e.is_transient() {
+                    if let Some(spool) = spool.as_mut() {
+                        spool_metrics(spool, &metrics);
+                    }
+                }
             }
         }
     }
 }
 
+/// Submit spooled metrics, oldest first
+///
+/// Returns `true` once the spool is empty, or `false` if the backend is still
+/// unreachable or more payloads are left for the next tick.
+async fn replay_spool(api_client: &ApiClient, spool: &mut Spool) -> Result<bool> {
+    let entries = match spool.entries() {
+        Ok(entries) => entries,
+        Err(e) => {
+            warn!("Failed to read metrics spool: {:#}", e);
+            return Ok(true);
+        }
+    };
+
+    if entries.is_empty() {
+        return Ok(true);
+    }
+
+    let pending = entries.len();
+    let mut replayed = 0;
+
+    for entry in entries.iter().take(SPOOL_REPLAY_BATCH) {
+        let payload = match spool.load(entry) {
+            Ok(payload) => payload,
+            Err(e) => {
+                warn!("Dropping unreadable spooled metrics: {:#}", e);
+                spool.remove(entry);
+                continue;
+            }
+        };
+
+        match api_client.submit_metrics(&payload).await {
+            Ok(()) => {
+                spool.remove(entry);
+                replayed += 1;
+            }
+            Err(ApiError::Unauthorized) => {
+                error!("API key became invalid. Stopping agent.");
+                anyhow::bail!("Authentication failed");
+            }
+            Err(e) if e.is_transient() => {
+                debug!("Backend still unavailable, keeping {} spooled metrics", pending - replayed);
+                return Ok(false);
+            }
+            Err(e) => {
+                warn!("Backend rejected spooled metrics, dropping them: {}", e);
+                spool.remove(entry);
+            }
+        }
+    }
+
+    if replayed > 0 {
+        info!("Replayed {} spooled metrics", replayed);
+    }
+
+    Ok(pending <= SPOOL_REPLAY_BATCH)
+}
+
+/// Add metrics to the spool, logging instead of failing
+fn spool_metrics(spool: &mut Spool, metrics: &MetricsPayload) {
+    if let Err(e) = spool.push(metrics) {
+        warn!("Failed to spool metrics: {:#}", e);
+    }
+}
+
 /// Heartbeat loop to keep server status active
 async fn heartbeat_loop(api_client: &ApiClient, interval_duration: Duration) -> Result<()> {
     let mut ticker = interval(interval_duration);
@@ -251,6 +394,104 @@ async fn heartbeat_loop(api_client: &ApiClient, interval_duration: Duration) ->
     }
 }
 
+/// Run every configured health check on its own schedule
+async fn health_check_loop(
+    api_client: &ApiClient,
+    checks: Vec<HealthCheckConfig>,
+    checked_from: String,
+) -> Result<()> {
+    if checks.is_empty() {
+        // Nothing to do, but don't end the select! in run_agent
+        std::future::pending::<()>().await;
+    }
+
+    let http_client = checks::http::build_client()?;
+    let mut tasks = tokio::task::JoinSet::new();
+
+    for check in checks {
+        let api_client = api_client.clone();
+        let http_client = http_client.clone();
+        let checked_from = checked_from.clone();
+
+        tasks.spawn(async move {
+            let mut ticker = interval(Duration::from_secs(check.interval_seconds));
+
+            loop {
+                ticker.tick().await;
+
+                let result = checks::run(&http_client, &check, &checked_from).await;
+                debug!(
+                    "Health check {} ({}): up={}, {:.0}ms",
+                    check.endpoint_id, check.target(), result.is_up, result.response_time
+                );
+
+                match api_client.submit_health_check(&result).await {
+                    Ok(()) => {}
+                    Err(ApiError::Unauthorized) => {
+                        error!("API key became invalid. Stopping health checks.");
+                        return;
+                    }
+                    Err(e) => {
+                        warn!("Failed to submit health check for {}: {}", check.endpoint_id, e);
+                    }
+                }
+            }
+        });
+    }
+
+    while let Some(result) = tasks.join_next().await {
+        if let Err(e) = result {
+            error!("Health check task failed: {}", e);
+        }
+    }
+
+    anyhow::bail!("All health checks stopped")
+}
+
+/// Periodically report the services, containers and units running on this server
+async fn service_discovery_loop(
+    api_client: &ApiClient,
+    interval_duration: Option<Duration>,
+    sources: collectors::services::ServiceSources,
+) -> Result<()> {
+    let Some(interval_duration) = interval_duration else {
+        // Discovery is disabled, but don't end the select! in run_agent
+        return std::future::pending().await;
+    };
+
+    let mut ticker = interval(interval_duration);
+
+    loop {
+        ticker.tick().await;
+
+        // Scanning /proc/<pid>/fd touches every process and the Docker and
+        // D-Bus clients block, keep them off the runtime threads
+        let sources = sources.clone();
+        let discovery = move || collectors::services::inventory(&sources);
+        let inventory = match tokio::task::spawn_blocking(discovery).await {
+            Ok(inventory) => inventory,
+            Err(e) => {
+                warn!("Service discovery failed: {}", e);
+                continue;
+            }
+        };
+        if inventory.service_types.is_empty() {
+            continue;
+        }
+
+        match api_client.submit_services(&inventory).await {
+            Ok(()) => debug!("Reported {} service(s)", inventory.services.len()),
+            Err(ApiError::Unauthorized) => {
+                error!("API key became invalid. Stopping service discovery.");
+                anyhow::bail!("Authentication failed");
+            }
+            Err(e) => {
+                warn!("Failed to submit services: {}", e);
+            }
+        }
+    }
+}
+
 /// Generate a configuration file template
 fn init_config(output: Option<PathBuf>) -> Result<()> {
     let output_path = output.unwrap_or_else(|| PathBuf::from("agent.toml"));
@@ -289,6 +530,16 @@ fn configure_agent(
             heartbeat_interval_seconds: 30,
         },
         features: config::FeaturesConfig::default(),
+        disk: config::DiskConfig::default(),
+        network: config::NetworkConfig::default(),
+        spool: config::SpoolConfig::default(),
+        cgroups: config::CgroupConfig::default(),
+        docker: config::DockerConfig::default(),
+        systemd: config::SystemdConfig::default(),
+        processes: config::ProcessConfig::default(),
+        services: config::ServicesConfig::default(),
+        health_checks: Vec::new(),
+        process_checks: Vec::new(),
     };
 
     // Create parent directories if needed
@@ -342,8 +593,15 @@ async fn test_connection(config_path: &PathBuf) -> Result<()> {
 }
 
 /// Show current system metrics
-fn show_metrics() -> Result<()> {
-    let mut collector = MetricsCollector::new();
+///
+/// Uses the collector settings from the config file when there is one, so
+/// the output matches what the agent would report.
+fn show_metrics(config_path: &PathBuf) -> Result<()> {
+    let mut collector = if config_path.exists() {
+        MetricsCollector::from_config(&Config::from_file(config_path)?)?
+    } else {
+        MetricsCollector::new()
+    };
     
     // Wait a moment for CPU usage to stabilize
     std::thread::sleep(Duration::from_millis(500));
@@ -355,18 +613,44 @@ fn show_metrics() -> Result<()> {
     println!("CPU:");
     println!("  Usage:    {:.1}%", metrics.cpu_usage);
     println!("  Cores:    {}", metrics.cpu_count);
+    if let Some(max) = metrics.max_core_usage {
+        println!("  Max Core: {:.1}%", max);
+    }
     if let Some(load) = metrics.load_average_1m {
         println!("  Load Avg: {:.2} / {:.2} / {:.2}", 
                  load, 
                  metrics.load_average_5m.unwrap_or(0.0),
                  metrics.load_average_15m.unwrap_or(0.0));
     }
-    
+
+    let pressures = [
+        ("CPU", &metrics.cpu_pressure),
+        ("Memory", &metrics.memory_pressure),
+        ("I/O", &metrics.io_pressure),
+    ];
+    if pressures.iter().any(|(_, pressure)| pressure.is_some()) {
+        println!("\nPressure (some avg10 / avg60 / avg300):");
+        for (name, pressure) in pressures {
+            if let Some(pressure) = pressure {
+                println!("  {:<7} {:.2}% / {:.2}% / {:.2}%",
+                         format!("{}:", name),
+                         pressure.some.avg10,
+                         pressure.some.avg60,
+                         pressure.some.avg300);
+            }
+        }
+    }
+
     println!("\nMemory:");
     println!("  Usage:     {:.1}%", metrics.memory_usage);
     println!("  Total:     {}", format_bytes(metrics.memory_total));
     println!("  Used:      {}", format_bytes(metrics.memory_used));
     println!("  Available: {}", format_bytes(metrics.memory_available));
+    if let (Some(cached), Some(buffers)) = (metrics.memory_cached, metrics.memory_buffers) {
+        println!("  Cached:    {}", format_bytes(cached));
+        println!("  Buffers:   {}", format_bytes(buffers));
+    }
+    println!("  Swap:      {} / {}", format_bytes(metrics.swap_used), format_bytes(metrics.swap_total));
     
     println!("\nDisk:");
     println!("  Usage: {:.1}%", metrics.disk_usage);
@@ -374,10 +658,59 @@ fn show_metrics() -> Result<()> {
     println!("  Used:  {}", format_bytes(metrics.disk_used));
     println!("  Read:  {}", format_bytes(metrics.disk_read_bytes));
     println!("  Write: {}", format_bytes(metrics.disk_write_bytes));
+
+    if !metrics.filesystems.is_empty() {
+        println!("\nFilesystems:");
+        for fs in &metrics.filesystems {
+            let inodes = fs
+                .inode_usage
+                .map(|usage| format!("  inodes {:.1}%", usage))
+                .unwrap_or_default();
+            println!(
+                "  {:<24} {:<8} {:>5.1}%  {} / {}{}",
+                fs.mount_point,
+                fs.fs_type,
+                fs.usage,
+                format_bytes(fs.used),
+                format_bytes(fs.total),
+                inodes
+            );
+        }
+    }
     
     println!("\nNetwork:");
     println!("  Received:    {}", format_bytes(metrics.network_in));
     println!("  Transmitted: {}", format_bytes(metrics.network_out));
+    for iface in &metrics.network_interfaces {
+        println!(
+            "  {:<12} {:<8} rx {} / tx {}",
+            iface.name,
+            iface.operstate.as_deref().unwrap_or("?"),
+            format_bytes(iface.rx_bytes as i64),
+            format_bytes(iface.tx_bytes as i64)
+        );
+    }
+
+    if !metrics.temperatures.is_empty() || !metrics.fans.is_empty() {
+        println!("\nSensors:");
+        for sensor in &metrics.temperatures {
+            println!("  {:<12} {:<20} {:>5.1}°C", sensor.chip, sensor.label, sensor.celsius);
+        }
+        for fan in &metrics.fans {
+            println!("  {:<12} {:<20} {:>5} RPM", fan.chip, fan.label, fan.rpm);
+        }
+    }
+
+    if !metrics.processes.is_empty() {
+        println!("\nTop Processes:");
+        println!("  {:>7}  {:>6}  {:>10}  {:<10}  NAME", "PID", "CPU%", "MEM", "STATUS");
+        for process in &metrics.processes {
+            println!(
+                "  {:>7}  {:>6.1}  {:>7.1} MB  {:<10}  {}",
+                process.pid, process.cpu_percent, process.memory_mb, process.status, process.process_name
+            );
+        }
+    }
 
     Ok(())
 }
@@ -532,7 +865,14 @@ fn uninstall_agent(keep_config: bool) -> Result<()> {
         info!("Keeping configuration files at /etc/nubilus");
     }
 
-    // 4. Get current executable path and schedule removal
+    // 4. Remove spooled metrics and other agent state
+    let state_dir = "/var/lib/nubilus";
+    if std::path::Path::new(state_dir).exists() {
+        info!("Removing state directory...");
+        std::fs::remove_dir_all(state_dir).ok();
+    }
+
+    // 5. Get current executable path and schedule removal
     let current_exe = std::env::current_exe()
         .unwrap_or_else(|_| std::path::PathBuf::from("/usr/local/bin/nubilus-agent"));
     
@@ -1,5 +1,6 @@
 //! Data models for API requests and responses
 
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 /// Server registration request sent on agent startup
@@ -27,20 +28,81 @@ pub struct RegisterData {
 }
 
 /// Metrics payload sent periodically
-#[derive(Debug, Serialize, Default)]
+///
+/// Fields missing when reading a payload back from the spool take their
+/// default, so files written by older agent versions can still be sent.
+#[derive(Debug, Serialize, Deserialize, Default)]
+#[serde(default)]
 pub struct MetricsPayload {
+    /// When the metrics were collected (payloads may be delivered late from the spool)
+    pub collected_at: DateTime<Utc>,
+    /// Random ID generated each time the agent starts
+    pub boot_id: String,
+    /// Increases by one for every payload collected since the agent started,
+    /// so the backend can detect gaps, duplicates and reordering
+    pub sequence: u64,
+
     // CPU metrics
     pub cpu_usage: f64,
     pub cpu_count: i32,
     pub load_average_1m: Option<f64>,
     pub load_average_5m: Option<f64>,
     pub load_average_15m: Option<f64>,
+    /// CPU time breakdown since the previous payload, in percent (Linux only)
+    pub cpu_user: Option<f64>,
+    pub cpu_nice: Option<f64>,
+    pub cpu_system: Option<f64>,
+    pub cpu_idle: Option<f64>,
+    pub cpu_iowait: Option<f64>,
+    pub cpu_irq: Option<f64>,
+    pub cpu_softirq: Option<f64>,
+    pub cpu_steal: Option<f64>,
+    pub cpu_guest: Option<f64>,
+    pub context_switches_per_sec: Option<f64>,
+    pub interrupts_per_sec: Option<f64>,
+    pub forks_per_sec: Option<f64>,
+    pub procs_running: Option<u64>,
+    pub procs_blocked: Option<u64>,
+    /// Usage of the busiest core (0-100)
+    pub max_core_usage: Option<f64>,
+    /// Per-core usage, only when `features.per_core_cpu` is enabled
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cpu_cores: Vec<CpuCoreMetrics>,
+    /// Hottest CPU temperature in °C, `None` without CPU sensors
+    pub cpu_temperature: Option<f64>,
+    /// Pressure stall information, `None` when the kernel lacks PSI (Linux only)
+    pub cpu_pressure: Option<ResourcePressure>,
+    pub memory_pressure: Option<ResourcePressure>,
+    pub io_pressure: Option<ResourcePressure>,
 
     // Memory metrics
     pub memory_usage: f64,
     pub memory_total: i64,
     pub memory_used: i64,
     pub memory_available: i64,
+    pub swap_total: i64,
+    pub swap_used: i64,
+    /// Memory breakdown from /proc/meminfo, in bytes (Linux only)
+    pub memory_cached: Option<i64>,
+    pub memory_buffers: Option<i64>,
+    pub memory_shared: Option<i64>,
+    pub memory_dirty: Option<i64>,
+    pub memory_writeback: Option<i64>,
+    pub memory_slab: Option<i64>,
+    pub memory_slab_reclaimable: Option<i64>,
+    pub hugepages_total: Option<u64>,
+    pub hugepages_free: Option<u64>,
+    pub hugepage_size: Option<i64>,
+    /// Paging activity since the previous payload (Linux only)
+    pub major_faults_per_sec: Option<f64>,
+    pub swap_in_pages_per_sec: Option<f64>,
+    pub swap_out_pages_per_sec: Option<f64>,
+    /// OOM kills since the previous payload (Linux only)
+    pub oom_kills: Option<u64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cgroup_oom_kills: Vec<CgroupOomKills>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub oom_kill_events: Vec<OomKillEvent>,
 
     // Disk metrics
     pub disk_usage: f64,
@@ -48,10 +110,461 @@ pub struct MetricsPayload {
     pub disk_used: i64,
     pub disk_read_bytes: i64,
     pub disk_write_bytes: i64,
+    /// Usage of each mounted filesystem (`disk_usage`/`disk_total`/`disk_used` cover `/`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub filesystems: Vec<FilesystemUsage>,
+    /// Per-device I/O statistics (`disk_read_bytes`/`disk_write_bytes` sum the leaf devices)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub disk_devices: Vec<DiskIoMetrics>,
+    /// Read throughput since the previous payload (`None` on the first sample or after a reset)
+    pub disk_read_bytes_per_sec: Option<f64>,
+    /// Write throughput since the previous payload
+    pub disk_write_bytes_per_sec: Option<f64>,
 
     // Network metrics
     pub network_in: i64,
     pub network_out: i64,
+    /// Per-interface statistics (`network_in`/`network_out` sum these interfaces)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub network_interfaces: Vec<NetworkInterfaceMetrics>,
+    /// Receive throughput since the previous payload
+    pub network_in_bytes_per_sec: Option<f64>,
+    /// Transmit throughput since the previous payload
+    pub network_out_bytes_per_sec: Option<f64>,
+    /// TCP/UDP socket statistics (Linux only)
+    pub sockets: Option<SocketMetrics>,
+
+    // cgroups
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cgroups: Vec<CgroupMetrics>,
+
+    // Docker
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub containers: Vec<ContainerMetrics>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub container_events: Vec<ContainerEvent>,
+
+    // systemd
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub systemd_units: Vec<SystemdUnitMetrics>,
+
+    // Sensors (Linux only)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub temperatures: Vec<TemperatureSensor>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fans: Vec<FanSensor>,
+
+    // Process metrics (only when features.collect_processes is enabled)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub processes: Vec<ProcessMetrics>,
+
+    // Process checks (only when features.process_checks is enabled)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub process_checks: Vec<ProcessCheckResult>,
+}
+
+/// Usage of a single logical CPU
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CpuCoreMetrics {
+    /// Index of the logical CPU, as in `cpu0`, `cpu1`, ...
+    pub core: u32,
+    /// Usage percentage (0-100)
+    pub usage: f64,
+    /// Current frequency, `None` when the platform doesn't expose it
+    pub frequency_mhz: Option<u64>,
+}
+
+/// Pressure stall information for one resource
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResourcePressure {
+    /// Time at least one task was stalled on the resource
+    pub some: PressureStall,
+    /// Time all non-idle tasks were stalled at once (not reported for cpu on older kernels)
+    pub full: Option<PressureStall>,
+}
+
+/// Stall figures from a single PSI line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PressureStall {
+    /// Percentage of time stalled over the last 10 seconds
+    pub avg10: f64,
+    /// Percentage of time stalled over the last 60 seconds
+    pub avg60: f64,
+    /// Percentage of time stalled over the last 300 seconds
+    pub avg300: f64,
+    /// Total stall time since boot, in microseconds
+    pub total_us: u64,
+}
+
+/// A process killed by the kernel OOM killer
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OomKillEvent {
+    pub time: DateTime<Utc>,
+    pub pid: i32,
+    pub process_name: Option<String>,
+    /// Memory cgroup of the killed task, e.g. "/system.slice/nginx.service"
+    pub cgroup: Option<String>,
+    /// Why the OOM killer ran, e.g. "CONSTRAINT_NONE" or "CONSTRAINT_MEMCG"
+    pub constraint: Option<String>,
+}
+
+/// OOM kills inside a single cgroup
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CgroupOomKills {
+    pub cgroup: String,
+    pub count: u64,
+}
+
+/// I/O statistics of a block device
+///
+/// Counters are cumulative since boot; rates are over the interval since the
+/// previous payload and are `None` on the first sample or after a reset.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DiskIoMetrics {
+    pub device: String,
+    /// False for stacked md/dm devices, whose I/O is also counted on the devices below
+    pub is_leaf: bool,
+    pub reads_completed: u64,
+    pub reads_merged: u64,
+    pub read_bytes: u64,
+    pub read_time_ms: u64,
+    pub writes_completed: u64,
+    pub writes_merged: u64,
+    pub write_bytes: u64,
+    pub write_time_ms: u64,
+    pub in_flight: u64,
+    pub io_time_ms: u64,
+    pub read_iops: Option<f64>,
+    pub write_iops: Option<f64>,
+    pub read_bytes_per_sec: Option<f64>,
+    pub write_bytes_per_sec: Option<f64>,
+    /// Average time per completed read, including queueing
+    pub read_await_ms: Option<f64>,
+    /// Average time per completed write, including queueing
+    pub write_await_ms: Option<f64>,
+    /// Percentage of time the device was busy
+    pub utilization: Option<f64>,
+}
+
+/// Statistics of a network interface
+///
+/// Counters are cumulative; rates are over the interval since the previous
+/// payload and are `None` on the first sample or after a reset.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NetworkInterfaceMetrics {
+    pub name: String,
+    /// Operational state from the kernel ("up", "down", "unknown", ...)
+    pub operstate: Option<String>,
+    pub mtu: Option<u32>,
+    /// Negotiated link speed, `None` when the link is down or has no speed
+    pub speed_mbps: Option<u64>,
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+    pub rx_packets: u64,
+    pub tx_packets: u64,
+    pub rx_errors: u64,
+    pub tx_errors: u64,
+    pub rx_dropped: Option<u64>,
+    pub tx_dropped: Option<u64>,
+    pub rx_bytes_per_sec: Option<f64>,
+    pub tx_bytes_per_sec: Option<f64>,
+    pub rx_packets_per_sec: Option<f64>,
+    pub tx_packets_per_sec: Option<f64>,
+}
+
+/// Resource usage of a cgroup
+///
+/// Rates are over the interval since the previous payload and are `None` on
+/// the first sample or when the controller isn't enabled for the cgroup.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CgroupMetrics {
+    /// Path under /sys/fs/cgroup, e.g. "/system.slice/nginx.service"
+    pub path: String,
+    /// CPU usage as a percentage of one core (can exceed 100)
+    pub cpu_usage_percent: Option<f64>,
+    pub cpu_user_percent: Option<f64>,
+    pub cpu_system_percent: Option<f64>,
+    /// Share of CPU quota periods in which the cgroup was throttled
+    pub cpu_throttled_periods_percent: Option<f64>,
+    /// Time spent throttled, as a percentage of one core
+    pub cpu_throttled_percent: Option<f64>,
+    /// Number of throttled periods since the cgroup was created
+    pub nr_throttled: Option<u64>,
+    /// Memory in use, in bytes
+    pub memory_current: Option<u64>,
+    /// Memory limit in bytes, `None` when unlimited
+    pub memory_max: Option<u64>,
+    /// Memory in use as a percentage of the limit
+    pub memory_usage: Option<f64>,
+    pub io_read_bytes_per_sec: Option<f64>,
+    pub io_write_bytes_per_sec: Option<f64>,
+    pub io_read_iops: Option<f64>,
+    pub io_write_iops: Option<f64>,
+    pub pids_current: Option<u64>,
+    /// Task limit, `None` when unlimited
+    pub pids_max: Option<u64>,
+}
+
+/// A Docker container
+///
+/// Resource usage is only reported for running containers; rates are over
+/// the interval since the previous payload and are `None` on the first sample.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ContainerMetrics {
+    /// Short (12 character) container ID
+    pub id: String,
+    pub name: String,
+    pub image: String,
+    /// created, running, paused, restarting, removing, exited or dead
+    pub state: String,
+    /// Human-readable status from Docker, e.g. "Up 2 hours (healthy)"
+    pub status: String,
+    pub restart_count: Option<u64>,
+    /// Health check status (starting, healthy or unhealthy), `None` without a health check
+    pub health: Option<String>,
+    /// Exit code of an exited container
+    pub exit_code: Option<i64>,
+    pub started_at: Option<DateTime<Utc>>,
+    /// CPU usage as a percentage of one core (can exceed 100)
+    pub cpu_usage_percent: Option<f64>,
+    /// Memory in use excluding reclaimable page cache, in bytes
+    pub memory_usage_bytes: Option<u64>,
+    /// Memory limit in bytes (the host's memory when unlimited)
+    pub memory_limit: Option<u64>,
+    /// Memory in use as a percentage of the limit
+    pub memory_usage: Option<f64>,
+    pub network_rx_bytes_per_sec: Option<f64>,
+    pub network_tx_bytes_per_sec: Option<f64>,
+    pub block_read_bytes_per_sec: Option<f64>,
+    pub block_write_bytes_per_sec: Option<f64>,
+    pub pids: Option<u64>,
+}
+
+/// A container start, stop or die event
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ContainerEvent {
+    pub time: DateTime<Utc>,
+    /// Short (12 character) container ID
+    pub container_id: String,
+    pub name: String,
+    pub image: Option<String>,
+    /// start, stop or die
+    pub action: String,
+    /// Exit code, for die events
+    pub exit_code: Option<i64>,
+}
+
+/// State of a systemd unit
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SystemdUnitMetrics {
+    /// Unit name, e.g. "nginx.service"
+    pub name: String,
+    pub description: String,
+    /// loaded, not-found, masked, ...
+    pub load_state: String,
+    /// active, reloading, inactive, failed, activating or deactivating
+    pub active_state: String,
+    /// Unit type specific state, e.g. running, exited, dead or auto-restart
+    pub sub_state: String,
+    /// Automatic restarts since the unit was last started manually (services only)
+    pub restarts: Option<u32>,
+    /// When the unit last changed its active state
+    pub state_changed_at: Option<DateTime<Utc>>,
+}
+
+/// A temperature sensor from hwmon or a thermal zone
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TemperatureSensor {
+    /// Driver or thermal zone type, e.g. "coretemp", "nvme", "acpitz"
+    pub chip: String,
+    /// Device the chip belongs to, e.g. "nvme0", to tell identical chips apart
+    pub device: Option<String>,
+    /// Sensor label, e.g. "Package id 0", "Core 3", "Composite"
+    pub label: String,
+    pub celsius: f64,
+    /// Temperature the hardware considers high
+    pub max: Option<f64>,
+    /// Temperature at which the hardware shuts down or throttles hard
+    pub critical: Option<f64>,
+}
+
+/// A fan speed sensor from hwmon
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FanSensor {
+    pub chip: String,
+    pub device: Option<String>,
+    pub label: String,
+    pub rpm: u64,
+}
+
+/// TCP/UDP socket statistics
+///
+/// Counts are current values; rates are over the interval since the previous
+/// payload and are `None` on the first sample.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SocketMetrics {
+    /// Sockets of any kind in use
+    pub sockets_used: Option<u64>,
+    pub tcp_inuse: Option<u64>,
+    pub tcp6_inuse: Option<u64>,
+    /// TCP sockets no longer attached to a process
+    pub tcp_orphan: Option<u64>,
+    pub tcp_time_wait: Option<u64>,
+    pub tcp_alloc: Option<u64>,
+    /// Memory used by TCP buffers, in pages
+    pub tcp_mem_pages: Option<u64>,
+    pub udp_inuse: Option<u64>,
+    pub udp6_inuse: Option<u64>,
+    /// Memory used by UDP buffers, in pages
+    pub udp_mem_pages: Option<u64>,
+    /// TCP sockets (IPv4 and IPv6) by connection state
+    pub tcp_states: TcpStateCounts,
+    pub tcp_active_opens_per_sec: Option<f64>,
+    pub tcp_passive_opens_per_sec: Option<f64>,
+    pub tcp_attempt_fails_per_sec: Option<f64>,
+    pub tcp_estab_resets_per_sec: Option<f64>,
+    pub tcp_out_segs_per_sec: Option<f64>,
+    pub tcp_retransmits_per_sec: Option<f64>,
+    /// Retransmitted segments as a percentage of segments sent
+    pub tcp_retransmit_ratio: Option<f64>,
+    pub tcp_in_errors_per_sec: Option<f64>,
+    pub tcp_resets_sent_per_sec: Option<f64>,
+    pub tcp_timeouts_per_sec: Option<f64>,
+    /// Connections dropped because a listen queue was full
+    pub listen_overflows_per_sec: Option<f64>,
+    /// Connections dropped at a listening socket for any reason
+    pub listen_drops_per_sec: Option<f64>,
+    /// UDP counters cover IPv4 and IPv6
+    pub udp_no_ports_per_sec: Option<f64>,
+    pub udp_in_errors_per_sec: Option<f64>,
+    pub udp_rcvbuf_errors_per_sec: Option<f64>,
+    pub udp_sndbuf_errors_per_sec: Option<f64>,
+}
+
+/// Number of TCP sockets in each connection state
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct TcpStateCounts {
+    pub established: u64,
+    pub syn_sent: u64,
+    pub syn_recv: u64,
+    pub fin_wait1: u64,
+    pub fin_wait2: u64,
+    pub time_wait: u64,
+    pub close: u64,
+    pub close_wait: u64,
+    pub last_ack: u64,
+    pub listen: u64,
+    pub closing: u64,
+}
+
+/// Space usage of a mounted filesystem
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FilesystemUsage {
+    pub mount_point: String,
+    pub device: String,
+    pub fs_type: String,
+    pub total: i64,
+    pub used: i64,
+    pub available: i64,
+    /// Used space as a percentage of the space usable by non-root users (like `df`)
+    pub usage: f64,
+    /// Inode counts, `None` for filesystems that allocate inodes dynamically
+    pub inodes_total: Option<u64>,
+    pub inodes_used: Option<u64>,
+    pub inodes_free: Option<u64>,
+    /// Used inodes as a percentage of the total (like `df -i`)
+    pub inode_usage: Option<f64>,
+    /// Whether the filesystem is mounted read-only
+    #[serde(default)]
+    pub read_only: bool,
+    /// Whether the mount is usable; unless "ok", sizes are the last known
+    /// values, or zero if the mount never answered
+    #[serde(default)]
+    pub health: FilesystemHealth,
+}
+
+/// Health of a mounted filesystem
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilesystemHealth {
+    #[default]
+    Ok,
+    /// Was mounted read-write earlier and has since become read-only,
+    /// typically after the kernel hit I/O errors
+    RemountedReadOnly,
+    /// A network filesystem that did not answer in time
+    Stale,
+    /// statvfs failed
+    Error,
+}
+
+/// Resource usage of a single process
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProcessMetrics {
+    pub process_name: String,
+    pub pid: i32,
+    /// CPU usage as a percentage of one core (can exceed 100 on multi-core hosts)
+    pub cpu_percent: f64,
+    /// Resident memory in MiB
+    pub memory_mb: f64,
+    pub status: String,
+}
+
+/// Result of a process presence/count check
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProcessCheckResult {
+    /// Name of the check from the config
+    pub name: String,
+    pub passed: bool,
+    /// Number of matching processes
+    pub count: usize,
+    pub matched_pids: Vec<u32>,
+    pub min_count: usize,
+    pub max_count: Option<usize>,
+    /// Why the check failed
+    pub message: Option<String>,
+    /// Dashboard endpoint the result is also reported to as a health check
+    pub endpoint_id: Option<String>,
+    /// Time taken to evaluate the check (in milliseconds)
+    pub duration_ms: f64,
+}
+
+/// Inventory of services running on this server
+///
+/// Replaces the server's previous inventory for `service_types`: services of
+/// those types that are no longer listed are marked as stopped.
+#[derive(Debug, Serialize)]
+pub struct ServiceInventory {
+    pub service_types: Vec<ServiceType>,
+    pub services: Vec<ServiceInfo>,
+}
+
+/// A discovered service
+#[derive(Debug, Clone, Serialize)]
+pub struct ServiceInfo {
+    pub service_type: ServiceType,
+    pub name: String,
+    pub port: Option<u16>,
+    pub status: ServiceStatus,
+}
+
+/// Service types known to the backend (mirrors its `ServiceType` enum)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ServiceType {
+    Http,
+    Docker,
+    Database,
+    Process,
+}
+
+/// Service states known to the backend (mirrors its `ServiceStatus` enum)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ServiceStatus {
+    Running,
+    Stopped,
+    Error,
 }
 
 /// Health check payload for endpoint monitoring
@@ -63,6 +576,28 @@ pub struct HealthCheckPayload {
     pub is_up: bool,
     pub error_message: Option<String>,
     pub checked_from: String,
+    /// Records returned by a DNS check
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub answers: Vec<String>,
+    /// Round trip times and packet loss of a ping check
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ping: Option<PingStatistics>,
+}
+
+/// Round trip statistics of a ping check, like the summary `ping` prints
+///
+/// The round trip times are `None` when no reply was received.
+#[derive(Debug, Clone, Serialize)]
+pub struct PingStatistics {
+    pub packets_sent: u32,
+    pub packets_received: u32,
+    /// Lost packets (in percent)
+    pub packet_loss: f64,
+    pub rtt_min_ms: Option<f64>,
+    pub rtt_avg_ms: Option<f64>,
+    pub rtt_max_ms: Option<f64>,
+    /// Standard deviation of the round trip times
+    pub rtt_mdev_ms: Option<f64>,
 }
 
 /// Generic API response (reserved for future use)
@@ -0,0 +1,246 @@
+//! Durable on-disk spool for undelivered metrics
+//!
+//! When a metrics submission fails, the payload is written to the spool
+//! directory as one JSON file. Files are named after the collection time,
+//! so replaying them in name order preserves the original order. The spool
+//! is capped by total size and by age, dropping the oldest payloads first.
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use std::fs;
+use std::path::PathBuf;
+use tracing::{debug, warn};
+
+use crate::config::SpoolConfig;
+use crate::models::MetricsPayload;
+
+const EXTENSION: &str = "json";
+const TEMP_EXTENSION: &str = "tmp";
+
+/// Bounded directory of metrics payloads waiting to be replayed
+pub struct Spool {
+    dir: PathBuf,
+    max_bytes: u64,
+    max_age_ms: i64,
+    /// Disambiguates payloads collected within the same millisecond
+    counter: u32,
+}
+
+/// A payload waiting in the spool
+pub struct SpoolEntry {
+    path: PathBuf,
+    collected_at_ms: i64,
+    size: u64,
+}
+
+impl Spool {
+    /// Open (and create if needed) the spool directory
+    pub fn open(config: &SpoolConfig) -> Result<Self> {
+        fs::create_dir_all(&config.directory).with_context(|| {
+            format!("Failed to create spool directory: {}", config.directory.display())
+        })?;
+
+        // Leftovers from a write interrupted by a crash or power loss
+        for entry in fs::read_dir(&config.directory)?.flatten() {
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == TEMP_EXTENSION) {
+                fs::remove_file(&path).ok();
+            }
+        }
+
+        let spool = Self {
+            dir: config.directory.clone(),
+            max_bytes: config.max_size_mb * 1024 * 1024,
+            max_age_ms: config.max_age_hours as i64 * 3600 * 1000,
+            counter: 0,
+        };
+        spool.prune()?;
+        Ok(spool)
+    }
+
+    /// Add a payload to the end of the spool
+    pub fn push(&mut self, payload: &MetricsPayload) -> Result<()> {
+        let name = format!(
+            "{:020}-{:06}.{}",
+            payload.collected_at.timestamp_millis(),
+            self.counter,
+            EXTENSION
+        );
+        self.counter = (self.counter + 1) % 1_000_000;
+
+        let body = serde_json::to_vec(payload).context("Failed to serialize metrics")?;
+
+        // Write to a temp file first so a crash never leaves a truncated entry
+        let path = self.dir.join(&name);
+        let temp_path = path.with_extension(TEMP_EXTENSION);
+        fs::write(&temp_path, body)
+            .with_context(|| format!("Failed to write spool file: {}", temp_path.display()))?;
+        fs::rename(&temp_path, &path)
+            .with_context(|| format!("Failed to write spool file: {}", path.display()))?;
+
+        debug!("Spooled metrics to {}", path.display());
+        self.prune()
+    }
+
+    /// List spooled payloads, oldest first
+    pub fn entries(&self) -> Result<Vec<SpoolEntry>> {
+        let mut entries = Vec::new();
+
+        let dir = fs::read_dir(&self.dir)
+            .with_context(|| format!("Failed to read spool directory: {}", self.dir.display()))?;
+
+        for entry in dir.flatten() {
+            let path = entry.path();
+            if path.extension().is_none_or(|ext| ext != EXTENSION) {
+                continue;
+            }
+
+            // File names start with the collection time in milliseconds
+            let collected_at_ms = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.split('-').next())
+                .and_then(|millis| millis.parse::<i64>().ok());
+
+            let Some(collected_at_ms) = collected_at_ms else {
+                continue;
+            };
+
+            let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+            entries.push(SpoolEntry {
+                path,
+                collected_at_ms,
+                size,
+            });
+        }
+
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(entries)
+    }
+
+    /// Read a spooled payload
+    pub fn load(&self, entry: &SpoolEntry) -> Result<MetricsPayload> {
+        let body = fs::read(&entry.path)
+            .with_context(|| format!("Failed to read spool file: {}", entry.path.display()))?;
+        serde_json::from_slice(&body)
+            .with_context(|| format!("Corrupt spool file: {}", entry.path.display()))
+    }
+
+    /// Remove a payload from the spool
+    pub fn remove(&self, entry: &SpoolEntry) {
+        if let Err(e) = fs::remove_file(&entry.path) {
+            warn!("Failed to remove spool file {}: {}", entry.path.display(), e);
+        }
+    }
+
+    /// Drop payloads that are too old or don't fit in the size cap
+    fn prune(&self) -> Result<()> {
+        let cutoff = Utc::now().timestamp_millis() - self.max_age_ms;
+        let mut entries = self.entries()?;
+
+        let expired = entries.iter().take_while(|e| e.collected_at_ms < cutoff).count();
+        for entry in entries.drain(..expired) {
+            self.remove(&entry);
+        }
+        if expired > 0 {
+            warn!("Dropped {} spooled metrics older than the spool age limit", expired);
+        }
+
+        let mut total: u64 = entries.iter().map(|e| e.size).sum();
+        let mut dropped = 0;
+        for entry in &entries {
+            if total <= self.max_bytes {
+                break;
+            }
+            total -= entry.size;
+            self.remove(entry);
+            dropped += 1;
+        }
+        if dropped > 0 {
+            warn!("Dropped {} spooled metrics to stay under the spool size limit", dropped);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open_spool(dir: &std::path::Path) -> Spool {
+        Spool::open(&SpoolConfig {
+            enabled: true,
+            directory: dir.to_path_buf(),
+            max_size_mb: 1,
+            max_age_hours: 24,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn replays_payloads_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut spool = open_spool(dir.path());
+
+        for sequence in 0..3 {
+            let payload = MetricsPayload {
+                collected_at: Utc::now(),
+                sequence,
+                ..Default::default()
+            };
+            spool.push(&payload).unwrap();
+        }
+
+        let sequences: Vec<u64> = spool
+            .entries()
+            .unwrap()
+            .iter()
+            .map(|entry| spool.load(entry).unwrap().sequence)
+            .collect();
+        assert_eq!(sequences, [0, 1, 2]);
+    }
+
+    #[test]
+    fn loads_payloads_written_by_older_agents() {
+        let dir = tempfile::tempdir().unwrap();
+        let spool = open_spool(dir.path());
+
+        // A payload from before boot IDs, swap and filesystem health existed
+        let collected_at = Utc::now();
+        let body = serde_json::json!({
+            "collected_at": collected_at,
+            "cpu_usage": 12.5,
+            "cpu_count": 4,
+            "load_average_1m": null,
+            "load_average_5m": null,
+            "load_average_15m": null,
+            "memory_total": 1024,
+            "memory_used": 512,
+            "memory_usage": 50.0,
+            "disk_total": 2048,
+            "disk_used": 1024,
+            "disk_usage": 50.0,
+            "network_in": 1,
+            "network_out": 2,
+            "filesystems": [{
+                "mount_point": "/",
+                "device": "/dev/sda1",
+                "fs_type": "ext4",
+                "total": 2048,
+                "used": 1024,
+                "available": 1024,
+                "usage": 50.0
+            }]
+        });
+        let name = format!("{:020}-000000.{}", collected_at.timestamp_millis(), EXTENSION);
+        fs::write(dir.path().join(name), body.to_string()).unwrap();
+
+        let entries = spool.entries().unwrap();
+        let payload = spool.load(&entries[0]).unwrap();
+        assert_eq!(payload.cpu_usage, 12.5);
+        assert_eq!(payload.boot_id, "");
+        assert_eq!(payload.swap_total, 0);
+        assert_eq!(payload.filesystems[0].health, crate::models::FilesystemHealth::Ok);
+    }
+}
@@ -46,6 +46,12 @@ if [ -f /usr/local/bin/nubilus-agent ]; then
     rm -f /usr/local/bin/nubilus-agent.old 2>/dev/null || true
 fi
 
+# Remove spooled metrics
+if [ -d /var/lib/nubilus ]; then
+    info "Removing state directory..."
+    rm -rf /var/lib/nubilus
+fi
+
 # Ask about config
 read -p "Remove configuration files? [y/N] " -n 1 -r
 echo
@@ -0,0 +1,30 @@
+-- Services may have been recorded more than once before agents upserted them.
+-- Keep the most recently seen row of each service, with the earliest discovery time.
+UPDATE server_services s
+SET discovered_at = first.discovered_at
+FROM (
+  SELECT server_id, service_type, name, COALESCE(port, 0) AS port, MIN(discovered_at) AS discovered_at
+  FROM server_services
+  GROUP BY server_id, service_type, name, COALESCE(port, 0)
+  HAVING COUNT(*) > 1
+) first
+WHERE s.server_id = first.server_id
+  AND s.service_type = first.service_type
+  AND s.name = first.name
+  AND COALESCE(s.port, 0) = first.port;
+
+DELETE FROM server_services
+WHERE id IN (
+  SELECT id FROM (
+    SELECT id, ROW_NUMBER() OVER (
+      PARTITION BY server_id, service_type, name, COALESCE(port, 0)
+      ORDER BY last_seen_at DESC NULLS LAST, id
+    ) AS rank
+    FROM server_services
+  ) ranked
+  WHERE rank > 1
+);
+
+-- One row per service on a server so agents can upsert their service inventory
+CREATE UNIQUE INDEX IF NOT EXISTS idx_server_services_unique
+  ON server_services (server_id, service_type, name, COALESCE(port, 0));
@@ -0,0 +1,9 @@
+-- Agent boot ID and per-boot sequence number of each metrics payload
+ALTER TABLE server_metrics ADD COLUMN IF NOT EXISTS boot_id TEXT;
+ALTER TABLE server_metrics ADD COLUMN IF NOT EXISTS sequence BIGINT;
+
+-- A payload delivered twice (e.g. replayed from the agent's spool after a
+-- lost response) is stored once. Unique indexes on hypertables must include
+-- the time column, which is the same for both copies.
+CREATE UNIQUE INDEX IF NOT EXISTS idx_server_metrics_sequence
+  ON server_metrics (server_id, boot_id, sequence, time);
@@ -0,0 +1,21 @@
+-- Usage of every mounted filesystem reported by the agent
+CREATE TABLE IF NOT EXISTS filesystem_metrics (
+  time TIMESTAMPTZ NOT NULL,
+  server_id UUID NOT NULL REFERENCES servers(id) ON DELETE CASCADE,
+  mount_point TEXT NOT NULL,
+  device TEXT,
+  fs_type TEXT,
+  total BIGINT,                                           -- Bytes
+  used BIGINT,                                            -- Bytes
+  available BIGINT,                                       -- Bytes available to non-root users
+  usage DOUBLE PRECISION,                                 -- Percentage (0-100)
+  inodes_total BIGINT,
+  inodes_used BIGINT,
+  inodes_free BIGINT,
+  inode_usage DOUBLE PRECISION,                           -- Percentage (0-100)
+  read_only BOOLEAN,
+  health TEXT                                             -- 'ok', 'remounted_read_only', 'stale' or 'error'
+);
+SELECT create_hypertable('filesystem_metrics', 'time', if_not_exists => TRUE);
+CREATE INDEX IF NOT EXISTS idx_filesystem_metrics_server_time ON filesystem_metrics (server_id, time DESC);
+COMMENT ON TABLE filesystem_metrics IS 'Per-filesystem usage (TimescaleDB hypertable)';
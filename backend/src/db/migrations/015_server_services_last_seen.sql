@@ -0,0 +1,2 @@
+-- Services inserted by the agent's inventory sync are seen at insert time
+ALTER TABLE server_services ALTER COLUMN last_seen_at SET DEFAULT NOW();
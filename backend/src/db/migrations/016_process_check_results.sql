@@ -0,0 +1,16 @@
+-- Results of the process checks configured on the agent
+CREATE TABLE IF NOT EXISTS process_check_results (
+  time TIMESTAMPTZ NOT NULL,
+  server_id UUID NOT NULL REFERENCES servers(id) ON DELETE CASCADE,
+  name TEXT NOT NULL,                                     -- Check name from the agent config
+  passed BOOLEAN NOT NULL,
+  count INTEGER NOT NULL,                                 -- Matching processes
+  min_count INTEGER NOT NULL,
+  max_count INTEGER,
+  message TEXT,                                           -- Why the check failed
+  endpoint_id TEXT,                                       -- Endpoint the result is also reported to
+  duration_ms DOUBLE PRECISION
+);
+SELECT create_hypertable('process_check_results', 'time', if_not_exists => TRUE);
+CREATE INDEX IF NOT EXISTS idx_process_check_results_server_time ON process_check_results (server_id, time DESC);
+COMMENT ON TABLE process_check_results IS 'Agent process check results (TimescaleDB hypertable)';
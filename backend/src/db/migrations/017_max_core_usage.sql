@@ -0,0 +1,6 @@
+-- Usage of the busiest CPU core, which a single-threaded process can max out
+-- while the average over all cores stays low
+ALTER TABLE server_metrics ADD COLUMN IF NOT EXISTS max_core_usage DOUBLE PRECISION;
+
+-- Compare the CPU threshold with the busiest core instead of the average
+ALTER TABLE server_settings ADD COLUMN IF NOT EXISTS cpu_alert_on_max_core BOOLEAN DEFAULT false;
@@ -0,0 +1,2 @@
+-- Records returned by agent-side DNS checks
+ALTER TABLE health_checks ADD COLUMN IF NOT EXISTS answers TEXT[];
@@ -0,0 +1,8 @@
+-- Packet loss and round trip times of agent-side ping checks
+ALTER TABLE health_checks ADD COLUMN IF NOT EXISTS packets_sent INTEGER;
+ALTER TABLE health_checks ADD COLUMN IF NOT EXISTS packets_received INTEGER;
+ALTER TABLE health_checks ADD COLUMN IF NOT EXISTS packet_loss DOUBLE PRECISION;  -- Percentage (0-100)
+ALTER TABLE health_checks ADD COLUMN IF NOT EXISTS rtt_min_ms DOUBLE PRECISION;
+ALTER TABLE health_checks ADD COLUMN IF NOT EXISTS rtt_avg_ms DOUBLE PRECISION;
+ALTER TABLE health_checks ADD COLUMN IF NOT EXISTS rtt_max_ms DOUBLE PRECISION;
+ALTER TABLE health_checks ADD COLUMN IF NOT EXISTS rtt_mdev_ms DOUBLE PRECISION;
@@ -4,9 +4,11 @@ import { getEffectiveServerThresholds, isAlertsEnabled } from "./settings";
 import { addAlertNotification } from "../../queues";
 import { Alert } from "../../types/database";
 import { AlertSeverity, AlertTargetType } from "../../types/enums";
+import { OomKillEventInput, ProcessCheckResultInput } from "../../modules/ingest/ingest.types";
 
 interface MetricsData {
   cpu_usage?: number;
+  max_core_usage?: number | null;
   memory_usage?: number;
   disk_usage?: number;
   load_average_1m?: number;
@@ -46,6 +48,15 @@ function isWithinCooldown(lastAlertTime: Date | null, cooldownMinutes: number):
   return timeSinceLastAlert < cooldownMs;
 }
 
+/**
+ * CPU usage to compare with the CPU threshold: the busiest core's when the
+ * server alerts on it and the agent reports it, otherwise the average
+ */
+function cpuAlertUsage(metrics: MetricsData, onMaxCore: boolean): number | undefined {
+  if (onMaxCore && metrics.max_core_usage != null) return metrics.max_core_usage;
+  return metrics.cpu_usage;
+}
+
 /**
  * Get server info for alert messages
  */
@@ -79,6 +90,7 @@ export async function checkAndTriggerAlerts(
 
   // Get effective thresholds
   const thresholds = await getEffectiveServerThresholds(serverInfo.orgId, serverId);
+  const cpuUsage = cpuAlertUsage(metrics, thresholds.cpuOnMaxCore);
 
   // Define metric checks
   const checks: {
@@ -89,14 +101,11 @@ export async function checkAndTriggerAlerts(
     severity: AlertSeverity;
   }[] = [
     {
-      name: "CPU Usage",
-      value: metrics.cpu_usage,
+      name: thresholds.cpuOnMaxCore ? "CPU Usage (busiest core)" : "CPU Usage",
+      value: cpuUsage,
       threshold: thresholds.cpu,
       unit: "%",
-      severity:
-        metrics.cpu_usage && metrics.cpu_usage > 95
-          ? AlertSeverity.CRITICAL
-          : AlertSeverity.WARNING,
+      severity: cpuUsage && cpuUsage > 95 ? AlertSeverity.CRITICAL : AlertSeverity.WARNING,
     },
     {
       name: "Memory Usage",
@@ -175,6 +184,136 @@ export async function checkAndTriggerAlerts(
   return { alertsTriggered };
 }
 
+/**
+ * Alert when the kernel's OOM killer killed processes since the previous
+ * payload. Kills are events rather than levels, so these alerts are never
+ * auto-resolved.
+ */
+export async function checkOomKills(
+  serverId: string,
+  oomKills: number | null | undefined,
+  events: OomKillEventInput[] = []
+): Promise<AlertCheckResult> {
+  const alertsTriggered: string[] = [];
+
+  // The kill count is missing on kernels without the oom_kill counter
+  const kills = oomKills ?? events.length;
+  if (kills <= 0) return { alertsTriggered };
+
+  const serverInfo = await getServerInfo(serverId);
+  if (!serverInfo) return { alertsTriggered };
+
+  const alertsEnabled = await isAlertsEnabled("server", serverId);
+  if (!alertsEnabled) return { alertsTriggered };
+
+  const thresholds = await getEffectiveServerThresholds(serverInfo.orgId, serverId);
+  const lastAlertTime = await getLastAlertTime("server", serverId, "OOM Kill");
+  if (isWithinCooldown(lastAlertTime, thresholds.cooldown)) {
+    return { alertsTriggered };
+  }
+
+  const victims = events.slice(0, 5).map(event => {
+    const name = event.process_name ?? "unknown process";
+    const cgroup = event.cgroup && event.cgroup !== "/" ? ` in ${event.cgroup}` : "";
+    return `${name} (pid ${event.pid})${cgroup}`;
+  });
+  const title = `OOM Kill on ${serverInfo.name}`;
+  const message =
+    `The kernel killed ${kills} process${kills === 1 ? "" : "es"} after running out of memory` +
+    (victims.length ? `: ${victims.join(", ")}` : "");
+
+  const alert = await createAlert({
+    org_id: serverInfo.orgId,
+    severity: AlertSeverity.CRITICAL,
+    title,
+    message,
+    target_type: AlertTargetType.SERVER,
+    target_id: serverId,
+    metric_value: kills,
+  });
+  alertsTriggered.push(alert.id);
+
+  await addAlertNotification({
+    orgId: serverInfo.orgId,
+    alertId: alert.id,
+    serverId,
+    type: "alert_triggered",
+    title,
+    message,
+  });
+
+  return { alertsTriggered };
+}
+
+// Title prefix of process check alerts, which are resolved by checkProcessChecks
+const PROCESS_CHECK_ALERT = "Process Check Failed";
+
+/**
+ * Alert on failing process checks and resolve the alert once the check
+ * passes again. Checks with an endpoint are left to that endpoint's health
+ * checks.
+ */
+export async function checkProcessChecks(
+  serverId: string,
+  results: ProcessCheckResultInput[] = []
+): Promise<AlertCheckResult> {
+  const alertsTriggered: string[] = [];
+
+  const serverResults = results.filter(result => !result.endpoint_id);
+  if (serverResults.length === 0) return { alertsTriggered };
+
+  const serverInfo = await getServerInfo(serverId);
+  if (!serverInfo) return { alertsTriggered };
+
+  const thresholds = await getEffectiveServerThresholds(serverInfo.orgId, serverId);
+  const alertsEnabled = await isAlertsEnabled("server", serverId);
+
+  for (const result of serverResults) {
+    const title = `${PROCESS_CHECK_ALERT}: ${result.name} on ${serverInfo.name}`;
+
+    if (result.passed) {
+      await sql`
+        UPDATE alerts
+        SET status = 'resolved', resolved_at = NOW()
+        WHERE target_type = 'server'
+          AND target_id = ${serverId}::uuid
+          AND status = 'open'
+          AND title = ${title}
+      `;
+      continue;
+    }
+    if (!alertsEnabled) continue;
+
+    const lastAlertTime = await getLastAlertTime("server", serverId, title);
+    if (isWithinCooldown(lastAlertTime, thresholds.cooldown)) continue;
+
+    const message =
+      result.message ?? `${result.count} matching processes, expected at least ${result.min_count}`;
+
+    const alert = await createAlert({
+      org_id: serverInfo.orgId,
+      severity: result.count === 0 ? AlertSeverity.CRITICAL : AlertSeverity.WARNING,
+      title,
+      message,
+      target_type: AlertTargetType.SERVER,
+      target_id: serverId,
+      metric_value: result.count,
+    });
+    alertsTriggered.push(alert.id);
+
+    await addAlertNotification({
+      orgId: serverInfo.orgId,
+      alertId: alert.id,
+      serverId,
+      type: "alert_triggered",
+      title,
+      message,
+    });
+  }
+
+  return { alertsTriggered };
+}
+
 /**
  * Auto-resolve alerts when metrics return to normal
  */
@@ -183,6 +322,7 @@ export async function autoResolveAlerts(serverId: string, metrics: MetricsData):
   if (!serverInfo) return;
 
   const thresholds = await getEffectiveServerThresholds(serverInfo.orgId, serverId);
+  const cpuUsage = cpuAlertUsage(metrics, thresholds.cpuOnMaxCore);
 
   // Get only 'open' alerts for this server (preserve acknowledged alerts)
   const activeAlerts = await sql<Alert[]>`
@@ -194,11 +334,14 @@ export async function autoResolveAlerts(serverId: string, metrics: MetricsData):
   `;
 
   for (const alert of activeAlerts) {
+    // Check names are free text and may mention a metric
+    if (alert.title.startsWith(PROCESS_CHECK_ALERT)) continue;
+
     let shouldResolve = false;
 
     // Check if the metric that triggered this alert is now below threshold
-    if (alert.title.includes("CPU") && metrics.cpu_usage !== undefined) {
-      shouldResolve = metrics.cpu_usage <= thresholds.cpu;
+    if (alert.title.includes("CPU") && cpuUsage !== undefined) {
+      shouldResolve = cpuUsage <= thresholds.cpu;
     } else if (alert.title.includes("Memory") && metrics.memory_usage !== undefined) {
       shouldResolve = metrics.memory_usage <= thresholds.memory;
     } else if (alert.title.includes("Disk") && metrics.disk_usage !== undefined) {
@@ -1,6 +1,7 @@
 import sql from "..";
 import { Endpoint, HealthCheck } from "../../types/database";
 import { HttpMethod } from "../../types/enums";
+import { PingStatisticsInput } from "../../modules/ingest/ingest.types";
 
 export async function getEndpointsByOrgId(
   orgId: string
@@ -152,12 +153,32 @@ export async function insertHealthCheck(data: {
   is_up: boolean;
   error_message?: string | null;
   checked_from?: string | null;
+  answers?: string[] | null;
+  ping?: PingStatisticsInput | null;
 }): Promise<void> {
+  const ping = data.ping;
   await sql`
-    INSERT INTO health_checks (time, endpoint_id, status_code, response_time, is_up, error_message, checked_from)
-    VALUES (NOW(), ${data.endpoint_id}::uuid, ${data.status_code}, ${data.response_time}, ${
-    data.is_up
-  }, ${data.error_message ?? null}, ${data.checked_from ?? null})
+    INSERT INTO health_checks (
+      time, endpoint_id, status_code, response_time, is_up, error_message, checked_from, answers,
+      packets_sent, packets_received, packet_loss, rtt_min_ms, rtt_avg_ms, rtt_max_ms, rtt_mdev_ms
+    )
+    VALUES (
+      NOW(),
+      ${data.endpoint_id}::uuid,
+      ${data.status_code},
+      ${data.response_time},
+      ${data.is_up},
+      ${data.error_message ?? null},
+      ${data.checked_from ?? null},
+      ${data.answers?.length ? data.answers : null},
+      ${ping?.packets_sent ?? null},
+      ${ping?.packets_received ?? null},
+      ${ping?.packet_loss ?? null},
+      ${ping?.rtt_min_ms ?? null},
+      ${ping?.rtt_avg_ms ?? null},
+      ${ping?.rtt_max_ms ?? null},
+      ${ping?.rtt_mdev_ms ?? null}
+    )
   `;
 
   await sql`
@@ -1,9 +1,24 @@
 import sql from "..";
+import {
+  FilesystemUsageInput,
+  ProcessCheckResultInput,
+  ProcessMetricInput,
+  ServiceInput,
+} from "../../modules/ingest/ingest.types";
+import { ServiceStatus, ServiceType } from "../../types/enums";
 
+/**
+ * Store a metrics payload. Returns false when the payload was already stored,
+ * identified by the agent's boot ID and sequence number.
+ */
 export async function insertServerMetrics(
   serverId: string,
   data: {
+    collected_at?: string;
+    boot_id?: string;
+    sequence?: number;
     cpu_usage?: number;
+    max_core_usage?: number | null;
     cpu_count?: number;
     load_average_1m?: number;
     load_average_5m?: number;
@@ -20,19 +35,22 @@ export async function insertServerMetrics(
     network_in?: number;
     network_out?: number;
   }
-): Promise<void> {
-  await sql`
+): Promise<boolean> {
+  const inserted = await sql`
     INSERT INTO server_metrics (
-      time, server_id,
-      cpu_usage, cpu_count, load_average_1m, load_average_5m, load_average_15m,
+      time, server_id, boot_id, sequence,
+      cpu_usage, max_core_usage, cpu_count, load_average_1m, load_average_5m, load_average_15m,
       memory_usage, memory_total, memory_used, memory_available,
       disk_usage, disk_total, disk_used, disk_read_bytes, disk_write_bytes,
       network_in, network_out
     )
     VALUES (
-      NOW(),
+      COALESCE(${data.collected_at ?? null}::timestamptz, NOW()),
       ${serverId}::uuid,
+      ${data.boot_id || null},
+      ${data.sequence ?? null},
       ${data.cpu_usage ?? null},
+      ${data.max_core_usage ?? null},
       ${data.cpu_count ?? null},
       ${data.load_average_1m ?? null},
       ${data.load_average_5m ?? null},
@@ -49,5 +67,185 @@ export async function insertServerMetrics(
       ${data.network_in ?? null},
       ${data.network_out ?? null}
     )
+    ON CONFLICT (server_id, boot_id, sequence, time) DO NOTHING
+    RETURNING time
+  `;
+  return inserted.length > 0;
+}
+
+/**
+ * Sequence number of the payload collected before the given time in the same
+ * agent run, or null when there is none.
+ */
+export async function getPreviousSequence(
+  serverId: string,
+  bootId: string,
+  collectedAt: string
+): Promise<number | null> {
+  const [row] = await sql<{ sequence: string }[]>`
+    SELECT sequence FROM server_metrics
+    WHERE server_id = ${serverId}::uuid
+      AND boot_id = ${bootId}
+      AND time < ${collectedAt}::timestamptz
+    ORDER BY time DESC
+    LIMIT 1
+  `;
+  return row ? Number(row.sequence) : null;
+}
+
+export async function insertProcessMetrics(
+  serverId: string,
+  processes: ProcessMetricInput[],
+  collectedAt?: string
+): Promise<void> {
+  if (processes.length === 0) return;
+
+  const time = collectedAt ? new Date(collectedAt) : new Date();
+  const rows = processes.map(p => ({
+    time,
+    server_id: serverId,
+    process_name: p.process_name,
+    pid: p.pid,
+    cpu_percent: p.cpu_percent,
+    memory_mb: p.memory_mb,
+    status: p.status,
+  }));
+
+  await sql`
+    INSERT INTO process_metrics ${sql(
+      rows,
+      "time",
+      "server_id",
+      "process_name",
+      "pid",
+      "cpu_percent",
+      "memory_mb",
+      "status"
+    )}
+  `;
+}
+
+export async function insertFilesystemMetrics(
+  serverId: string,
+  filesystems: FilesystemUsageInput[],
+  collectedAt?: string
+): Promise<void> {
+  if (filesystems.length === 0) return;
+
+  const time = collectedAt ? new Date(collectedAt) : new Date();
+  const rows = filesystems.map(f => ({
+    time,
+    server_id: serverId,
+    mount_point: f.mount_point,
+    device: f.device,
+    fs_type: f.fs_type,
+    total: f.total ?? null,
+    used: f.used ?? null,
+    available: f.available ?? null,
+    usage: f.usage ?? null,
+    inodes_total: f.inodes_total ?? null,
+    inodes_used: f.inodes_used ?? null,
+    inodes_free: f.inodes_free ?? null,
+    inode_usage: f.inode_usage ?? null,
+    read_only: f.read_only ?? null,
+    health: f.health ?? null,
+  }));
+
+  await sql`
+    INSERT INTO filesystem_metrics ${sql(
+      rows,
+      "time",
+      "server_id",
+      "mount_point",
+      "device",
+      "fs_type",
+      "total",
+      "used",
+      "available",
+      "usage",
+      "inodes_total",
+      "inodes_used",
+      "inodes_free",
+      "inode_usage",
+      "read_only",
+      "health"
+    )}
+  `;
+}
+
+export async function insertProcessCheckResults(
+  serverId: string,
+  results: ProcessCheckResultInput[],
+  collectedAt?: string
+): Promise<void> {
+  if (results.length === 0) return;
+
+  const time = collectedAt ? new Date(collectedAt) : new Date();
+  const rows = results.map(r => ({
+    time,
+    server_id: serverId,
+    name: r.name,
+    passed: r.passed,
+    count: r.count,
+    min_count: r.min_count,
+    max_count: r.max_count ?? null,
+    message: r.message ?? null,
+    endpoint_id: r.endpoint_id ?? null,
+    duration_ms: r.duration_ms ?? null,
+  }));
+
+  await sql`
+    INSERT INTO process_check_results ${sql(
+      rows,
+      "time",
+      "server_id",
+      "name",
+      "passed",
+      "count",
+      "min_count",
+      "max_count",
+      "message",
+      "endpoint_id",
+      "duration_ms"
+    )}
   `;
 }
+
+/**
+ * Replace a server's service inventory for the given service types.
+ * Reported services are upserted as seen now; services of those types that
+ * were not reported are marked as stopped.
+ */
+export async function syncServerServices(
+  serverId: string,
+  serviceTypes: ServiceType[],
+  services: ServiceInput[]
+): Promise<void> {
+  await sql.begin(async tx => {
+    if (services.length > 0) {
+      const rows = services.map(s => ({
+        server_id: serverId,
+        service_type: s.service_type,
+        name: s.name,
+        port: s.port ?? null,
+        status: s.status,
+      }));
+
+      // New rows get last_seen_at = NOW() from the column default
+      await tx`
+        INSERT INTO server_services ${tx(rows, "server_id", "service_type", "name", "port", "status")}
+        ON CONFLICT (server_id, service_type, name, (COALESCE(port, 0)))
+        DO UPDATE SET status = EXCLUDED.status, last_seen_at = NOW()
+      `;
+    }
+
+    // NOW() is the transaction start time, so everything reported above has last_seen_at = NOW()
+    await tx`
+      UPDATE server_services SET status = ${ServiceStatus.STOPPED}
+      WHERE server_id = ${serverId}::uuid
+        AND service_type = ANY(${serviceTypes})
+        AND (last_seen_at IS NULL OR last_seen_at < NOW())
+        AND status <> ${ServiceStatus.STOPPED}
+    `;
+  });
+}
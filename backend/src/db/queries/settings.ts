@@ -4,6 +4,7 @@ export interface ServerSettings {
   id: string;
   server_id: string;
   cpu_threshold: number | null;
+  cpu_alert_on_max_core: boolean;
   memory_threshold: number | null;
   disk_threshold: number | null;
   load_threshold: number | null;
@@ -49,6 +50,7 @@ export async function updateServerSettings(
   serverId: string,
   updates: Partial<{
     cpu_threshold: number | null;
+    cpu_alert_on_max_core: boolean;
     memory_threshold: number | null;
     disk_threshold: number | null;
     load_threshold: number | null;
@@ -134,6 +136,7 @@ import { getOrgSettings, OrgSettings } from "./org";
 
 interface EffectiveThresholds {
   cpu: number;
+  cpuOnMaxCore: boolean;
   memory: number;
   disk: number;
   load: number;
@@ -154,6 +157,7 @@ export async function getEffectiveServerThresholds(
 
   return {
     cpu: serverSettings?.cpu_threshold ?? orgSettings?.default_cpu_threshold ?? 90.0,
+    cpuOnMaxCore: serverSettings?.cpu_alert_on_max_core ?? false,
     memory: serverSettings?.memory_threshold ?? orgSettings?.default_memory_threshold ?? 90.0,
     disk: serverSettings?.disk_threshold ?? orgSettings?.default_disk_threshold ?? 85.0,
     load: serverSettings?.load_threshold ?? orgSettings?.default_load_threshold ?? 10.0,
@@ -6,10 +6,31 @@ import {
   updateServerLastSeen,
   updateServerOnReconnect,
 } from "../../db/queries/servers";
-import { insertServerMetrics } from "../../db/queries/ingest";
+import {
+  getPreviousSequence,
+  insertFilesystemMetrics,
+  insertProcessCheckResults,
+  insertProcessMetrics,
+  insertServerMetrics,
+  syncServerServices,
+} from "../../db/queries/ingest";
 import { insertHealthCheck } from "../../db/queries/endpoints";
-import { checkAndTriggerAlerts, autoResolveAlerts } from "../../db/queries/alertTrigger";
-import { RegisterServerInput, SubmitHealthCheckInput, SubmitMetricsInput } from "./ingest.types";
+import {
+  autoResolveAlerts,
+  checkAndTriggerAlerts,
+  checkOomKills,
+  checkProcessChecks,
+} from "../../db/queries/alertTrigger";
+import {
+  RegisterServerInput,
+  SubmitHealthCheckInput,
+  SubmitMetricsInput,
+  SubmitServicesInput,
+} from "./ingest.types";
+import { ServiceStatus, ServiceType } from "../../types/enums";
+
+// Metrics collected longer ago than this don't update last seen or alerts
+const STALE_METRICS_MS = 5 * 60 * 1000;
 
 export async function registerServer(req: Request, res: Response) {
   const apiKey = req.apiKey;
@@ -46,21 +67,58 @@ export async function submitMetrics(req: Request, res: Response) {
 
   const data = req.body as SubmitMetricsInput;
 
-  // Store metrics
-  await insertServerMetrics(server.id, data);
+  // Store metrics; a payload that was already delivered is acknowledged again
+  // so the agent drops it, without being processed twice
+  const stored = await insertServerMetrics(server.id, data);
+  if (!stored) {
+    sendResponse(res, 200, "Metrics already recorded");
+    return;
+  }
+  reportSequenceGap(server.id, data).catch(err => console.error("Sequence check failed:", err));
+
+  if (data.processes?.length) {
+    await insertProcessMetrics(server.id, data.processes, data.collected_at);
+  }
+  if (data.filesystems?.length) {
+    await insertFilesystemMetrics(server.id, data.filesystems, data.collected_at);
+  }
+  if (data.process_checks?.length) {
+    await insertProcessCheckResults(server.id, data.process_checks, data.collected_at);
+  }
+
+  // Payloads replayed from the agent's spool are stored, but they don't say
+  // anything about the server's current state
+  const collectedAt = data.collected_at ? new Date(data.collected_at).getTime() : Date.now();
+  if (Date.now() - collectedAt > STALE_METRICS_MS) {
+    sendResponse(res, 200, "Metrics recorded");
+    return;
+  }
+
   await updateServerLastSeen(server.id);
 
   // Check thresholds and trigger alerts (non-blocking)
   checkAndTriggerAlerts(server.id, {
     cpu_usage: data.cpu_usage,
+    max_core_usage: data.max_core_usage,
     memory_usage: data.memory_usage,
     disk_usage: data.disk_usage,
     load_average_1m: data.load_average_1m,
   }).catch(err => console.error("Alert check failed:", err));
 
+  // Alert on processes killed by the OOM killer (non-blocking)
+  checkOomKills(server.id, data.oom_kills, data.oom_kill_events).catch(err =>
+    console.error("OOM alert check failed:", err)
+  );
+
+  // Alert on failing process checks, resolving them once they pass (non-blocking)
+  checkProcessChecks(server.id, data.process_checks).catch(err =>
+    console.error("Process check alert failed:", err)
+  );
+
   // Auto-resolve alerts when metrics return to normal (non-blocking)
   autoResolveAlerts(server.id, {
     cpu_usage: data.cpu_usage,
+    max_core_usage: data.max_core_usage,
     memory_usage: data.memory_usage,
     disk_usage: data.disk_usage,
     load_average_1m: data.load_average_1m,
@@ -69,6 +127,22 @@ export async function submitMetrics(req: Request, res: Response) {
   sendResponse(res, 200, "Metrics recorded");
 }
 
+/**
+ * Log payloads missing between this one and the one collected before it in
+ * the same agent run. They may still arrive later from the agent's spool.
+ */
+async function reportSequenceGap(serverId: string, data: SubmitMetricsInput) {
+  if (!data.boot_id || data.sequence == null || !data.collected_at) return;
+
+  const previous = await getPreviousSequence(serverId, data.boot_id, data.collected_at);
+  if (previous !== null && data.sequence - previous > 1) {
+    console.warn(
+      `Server ${serverId} is missing ${data.sequence - previous - 1} metrics payloads ` +
+        `between sequence ${previous} and ${data.sequence} (boot ${data.boot_id})`
+    );
+  }
+}
+
 export async function heartbeat(req: Request, res: Response) {
   const apiKey = req.apiKey;
   if (!apiKey) throw new AppError("API key required", 401);
@@ -94,7 +168,38 @@ export async function submitHealthCheck(req: Request, res: Response) {
     is_up: data.is_up,
     error_message: data.error_message,
     checked_from: data.checked_from,
+    answers: data.answers,
+    ping: data.ping,
   });
 
   sendResponse(res, 200, "Health check recorded");
 }
+
+export async function submitServices(req: Request, res: Response) {
+  const apiKey = req.apiKey;
+  if (!apiKey) throw new AppError("API key required", 401);
+
+  const server = await getServerByApiKeyId(apiKey.id);
+  if (!server) throw new AppError("Server not registered. Call /ingest/register first", 404);
+
+  const data = req.body as SubmitServicesInput;
+  const serviceTypes = Object.values(ServiceType) as string[];
+  const statuses = Object.values(ServiceStatus) as string[];
+
+  if (!Array.isArray(data.service_types) || !Array.isArray(data.services)) {
+    throw new AppError("service_types and services required", 400);
+  }
+  if (data.service_types.some(type => !serviceTypes.includes(type))) {
+    throw new AppError("Invalid service type", 400);
+  }
+  for (const service of data.services) {
+    if (!service.name?.trim()) throw new AppError("Service name required", 400);
+    if (!data.service_types.includes(service.service_type)) {
+      throw new AppError(`Service type ${service.service_type} not in service_types`, 400);
+    }
+    if (!statuses.includes(service.status)) throw new AppError("Invalid service status", 400);
+  }
+
+  await syncServerServices(server.id, data.service_types, data.services);
+  sendResponse(res, 200, "Services recorded");
+}
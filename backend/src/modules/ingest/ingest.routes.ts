@@ -1,6 +1,12 @@
 import { Router } from "express";
 import { authenticateApiKey } from "./ingest.middleware";
-import { heartbeat, registerServer, submitHealthCheck, submitMetrics } from "./ingest.controller";
+import {
+  heartbeat,
+  registerServer,
+  submitHealthCheck,
+  submitMetrics,
+  submitServices,
+} from "./ingest.controller";
 
 const router = Router();
 
@@ -8,5 +14,6 @@ router.post("/register", authenticateApiKey, registerServer);
 router.post("/metrics", authenticateApiKey, submitMetrics);
 router.post("/heartbeat", authenticateApiKey, heartbeat);
 router.post("/health", authenticateApiKey, submitHealthCheck);
+router.post("/services", authenticateApiKey, submitServices);
 
 export default router;
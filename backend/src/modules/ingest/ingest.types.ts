@@ -1,3 +1,5 @@
+import { ServiceStatus, ServiceType } from "../../types/enums";
+
 export interface RegisterServerInput {
   name: string;
   hostname?: string;
@@ -8,22 +10,306 @@ export interface RegisterServerInput {
 }
 
 export interface SubmitMetricsInput {
+  collected_at?: string;
+  boot_id?: string;
+  sequence?: number;
   cpu_usage?: number;
   cpu_count?: number;
   load_average_1m?: number;
   load_average_5m?: number;
   load_average_15m?: number;
+  cpu_user?: number | null;
+  cpu_nice?: number | null;
+  cpu_system?: number | null;
+  cpu_idle?: number | null;
+  cpu_iowait?: number | null;
+  cpu_irq?: number | null;
+  cpu_softirq?: number | null;
+  cpu_steal?: number | null;
+  cpu_guest?: number | null;
+  context_switches_per_sec?: number | null;
+  interrupts_per_sec?: number | null;
+  forks_per_sec?: number | null;
+  procs_running?: number | null;
+  procs_blocked?: number | null;
+  max_core_usage?: number | null;
+  cpu_cores?: CpuCoreMetricsInput[];
+  cpu_temperature?: number | null;
+  cpu_pressure?: ResourcePressureInput | null;
+  memory_pressure?: ResourcePressureInput | null;
+  io_pressure?: ResourcePressureInput | null;
   memory_usage?: number;
   memory_total?: number;
   memory_used?: number;
   memory_available?: number;
+  swap_total?: number;
+  swap_used?: number;
+  memory_cached?: number | null;
+  memory_buffers?: number | null;
+  memory_shared?: number | null;
+  memory_dirty?: number | null;
+  memory_writeback?: number | null;
+  memory_slab?: number | null;
+  memory_slab_reclaimable?: number | null;
+  hugepages_total?: number | null;
+  hugepages_free?: number | null;
+  hugepage_size?: number | null;
+  major_faults_per_sec?: number | null;
+  swap_in_pages_per_sec?: number | null;
+  swap_out_pages_per_sec?: number | null;
+  oom_kills?: number | null;
+  cgroup_oom_kills?: CgroupOomKillsInput[];
+  oom_kill_events?: OomKillEventInput[];
   disk_usage?: number;
   disk_total?: number;
   disk_used?: number;
   disk_read_bytes?: number;
   disk_write_bytes?: number;
+  filesystems?: FilesystemUsageInput[];
+  disk_devices?: DiskIoMetricsInput[];
+  disk_read_bytes_per_sec?: number | null;
+  disk_write_bytes_per_sec?: number | null;
   network_in?: number;
   network_out?: number;
+  network_interfaces?: NetworkInterfaceMetricsInput[];
+  network_in_bytes_per_sec?: number | null;
+  network_out_bytes_per_sec?: number | null;
+  sockets?: SocketMetricsInput | null;
+  cgroups?: CgroupMetricsInput[];
+  containers?: ContainerMetricsInput[];
+  container_events?: ContainerEventInput[];
+  systemd_units?: SystemdUnitMetricsInput[];
+  temperatures?: TemperatureSensorInput[];
+  fans?: FanSensorInput[];
+  processes?: ProcessMetricInput[];
+  process_checks?: ProcessCheckResultInput[];
+}
+
+export interface CpuCoreMetricsInput {
+  core: number;
+  usage: number;
+  frequency_mhz: number | null;
+}
+
+export interface PressureStallInput {
+  avg10: number;
+  avg60: number;
+  avg300: number;
+  total_us: number;
+}
+
+export interface ResourcePressureInput {
+  some: PressureStallInput;
+  full: PressureStallInput | null;
+}
+
+export interface OomKillEventInput {
+  time: string;
+  pid: number;
+  process_name: string | null;
+  cgroup: string | null;
+  constraint: string | null;
+}
+
+export interface CgroupOomKillsInput {
+  cgroup: string;
+  count: number;
+}
+
+export interface DiskIoMetricsInput {
+  device: string;
+  is_leaf: boolean;
+  reads_completed: number;
+  reads_merged: number;
+  read_bytes: number;
+  read_time_ms: number;
+  writes_completed: number;
+  writes_merged: number;
+  write_bytes: number;
+  write_time_ms: number;
+  in_flight: number;
+  io_time_ms: number;
+  read_iops: number | null;
+  write_iops: number | null;
+  read_bytes_per_sec: number | null;
+  write_bytes_per_sec: number | null;
+  read_await_ms: number | null;
+  write_await_ms: number | null;
+  utilization: number | null;
+}
+
+export interface NetworkInterfaceMetricsInput {
+  name: string;
+  operstate: string | null;
+  mtu: number | null;
+  speed_mbps: number | null;
+  rx_bytes: number;
+  tx_bytes: number;
+  rx_packets: number;
+  tx_packets: number;
+  rx_errors: number;
+  tx_errors: number;
+  rx_dropped: number | null;
+  tx_dropped: number | null;
+  rx_bytes_per_sec: number | null;
+  tx_bytes_per_sec: number | null;
+  rx_packets_per_sec: number | null;
+  tx_packets_per_sec: number | null;
+}
+
+export interface FilesystemUsageInput {
+  mount_point: string;
+  device: string;
+  fs_type: string;
+  total: number;
+  used: number;
+  available: number;
+  usage: number;
+  inodes_total: number | null;
+  inodes_used: number | null;
+  inodes_free: number | null;
+  inode_usage: number | null;
+  read_only: boolean;
+  health: "ok" | "remounted_read_only" | "stale" | "error";
+}
+
+export interface CgroupMetricsInput {
+  path: string;
+  cpu_usage_percent: number | null;
+  cpu_user_percent: number | null;
+  cpu_system_percent: number | null;
+  cpu_throttled_periods_percent: number | null;
+  cpu_throttled_percent: number | null;
+  nr_throttled: number | null;
+  memory_current: number | null;
+  memory_max: number | null;
+  memory_usage: number | null;
+  io_read_bytes_per_sec: number | null;
+  io_write_bytes_per_sec: number | null;
+  io_read_iops: number | null;
+  io_write_iops: number | null;
+  pids_current: number | null;
+  pids_max: number | null;
+}
+
+export interface ContainerMetricsInput {
+  id: string;
+  name: string;
+  image: string;
+  state: string;
+  status: string;
+  restart_count: number | null;
+  health: string | null;
+  exit_code: number | null;
+  started_at: string | null;
+  cpu_usage_percent: number | null;
+  memory_usage_bytes: number | null;
+  memory_limit: number | null;
+  memory_usage: number | null;
+  network_rx_bytes_per_sec: number | null;
+  network_tx_bytes_per_sec: number | null;
+  block_read_bytes_per_sec: number | null;
+  block_write_bytes_per_sec: number | null;
+  pids: number | null;
+}
+
+export interface ContainerEventInput {
+  time: string;
+  container_id: string;
+  name: string;
+  image: string | null;
+  action: "start" | "stop" | "die";
+  exit_code: number | null;
+}
+
+export interface SystemdUnitMetricsInput {
+  name: string;
+  description: string;
+  load_state: string;
+  active_state: string;
+  sub_state: string;
+  restarts: number | null;
+  state_changed_at: string | null;
+}
+
+export interface TemperatureSensorInput {
+  chip: string;
+  device: string | null;
+  label: string;
+  celsius: number;
+  max: number | null;
+  critical: number | null;
+}
+
+export interface FanSensorInput {
+  chip: string;
+  device: string | null;
+  label: string;
+  rpm: number;
+}
+
+export interface SocketMetricsInput {
+  sockets_used: number | null;
+  tcp_inuse: number | null;
+  tcp6_inuse: number | null;
+  tcp_orphan: number | null;
+  tcp_time_wait: number | null;
+  tcp_alloc: number | null;
+  tcp_mem_pages: number | null;
+  udp_inuse: number | null;
+  udp6_inuse: number | null;
+  udp_mem_pages: number | null;
+  tcp_states: TcpStateCountsInput;
+  tcp_active_opens_per_sec: number | null;
+  tcp_passive_opens_per_sec: number | null;
+  tcp_attempt_fails_per_sec: number | null;
+  tcp_estab_resets_per_sec: number | null;
+  tcp_out_segs_per_sec: number | null;
+  tcp_retransmits_per_sec: number | null;
+  tcp_retransmit_ratio: number | null;
+  tcp_in_errors_per_sec: number | null;
+  tcp_resets_sent_per_sec: number | null;
+  tcp_timeouts_per_sec: number | null;
+  listen_overflows_per_sec: number | null;
+  listen_drops_per_sec: number | null;
+  udp_no_ports_per_sec: number | null;
+  udp_in_errors_per_sec: number | null;
+  udp_rcvbuf_errors_per_sec: number | null;
+  udp_sndbuf_errors_per_sec: number | null;
+}
+
+export interface TcpStateCountsInput {
+  established: number;
+  syn_sent: number;
+  syn_recv: number;
+  fin_wait1: number;
+  fin_wait2: number;
+  time_wait: number;
+  close: number;
+  close_wait: number;
+  last_ack: number;
+  listen: number;
+  closing: number;
+}
+
+export interface ProcessMetricInput {
+  process_name: string;
+  pid: number;
+  cpu_percent: number;
+  memory_mb: number;
+  status: string;
+}
+
+export interface ProcessCheckResultInput {
+  name: string;
+  passed: boolean;
+  count: number;
+  matched_pids: number[];
+  min_count: number;
+  max_count: number | null;
+  message: string | null;
+  endpoint_id: string | null;
+  duration_ms: number;
 }
 
 export interface SubmitHealthCheckInput {
@@ -33,4 +319,28 @@ export interface SubmitHealthCheckInput {
   is_up: boolean;
   error_message?: string;
   checked_from?: string;
+  answers?: string[];
+  ping?: PingStatisticsInput;
+}
+
+export interface PingStatisticsInput {
+  packets_sent: number;
+  packets_received: number;
+  packet_loss: number;
+  rtt_min_ms: number | null;
+  rtt_avg_ms: number | null;
+  rtt_max_ms: number | null;
+  rtt_mdev_ms: number | null;
+}
+
+export interface SubmitServicesInput {
+  service_types: ServiceType[];
+  services: ServiceInput[];
+}
+
+export interface ServiceInput {
+  service_type: ServiceType;
+  name: string;
+  port: number | null;
+  status: ServiceStatus;
 }
@@ -24,6 +24,7 @@ export interface ServerMetricsQuery {
 
 export interface UpdateServerSettingsInput {
   cpu_threshold?: number | null;
+  cpu_alert_on_max_core?: boolean;
   memory_threshold?: number | null;
   disk_threshold?: number | null;
   load_threshold?: number | null;
@@ -135,6 +135,14 @@ export interface HealthCheck {
   is_up: boolean | null;
   error_message: string | null;
   checked_from: string | null;
+  answers: string[] | null;
+  packets_sent: number | null;
+  packets_received: number | null;
+  packet_loss: number | null;
+  rtt_min_ms: number | null;
+  rtt_avg_ms: number | null;
+  rtt_max_ms: number | null;
+  rtt_mdev_ms: number | null;
 }
 
 export interface ServerMetric {